@@ -0,0 +1,364 @@
+use std::path::PathBuf;
+
+use syn::{Error, LitStr, Result};
+
+use crate::Column;
+
+/// Compare the columns of a schema with the `CREATE TABLE` statement of its table
+///
+/// `path` is a SQL file or a directory of them, read in file name order like `migrations/`.
+/// Returns the files that were read, relative to the crate root.
+pub(crate) fn check(
+    path: &LitStr,
+    table: &LitStr,
+    columns: &[Column],
+    soft_delete: bool,
+) -> Result<Vec<String>> {
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    let read_error = |file: &PathBuf, e: std::io::Error| {
+        Error::new(
+            path.span(),
+            format!("Failed to read {}: {}", file.display(), e),
+        )
+    };
+
+    let target = root.join(path.value());
+    let files = match target.is_dir() {
+        true => {
+            let mut files = (std::fs::read_dir(&target).map_err(|e| read_error(&target, e))?)
+                .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+                .filter_map(|name| name.into_string().ok())
+                .filter(|name| name.ends_with(".sql"))
+                .map(|name| format!("{}/{}", path.value().trim_end_matches('/'), name))
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        }
+        false => vec![path.value()],
+    };
+    let mut sql = String::new();
+    for file in files.iter() {
+        let file = root.join(file);
+        sql.push_str(&std::fs::read_to_string(&file).map_err(|e| read_error(&file, e))?);
+        sql.push('\n');
+    }
+
+    let Some(ddl_columns) = table_columns(&sql, &table.value()) else {
+        return Err(Error::new(
+            table.span(),
+            format!(
+                "No `CREATE TABLE {}` found in {}",
+                table.value(),
+                path.value()
+            ),
+        ));
+    };
+
+    if soft_delete
+        && !ddl_columns
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case("deleted_at"))
+    {
+        return Err(Error::new(
+            table.span(),
+            format!(
+                "`soft_delete` requires a `deleted_at` column in table `{}` ({})",
+                table.value(),
+                path.value()
+            ),
+        ));
+    }
+
+    let mut errors = (columns.iter())
+        .filter(|column| {
+            !ddl_columns
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&column.name))
+        })
+        .map(|column| {
+            Error::new(
+                column.span,
+                format!(
+                    "Column `{}` does not exist in table `{}` ({}), which has: {}",
+                    column.name,
+                    table.value(),
+                    path.value(),
+                    ddl_columns.join(", ")
+                ),
+            )
+        });
+
+    match errors.next() {
+        Some(mut first) => {
+            errors.for_each(|e| first.combine(e));
+            Err(first)
+        }
+        None => Ok(files),
+    }
+}
+
+/// Column names of the last `CREATE TABLE <table>` in `sql`, the one that wins when applied in order,
+/// followed by the columns later `ALTER TABLE <table> ADD COLUMN` statements add to it
+pub(crate) fn table_columns(sql: &str, table: &str) -> Option<Vec<String>> {
+    let sql = strip_comments(sql);
+    let lower = sql.to_ascii_lowercase();
+
+    let mut found = None;
+    let mut search = 0;
+    while let Some(offset) = lower[search..].find("create table") {
+        let start = search + offset + "create table".len();
+        search = start;
+
+        let rest = lower[start..].trim_start();
+        let rest = rest
+            .strip_prefix("if not exists")
+            .unwrap_or(rest)
+            .trim_start();
+        let name_len = rest
+            .find(|c: char| c == '(' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']');
+        if name != table.to_ascii_lowercase() {
+            continue;
+        }
+
+        let body_start = start + lower[start..].find('(')? + 1;
+        let mut depth = 1;
+        let mut body_end = body_start;
+        for (i, c) in sql[body_start..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                body_end = body_start + i;
+                break;
+            }
+        }
+        found = Some((body_end, columns_of(&sql[body_start..body_end])));
+    }
+
+    let (created_at, mut columns) = found?;
+    columns.extend(added_columns(&sql[created_at..], table));
+    Some(columns)
+}
+
+/// Columns added to `table` by the `ALTER TABLE ... ADD [COLUMN]` statements in `sql`
+fn added_columns(sql: &str, table: &str) -> Vec<String> {
+    let table = table.to_ascii_lowercase();
+    let mut columns = Vec::new();
+    for statement in sql.split(';') {
+        let mut words = statement
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']'));
+        let mut next_is =
+            |expected: &str| (words.next()).is_some_and(|word| word.eq_ignore_ascii_case(expected));
+        if !(next_is("alter") && next_is("table") && next_is(&table) && next_is("add")) {
+            continue;
+        }
+        let name = match words.next() {
+            Some(word) if word.eq_ignore_ascii_case("column") => words.next(),
+            word => word,
+        };
+        columns.extend(name.map(String::from));
+    }
+    columns
+}
+
+/// Split a table body on top level commas and keep the names of column definitions
+fn columns_of(body: &str) -> Vec<String> {
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in body.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                definitions.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    definitions.push(current);
+
+    const CONSTRAINTS: [&str; 5] = ["foreign", "primary", "unique", "check", "constraint"];
+    (definitions.iter())
+        .filter_map(|definition| definition.split_whitespace().next())
+        .filter(|first| !CONSTRAINTS.contains(&first.to_ascii_lowercase().as_str()))
+        .map(|first| {
+            first
+                .trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']')
+                .to_string()
+        })
+        .collect()
+}
+
+fn strip_comments(sql: &str) -> String {
+    (sql.lines())
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+    use syn::{Ident, parse_quote};
+
+    use super::*;
+
+    fn column(name: &str) -> Column {
+        Column {
+            field: Ident::new(name, Span::call_site()),
+            ty: parse_quote!(String),
+            name: name.to_string(),
+            variant: Ident::new(&crate::upper_camel_case(name), Span::call_site()),
+            generated: false,
+            span: Span::call_site(),
+        }
+    }
+
+    fn lit(value: &str) -> LitStr {
+        LitStr::new(value, Span::call_site())
+    }
+
+    /// A directory of SQL files under the system temp dir, removed when dropped
+    struct SqlDir(PathBuf);
+
+    impl SqlDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("schema-derive-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            for (file, sql) in files {
+                std::fs::write(dir.join(file), sql).unwrap();
+            }
+            Self(dir)
+        }
+
+        fn path(&self) -> LitStr {
+            lit(&self.0.display().to_string())
+        }
+    }
+
+    impl Drop for SqlDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn columns_of_a_create_table() {
+        let sql = "CREATE TABLE IF NOT EXISTS guilds (
+            id TEXT PRIMARY KEY, -- Guild ID, (with a comma)
+            enabled BOOLEAN NOT NULL DEFAULT 1 CHECK(enabled IN (0, 1)),
+            \"shard_id\" INTEGER NOT NULL,
+            FOREIGN KEY (shard_id) REFERENCES shards(id) ON DELETE CASCADE,
+            PRIMARY KEY (id),
+            UNIQUE (id, shard_id)
+        );";
+        assert_eq!(
+            table_columns(sql, "guilds").unwrap(),
+            ["id", "enabled", "shard_id"]
+        );
+    }
+
+    #[test]
+    fn only_the_requested_table() {
+        let sql = "CREATE TABLE guild_members (user_id TEXT);\nCREATE TABLE guilds (id TEXT);";
+        assert_eq!(table_columns(sql, "guilds").unwrap(), ["id"]);
+        assert_eq!(table_columns(sql, "GUILDS").unwrap(), ["id"]);
+        assert!(table_columns(sql, "guild").is_none());
+    }
+
+    #[test]
+    fn the_last_create_table_wins() {
+        let sql = "CREATE TABLE t (a TEXT);\nDROP TABLE t;\nCREATE TABLE t (b TEXT);";
+        assert_eq!(table_columns(sql, "t").unwrap(), ["b"]);
+    }
+
+    #[test]
+    fn alter_table_add_column() {
+        let sql = "CREATE TABLE t (a TEXT);
+            ALTER TABLE t ADD COLUMN b TEXT DEFAULT NULL;
+            ALTER TABLE \"t\" add c INTEGER;
+            ALTER TABLE other ADD COLUMN d TEXT;
+            ALTER TABLE t RENAME COLUMN a TO e;";
+        assert_eq!(table_columns(sql, "t").unwrap(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn columns_added_before_a_rebuild_are_dropped() {
+        let sql = "CREATE TABLE t (a TEXT);
+            ALTER TABLE t ADD COLUMN b TEXT;
+            CREATE TABLE t (a TEXT, c TEXT);
+            ALTER TABLE t ADD COLUMN d TEXT;";
+        assert_eq!(table_columns(sql, "t").unwrap(), ["a", "c", "d"]);
+    }
+
+    #[test]
+    fn check_reads_a_directory_in_file_name_order() {
+        let dir = SqlDir::new(
+            "order",
+            &[
+                ("0002_add.sql", "ALTER TABLE t ADD COLUMN b TEXT;"),
+                ("0001_create.sql", "CREATE TABLE t (a TEXT);"),
+                ("README.md", "CREATE TABLE t (ignored TEXT);"),
+            ],
+        );
+        let files = check(&dir.path(), &lit("t"), &[column("a"), column("b")], false).unwrap();
+        let names: Vec<_> = (files.iter())
+            .map(|file| file.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(names, ["0001_create.sql", "0002_add.sql"]);
+    }
+
+    #[test]
+    fn check_reports_unknown_columns() {
+        let dir = SqlDir::new("unknown", &[("0001.sql", "CREATE TABLE t (a TEXT);")]);
+        let error = check(&dir.path(), &lit("t"), &[column("a"), column("b")], false)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Column `b` does not exist in table `t`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn check_requires_deleted_at_for_soft_delete() {
+        let dir = SqlDir::new(
+            "soft_delete",
+            &[
+                ("0001.sql", "CREATE TABLE t (a TEXT);"),
+                ("0002.sql", "ALTER TABLE t ADD COLUMN deleted_at TIMESTAMP;"),
+            ],
+        );
+        assert!(check(&dir.path(), &lit("t"), &[column("a")], true).is_ok());
+
+        let dir = SqlDir::new(
+            "no_soft_delete",
+            &[("0001.sql", "CREATE TABLE t (a TEXT);")],
+        );
+        assert!(check(&dir.path(), &lit("t"), &[column("a")], true).is_err());
+    }
+
+    #[test]
+    fn check_reports_a_missing_table() {
+        let dir = SqlDir::new("missing", &[("0001.sql", "CREATE TABLE t (a TEXT);")]);
+        assert!(check(&dir.path(), &lit("u"), &[column("a")], false).is_err());
+    }
+
+    #[test]
+    fn check_reads_a_single_file() {
+        let dir = SqlDir::new("file", &[("schema.sql", "CREATE TABLE t (a TEXT);")]);
+        let file = lit(&dir.0.join("schema.sql").display().to_string());
+        let files = check(&file, &lit("t"), &[column("a")], false).unwrap();
+        assert_eq!(files, [file.value()]);
+    }
+}
@@ -0,0 +1,390 @@
+//! `#[derive(Schema)]` generates the sea-query boilerplate of a `*Schema` struct from its fields
+//!
+//! ```ignore
+//! #[derive(Schema)]
+//! #[schema(table = "timed_messages", iden = TimedMessages, key(id), stream, ddl = "migrations")]
+//! pub struct TimedMessagesSchema {
+//!     #[schema(generated)]
+//!     pub id: i64,
+//!     pub guild_id: String,
+//!     ...
+//! }
+//! ```
+//!
+//! Struct attributes:
+//! - `table = "..."`: SQL table name (required)
+//! - `iden = Name`: name of the generated `Iden` enum (required)
+//! - `key(a, b)`: primary key fields, used by the `*_by_key` builders, upserts and streams
+//! - `stream`: implement `StreamableSchema` with keyset pagination on the key
+//! - `soft_delete`: the table has a `deleted_at` column, selects skip soft deleted rows
+//! - `ddl = "path"`: check at compile time that every column exists in that table's `CREATE TABLE`
+//!   (and later `ALTER TABLE ... ADD COLUMN`), in a SQL file or a directory of them like `migrations`
+//!
+//! Field attributes:
+//! - `column = "..."`: column name when it differs from the field name
+//! - `generated`: filled by the database (AUTOINCREMENT ids, `DEFAULT CURRENT_TIMESTAMP`), never inserted
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Error, Fields, Ident, LitStr, Result, Type, Visibility, parse_macro_input,
+};
+
+mod ddl;
+
+#[proc_macro_derive(Schema, attributes(schema))]
+pub fn derive_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct SchemaAttrs {
+    table: LitStr,
+    iden: Ident,
+    key: Vec<Ident>,
+    stream: bool,
+    soft_delete: bool,
+    ddl: Option<LitStr>,
+}
+
+struct Column {
+    field: Ident,
+    ty: Type,
+    name: String,
+    variant: Ident,
+    generated: bool,
+    span: Span,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let attrs = parse_struct_attrs(&input)?;
+    let columns = parse_columns(&input)?;
+
+    let key_columns = (attrs.key.iter())
+        .map(|key| {
+            (columns.iter())
+                .find(|c| c.field == *key)
+                .ok_or_else(|| Error::new(key.span(), format!("No field named `{}`", key)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Referencing the SQL files makes cargo rebuild the schema when they change
+    let ddl_dependency = match &attrs.ddl {
+        Some(path) => {
+            let files = ddl::check(path, &attrs.table, &columns, attrs.soft_delete)?;
+            Some(quote! {
+                #(const _: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #files));)*
+            })
+        }
+        None => None,
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let iden = &attrs.iden;
+    let table = &attrs.table;
+
+    let iden_enum = iden_enum(vis, iden, table, &columns);
+    let variants: Vec<&Ident> = columns.iter().map(|c| &c.variant).collect();
+    let key_variants: Vec<&Ident> = key_columns.iter().map(|c| &c.variant).collect();
+    let key_fields: Vec<&Ident> = key_columns.iter().map(|c| &c.field).collect();
+
+    let insertable: Vec<&Column> = columns.iter().filter(|c| !c.generated).collect();
+    let insert_variants: Vec<&Ident> = insertable.iter().map(|c| &c.variant).collect();
+    let insert_fields: Vec<&Ident> = insertable.iter().map(|c| &c.field).collect();
+
+    let on_conflict = on_conflict(iden, &key_columns, &insertable);
+
+    let live_filter = attrs
+        .soft_delete
+        .then(|| quote!(.and_where(crate::schema::SoftDelete::live(#iden::Table))));
+
+    let by_key = (!key_columns.is_empty()).then(|| {
+        quote! {
+            /// Every column of the row with this primary key
+            pub fn select_by_key(#(#key_fields: impl Into<sea_query::Value>),*) -> sea_query::SelectStatement {
+                let mut query = Self::select_all();
+                #(query.and_where(sea_query::Expr::col(#iden::#key_variants).eq(Into::<sea_query::Value>::into(#key_fields)));)*
+                query
+            }
+
+            /// Delete the row with this primary key, returning it
+            pub fn delete_by_key(#(#key_fields: impl Into<sea_query::Value>),*) -> sea_query::DeleteStatement {
+                sea_query::Query::delete()
+                    .from_table(#iden::Table)
+                    #(.and_where(sea_query::Expr::col(#iden::#key_variants).eq(Into::<sea_query::Value>::into(#key_fields))))*
+                    .returning_all()
+                    .to_owned()
+            }
+        }
+    });
+
+    let stream = if attrs.stream {
+        if key_columns.is_empty() {
+            return Err(Error::new(
+                Span::call_site(),
+                "`stream` requires a `key(...)` to paginate on",
+            ));
+        }
+        Some(stream_impl(name, iden, &key_columns))
+    } else {
+        None
+    };
+
+    Ok(quote! {
+        #ddl_dependency
+
+        #iden_enum
+
+        impl #iden {
+            /// Every column, in field order
+            pub fn all_columns() -> Vec<Self> {
+                vec![#(#iden::#variants),*]
+            }
+
+            /// Primary key columns
+            pub fn key_columns() -> Vec<Self> {
+                vec![#(#iden::#key_variants),*]
+            }
+        }
+
+        impl #name {
+            /// Insert this row, updating every non key column when the key already exists
+            pub fn upsert(&self) -> sea_query::InsertStatement {
+                sea_query::Query::insert()
+                    .into_table(#iden::Table)
+                    .columns([#(#iden::#insert_variants),*])
+                    .values_panic([#(self.#insert_fields.clone().into()),*])
+                    #on_conflict
+                    .returning_all()
+                    .to_owned()
+            }
+
+            /// Every column of every row
+            pub fn select_all() -> sea_query::SelectStatement {
+                sea_query::Query::select()
+                    .from(#iden::Table)
+                    .columns(#iden::all_columns())
+                    #live_filter
+                    .to_owned()
+            }
+
+            #by_key
+        }
+
+        #stream
+    })
+}
+
+fn iden_enum(vis: &Visibility, iden: &Ident, table: &LitStr, columns: &[Column]) -> TokenStream2 {
+    let variants = columns.iter().map(|c| {
+        let variant = &c.variant;
+        let name = &c.name;
+        quote!(#[iden = #name] #variant)
+    });
+    quote! {
+        #[derive(sea_query::Iden, Debug, Clone, Copy, PartialEq, Eq)]
+        #vis enum #iden {
+            #[iden = #table]
+            Table,
+            #(#variants),*
+        }
+    }
+}
+
+/// Upserts only make sense when the key is supplied by us, not generated by the database
+fn on_conflict(iden: &Ident, key: &[&Column], insertable: &[&Column]) -> Option<TokenStream2> {
+    if key.is_empty() || key.iter().any(|c| c.generated) {
+        return None;
+    }
+    let key_variants = key.iter().map(|c| &c.variant);
+    let updates: Vec<&Ident> = (insertable.iter())
+        .filter(|c| !key.iter().any(|k| k.field == c.field))
+        .map(|c| &c.variant)
+        .collect();
+    let action = if updates.is_empty() {
+        quote!(.do_nothing())
+    } else {
+        quote!(.update_columns([#(#iden::#updates),*]))
+    };
+    Some(quote! {
+        .on_conflict(
+            sea_query::OnConflict::columns([#(#iden::#key_variants),*])
+                #action
+                .to_owned(),
+        )
+    })
+}
+
+fn stream_impl(name: &Ident, iden: &Ident, key: &[&Column]) -> TokenStream2 {
+    let variants: Vec<&Ident> = key.iter().map(|c| &c.variant).collect();
+    let fields: Vec<&Ident> = key.iter().map(|c| &c.field).collect();
+    let types: Vec<&Type> = key.iter().map(|c| &c.ty).collect();
+
+    let (cursor_type, cursor_value, after_condition) = if let [column] = key {
+        let (variant, field, ty) = (&column.variant, &column.field, &column.ty);
+        (
+            quote!(#ty),
+            quote!(self.#field.clone()),
+            quote!(sea_query::Expr::col(#iden::#variant).gt(after.clone())),
+        )
+    } else {
+        let bindings: Vec<Ident> = (0..key.len()).map(|i| format_ident!("key_{}", i)).collect();
+        (
+            quote!((#(#types),*)),
+            quote!((#(self.#fields.clone()),*)),
+            quote!({
+                let (#(#bindings),*) = after;
+                sea_query::Expr::tuple([#(sea_query::Expr::col(#iden::#variants).into()),*])
+                    .gt(sea_query::Expr::tuple([#(sea_query::Expr::val(#bindings.clone()).into()),*]))
+            }),
+        )
+    };
+
+    quote! {
+        impl crate::services::streaming::StreamableSchema for #name {
+            type Cursor = #cursor_type;
+
+            fn batch_after(batch_size: u64, after: Option<&Self::Cursor>) -> sea_query::SelectStatement {
+                let mut query = Self::select_all();
+                #(query.order_by(#iden::#variants, sea_query::Order::Asc);)*
+                query.limit(batch_size);
+                if let Some(after) = after {
+                    query.and_where(#after_condition);
+                }
+                query
+            }
+
+            fn cursor(&self) -> Self::Cursor {
+                #cursor_value
+            }
+        }
+    }
+}
+
+fn parse_struct_attrs(input: &DeriveInput) -> Result<SchemaAttrs> {
+    let mut table = None;
+    let mut iden = None;
+    let mut key = Vec::new();
+    let mut stream = false;
+    let mut soft_delete = false;
+    let mut ddl = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("schema")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("iden") {
+                iden = Some(meta.value()?.parse::<Ident>()?);
+            } else if meta.path.is_ident("key") {
+                meta.parse_nested_meta(|field| {
+                    key.push(field.path.require_ident()?.clone());
+                    Ok(())
+                })?;
+            } else if meta.path.is_ident("stream") {
+                stream = true;
+            } else if meta.path.is_ident("soft_delete") {
+                soft_delete = true;
+            } else if meta.path.is_ident("ddl") {
+                ddl = Some(meta.value()?.parse::<LitStr>()?);
+            } else {
+                return Err(meta.error("Unknown schema attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(SchemaAttrs {
+        table: table
+            .ok_or_else(|| Error::new(Span::call_site(), "Missing #[schema(table = \"...\")]"))?,
+        iden: iden
+            .ok_or_else(|| Error::new(Span::call_site(), "Missing #[schema(iden = Name)]"))?,
+        key,
+        stream,
+        soft_delete,
+        ddl,
+    })
+}
+
+fn parse_columns(input: &DeriveInput) -> Result<Vec<Column>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "Schema can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            Span::call_site(),
+            "Schema requires named fields",
+        ));
+    };
+
+    let mut columns = Vec::with_capacity(fields.named.len());
+    for field in fields.named.iter() {
+        let Some(ident) = field.ident.clone() else {
+            continue;
+        };
+        let mut name = ident.to_string();
+        let mut generated = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("schema")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("column") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("generated") {
+                    generated = true;
+                } else {
+                    return Err(meta.error("Unknown schema field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        columns.push(Column {
+            variant: Ident::new(&upper_camel_case(&name), ident.span()),
+            span: ident.span(),
+            field: ident,
+            ty: field.ty.clone(),
+            name,
+            generated,
+        });
+    }
+    Ok(columns)
+}
+
+fn upper_camel_case(name: &str) -> String {
+    (name.split('_'))
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::upper_camel_case;
+
+    #[test]
+    fn upper_camel_case_of_column_names() {
+        assert_eq!(upper_camel_case("id"), "Id");
+        assert_eq!(upper_camel_case("guild_id"), "GuildId");
+        assert_eq!(upper_camel_case("last_message_at"), "LastMessageAt");
+        assert_eq!(upper_camel_case("xp"), "Xp");
+    }
+
+    #[test]
+    fn upper_camel_case_skips_empty_parts() {
+        assert_eq!(upper_camel_case("_guild__id_"), "GuildId");
+        assert_eq!(upper_camel_case("round2"), "Round2");
+        assert_eq!(upper_camel_case("already_Upper"), "AlreadyUpper");
+    }
+}
@@ -0,0 +1,973 @@
+-- Baseline: the schema as it existed before versioned migrations were introduced
+-- Every statement is idempotent so it can be recorded against databases built from schemas/*.sql
+
+-- 001_core ---
+
+CREATE TABLE IF NOT EXISTS shards (
+    id INTEGER PRIMARY KEY, -- Shard ID
+    started_at TIMESTAMP DEFAULT NULL -- When the shard was started
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY, -- User ID
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- When the user was first seen by the system
+);
+
+CREATE TABLE IF NOT EXISTS guilds (
+    id TEXT PRIMARY KEY, -- Guild ID
+    enabled BOOLEAN NOT NULL DEFAULT 1 CHECK(enabled IN (0, 1)), -- Whether the bot is active in this guild
+    shard_id INTEGER NOT NULL,
+    added_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- When the bot was added to the guild
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- When the guild activity was last recorded
+    FOREIGN KEY (shard_id) REFERENCES shards(id) ON DELETE CASCADE
+);
+
+-- 002_guild_config ---
+CREATE TABLE IF NOT EXISTS guild_prefixes (
+    guild_id TEXT PRIMARY KEY NOT NULL,
+    prefix TEXT NOT NULL,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS guild_timezones (
+    guild_id TEXT PRIMARY KEY NOT NULL,
+    timezone TEXT NOT NULL,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS guild_languages (
+    guild_id TEXT PRIMARY KEY NOT NULL,
+    language TEXT NOT NULL CHECK(language IN ('english', 'français')),
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS guild_colours (
+    guild_id TEXT PRIMARY KEY NOT NULL,
+    colour TEXT NOT NULL,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_prefixes
+AFTER INSERT ON guild_prefixes
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_prefixes
+AFTER UPDATE ON guild_prefixes
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_prefixes
+AFTER DELETE ON guild_prefixes
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_languages
+AFTER INSERT ON guild_languages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_languages
+AFTER UPDATE ON guild_languages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_languages
+AFTER DELETE ON guild_languages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_timezones
+AFTER INSERT ON guild_timezones
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_timezones
+AFTER UPDATE ON guild_timezones
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_timezones
+AFTER DELETE ON guild_timezones
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_colours
+AFTER INSERT ON guild_colours
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_colours
+AFTER UPDATE ON guild_colours
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_colours
+AFTER DELETE ON guild_colours
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+
+-- 003_afk ---
+CREATE TABLE IF NOT EXISTS afk_statuses(
+    user_id TEXT NOT NULL, -- User ID
+    guild_id TEXT DEFAULT NULL, -- Guild ID for server-specific AFK, NULL for global AFK
+    reason TEXT NOT NULL DEFAULT "", -- Reason for AFK
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- When the user went AFK
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS afk_configs(
+    user_id TEXT PRIMARY KEY, -- User ID
+    per_guild BOOLEAN NOT NULL DEFAULT 0 CHECK(per_guild IN (0, 1)), -- Whether AFK statuses will be per guild or global
+    default_reason TEXT DEFAULT null,-- Message to show when user is AFK
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_afk_statuses
+BEFORE INSERT ON afk_statuses
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_afk_configs
+BEFORE INSERT ON afk_configs
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_afk_statuses
+AFTER INSERT ON afk_statuses
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_afk_statuses
+AFTER UPDATE ON afk_statuses
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_afk_statuses
+AFTER DELETE ON afk_statuses
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 004_leveling ---
+CREATE TABLE IF NOT EXISTS user_levels(
+    user_id TEXT NOT NULL, -- User ID
+    guild_id TEXT NOT NULL, -- Guild ID
+    level INTEGER NOT NULL DEFAULT 0, -- User's current level
+    xp INTEGER NOT NULL DEFAULT 0, -- User's current XP
+    last_message_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- When the user last sent a message (for cooldown)
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_profiles(
+    user_id TEXT PRIMARY KEY, -- User ID
+    avatar_url TEXT DEFAULT NULL, -- Custom avatar URL for level card
+    background_url TEXT DEFAULT NULL, -- Custom background URL for level card
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS level_configs(
+    guild_id TEXT PRIMARY KEY, -- Guild ID
+    minimum_xp_gain INTEGER NOT NULL DEFAULT 15, -- Minimum XP gain per message
+    maximum_xp_gain INTEGER NOT NULL DEFAULT 25, -- Maximum XP gain per message
+    level_up_message TEXT NOT NULL DEFAULT 'GGs {user}, you have reached level {level.rank}!', -- Message to show when user levels up
+    channel_id TEXT DEFAULT NULL, -- Channel ID to send level up messages in, NULL for current channel
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS level_roles(
+    guild_id TEXT NOT NULL, -- Guild ID
+    role_id TEXT NOT NULL, -- Role ID to assign
+    level INTEGER NOT NULL, -- Level required for the role
+    stackable BOOLEAN NOT NULL DEFAULT 0 CHECK(stackable IN (0, 1)), -- Whether the role is stackable with other level roles
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, role_id)
+);
+
+CREATE TABLE IF NOT EXISTS level_xp_multipliers(
+    guild_id TEXT NOT NULL, -- Guild ID
+    role_id TEXT DEFAULT NULL, -- Role ID for role-specific multiplier, NULL for guild-wide multiplier
+    multiplier REAL NOT NULL DEFAULT 1.0 CHECK(multiplier > 0 AND multiplier < 10), -- XP multiplier for the guild
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, role_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_user_levels
+BEFORE INSERT ON user_levels
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_user_levels
+AFTER INSERT ON user_levels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_user_levels
+AFTER UPDATE ON user_levels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_user_levels
+AFTER DELETE ON user_levels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_user_profiles
+BEFORE INSERT ON user_profiles
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_level_configs
+AFTER INSERT ON level_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_level_configs
+AFTER UPDATE ON level_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_level_configs
+AFTER DELETE ON level_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_level_roles
+AFTER INSERT ON level_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_level_roles
+AFTER UPDATE ON level_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_level_roles
+AFTER DELETE ON level_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_level_xp_multipliers
+AFTER INSERT ON level_xp_multipliers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_level_xp_multipliers
+AFTER UPDATE ON level_xp_multipliers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_level_xp_multipliers
+AFTER DELETE ON level_xp_multipliers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+
+-- 005_birthday ---
+CREATE TABLE IF NOT EXISTS birthdays (
+    user_id TEXT PRIMARY KEY, -- User ID
+    day INTEGER NOT NULL CHECK(day BETWEEN 1 AND 31), -- Day of the month (1-31)
+    month INTEGER NOT NULL CHECK(month BETWEEN 1 AND 12), -- Month of the year (1-12)
+    year INTEGER DEFAULT NULL, -- Year of birth / Can be null if the user doesn't want to share their age
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS birthday_configs (
+    guild_id TEXT PRIMARY KEY, -- Guild ID
+    channel_id TEXT DEFAULT NULL, -- Channel ID to send birthday messages in, NULL for current channel
+    message TEXT NOT NULL DEFAULT 'Happy Birthday {user}! 🎉', -- Message to show when it's a user's birthday
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_birthdays
+BEFORE INSERT ON birthdays
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_birthday_configs
+AFTER INSERT ON birthday_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_birthday_configs
+AFTER UPDATE ON birthday_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_birthday_configs
+AFTER DELETE ON birthday_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 006_permission ---
+CREATE TABLE IF NOT EXISTS permission_roles (
+    guild_id TEXT NOT NULL, -- Guild ID
+    permission TEXT NOT NULL, -- Key for the permission
+    role_id TEXT DEFAULT NULL, -- Role ID to assign 
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, permission)
+);
+
+CREATE TABLE IF NOT EXISTS permission_users (
+    guild_id TEXT NOT NULL, -- Guild ID
+    permission TEXT NOT NULL, -- Key for the permission
+    user_id TEXT NOT NULL, -- User ID to assign
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, permission, user_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_permission_users
+BEFORE INSERT ON permission_users
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_permission_users
+AFTER INSERT ON permission_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_permission_users
+AFTER UPDATE ON permission_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_permission_users
+AFTER DELETE ON permission_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_permission_roles
+AFTER INSERT ON permission_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_permission_roles
+AFTER UPDATE ON permission_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_permission_roles
+AFTER DELETE ON permission_roles
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 007_embed ---
+CREATE TABLE IF NOT EXISTS embeds (
+    name TEXT NOT NULL, -- Name of the embed config, e.g., "welcome", "goodbye", "announcement"
+    guild_id TEXT NOT NULL, -- Guild ID for server-specific embed
+    user_id TEXT NOT NULL, -- User ID of the person who created the embed config
+    content TEXT DEFAULT NULL,
+    embeds TEXT DEFAULT "[]", -- JSON array as string
+    components TEXT DEFAULT "[]", -- JSON data as string
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, name)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_embeds
+BEFORE INSERT ON embeds
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_embeds
+AFTER INSERT ON embeds
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_embeds
+AFTER UPDATE ON embeds
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_embeds
+AFTER DELETE ON embeds
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 008_reaction ---
+CREATE TABLE IF NOT EXISTS reaction_triggers (
+    guild_id TEXT NOT NULL,
+    emoji TEXT NOT NULL,
+    trigger TEXT NOT NULL,
+    author_id TEXT NOT NULL, -- User ID of the person who created the trigger
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (author_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, trigger, emoji)
+);
+
+CREATE TABLE IF NOT EXISTS reaction_channels (
+    guild_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    emoji TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, channel_id, emoji)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_reaction_triggers
+BEFORE INSERT ON reaction_triggers
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_reaction_triggers
+AFTER INSERT ON reaction_triggers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_reaction_triggers
+AFTER UPDATE ON reaction_triggers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_reaction_triggers
+AFTER DELETE ON reaction_triggers
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_reaction_channels
+AFTER INSERT ON reaction_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_reaction_channels
+AFTER UPDATE ON reaction_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_reaction_channels
+AFTER DELETE ON reaction_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 009_logs ---
+CREATE TABLE IF NOT EXISTS guild_log_configs (
+    guild_id TEXT NOT NULL,
+    log_type TEXT NOT NULL CHECK(log_type IN ('message', 'voice', 'moderation', 'member', 'channel', 'role', 'emoji', 'guild')), -- Type of log (e.g., message, voice, moderation)
+    data TEXT NOT NULL, -- JSON data as string
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, log_type)
+);
+
+CREATE TABLE IF NOT EXISTS guild_ignore_channels (
+    guild_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE IF NOT EXISTS guild_ignore_users (
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_log_configs
+AFTER INSERT ON guild_log_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_log_configs
+AFTER UPDATE ON guild_log_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_log_configs
+AFTER DELETE ON guild_log_configs
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_ignore_channels
+AFTER INSERT ON guild_ignore_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_ignore_channels
+AFTER UPDATE ON guild_ignore_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_ignore_channels
+AFTER DELETE ON guild_ignore_channels
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_guild_ignore_users
+AFTER INSERT ON guild_ignore_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_guild_ignore_users
+AFTER UPDATE ON guild_ignore_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_guild_ignore_users
+AFTER DELETE ON guild_ignore_users
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_guild_ignore_users
+BEFORE INSERT ON guild_ignore_users
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+-- 010_voice ---
+CREATE TABLE IF NOT EXISTS voice_masters(
+    guild_id TEXT KEY, -- Server ID
+    master_id TEXT NOT NULL, -- Create channel
+    category_id TEXT DEFAULT NULL, -- Category for created channels
+
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, master_id)
+);
+
+CREATE TABLE IF NOT EXISTS voice_configs(
+    user_id TEXT DEFAULT NULL, -- NULL for guild configs
+    guild_id TEXT DEFAULT NULL, -- NULL for non-guild config
+
+    name TEXT DEFAULT NULL,
+    bitrate INTEGER DEFAULT NULL,
+    user_limit INTEGER DEFAULT NULL,
+    locked INTEGER DEFAULT 0 CHECK(locked IN (0, 1)),
+
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (user_id, guild_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_voice_configs
+BEFORE INSERT ON voice_configs
+FOR EACH ROW
+WHEN NEW.user_id IS NOT NULL
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_voice_configs
+AFTER INSERT ON voice_configs
+FOR EACH ROW
+WHEN NEW.guild_id IS NOT NULL
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_voice_configs
+AFTER UPDATE ON voice_configs
+FOR EACH ROW
+WHEN NEW.guild_id IS NOT NULL
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_voice_configs
+AFTER DELETE ON voice_configs
+FOR EACH ROW
+WHEN OLD.guild_id IS NOT NULL
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_voice_masters
+AFTER INSERT ON voice_masters
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_voice_masters
+AFTER UPDATE ON voice_masters
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_voice_masters
+AFTER DELETE ON voice_masters
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 011_giveaway ---
+CREATE TABLE IF NOT EXISTS giveaways(
+    giveaway_id TEXT PRIMARY KEY, -- Giveaway ID (unique identifier)
+    guild_id TEXT NOT NULL, -- Guild ID where the giveaway is hosted
+    channel_id TEXT NOT NULL, -- Channel ID where the giveaway is hosted
+    message_id TEXT NOT NULL, -- Message ID of the giveaway announcement
+    prize TEXT NOT NULL, -- Prize for the giveaway
+    end_time TIMESTAMP NOT NULL, -- When the giveaway ends
+    winners_count INTEGER NOT NULL, -- Number of winners to select
+    user_id TEXT NOT NULL, -- User ID of the giveaway host
+    ended BOOLEAN DEFAULT 0, -- Whether the giveaway has ended
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS giveaway_entries(
+    giveaway_id TEXT NOT NULL, -- Giveaway ID (foreign key to giveaways table)
+    user_id TEXT NOT NULL, -- User ID of the participant
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (giveaway_id) REFERENCES giveaways(giveaway_id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (giveaway_id, user_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_giveaways
+BEFORE INSERT ON giveaways
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_giveaways
+AFTER INSERT ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_giveaways
+AFTER UPDATE ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_giveaways
+AFTER DELETE ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_giveaways
+AFTER INSERT ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_giveaways
+AFTER UPDATE ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_giveaways
+AFTER DELETE ON giveaways
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_giveaway_entries
+BEFORE INSERT ON giveaway_entries
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+-- 012_misc ---
+CREATE TABLE IF NOT EXISTS reminders(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL, -- User ID of the person to remind
+    guild_id TEXT NOT NULL, -- Guild ID for server-specific reminders, NULL for global reminders
+    channel_id TEXT DEFAULT NULL, -- Channel ID to send the reminder in, NULL for DM
+    message TEXT NOT NULL, -- Reminder message
+    remind_at TIMESTAMP NOT NULL, -- When to send the reminder
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS timed_messages(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id TEXT NOT NULL, -- Guild ID to send the message in
+    channel_id TEXT NOT NULL, -- Channel ID to send the message in
+    message TEXT NOT NULL, -- Message to send
+    interval INTEGER NOT NULL, -- Interval for sending the message, stored in seconds
+    author_id TEXT NOT NULL, -- User ID of the person who created the timed message
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_reminders
+BEFORE INSERT ON reminders
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_timed_messages   
+BEFORE INSERT ON timed_messages
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_reminders
+AFTER INSERT ON reminders
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_reminders
+AFTER UPDATE ON reminders
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_reminders
+AFTER DELETE ON reminders
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_timed_messages
+AFTER INSERT ON timed_messages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_timed_messages
+AFTER UPDATE ON timed_messages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_timed_messages
+AFTER DELETE ON timed_messages
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+-- 013_members ---
+CREATE TABLE IF NOT EXISTS guild_members (
+    user_id TEXT NOT NULL,
+    guild_id TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (guild_id) REFERENCES guilds(id),
+    PRIMARY KEY (user_id, guild_id)
+);
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_members
+BEFORE INSERT ON guild_members
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
+
+-- 014_command_aliases ---
+CREATE TABLE IF NOT EXISTS command_aliases(
+    guild_id TEXT NOT NULL,
+    command TEXT NOT NULL,
+    alias TEXT NOT NULL,
+    args TEXT DEFAULT NULL,
+    author_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (guild_id) REFERENCES guilds(id) ON DELETE CASCADE,
+    FOREIGN KEY (author_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (guild_id, command, alias)
+);
+
+CREATE TRIGGER IF NOT EXISTS guild_inserted_command_aliases
+AFTER INSERT ON command_aliases
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_updated_command_aliases
+AFTER UPDATE ON command_aliases
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_deleted_command_aliases
+AFTER DELETE ON command_aliases
+FOR EACH ROW
+BEGIN
+    UPDATE guilds SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.guild_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_not_exists_command_aliases
+BEFORE INSERT ON command_aliases
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.author_id);
+END;
@@ -0,0 +1,94 @@
+-- Soft delete guilds and their configuration instead of cascading a hard delete
+
+ALTER TABLE guilds ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_prefixes ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_timezones ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_languages ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_colours ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE afk_statuses ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE user_levels ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE level_configs ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE level_roles ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE level_xp_multipliers ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE birthday_configs ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE permission_roles ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE permission_users ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE embeds ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE reaction_triggers ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE reaction_channels ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_log_configs ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_ignore_channels ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_ignore_users ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE voice_masters ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE voice_configs ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE giveaways ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE reminders ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE timed_messages ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE guild_members ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE command_aliases ADD COLUMN deleted_at TIMESTAMP DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_guilds_deleted_at ON guilds(deleted_at);
+
+CREATE TRIGGER IF NOT EXISTS guild_soft_deleted
+AFTER UPDATE OF deleted_at ON guilds
+FOR EACH ROW
+WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL
+BEGIN
+    UPDATE guild_prefixes SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_timezones SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_languages SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_colours SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE afk_statuses SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE user_levels SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE level_configs SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE level_roles SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE level_xp_multipliers SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE birthday_configs SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE permission_roles SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE permission_users SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE embeds SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE reaction_triggers SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE reaction_channels SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_log_configs SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_ignore_channels SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_ignore_users SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE voice_masters SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE voice_configs SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE giveaways SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE reminders SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE timed_messages SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE guild_members SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+    UPDATE command_aliases SET deleted_at = NEW.deleted_at WHERE guild_id = NEW.id AND deleted_at IS NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS guild_restored
+AFTER UPDATE OF deleted_at ON guilds
+FOR EACH ROW
+WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+    UPDATE guild_prefixes SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_timezones SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_languages SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_colours SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE afk_statuses SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE user_levels SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE level_configs SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE level_roles SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE level_xp_multipliers SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE birthday_configs SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE permission_roles SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE permission_users SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE embeds SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE reaction_triggers SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE reaction_channels SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_log_configs SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_ignore_channels SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_ignore_users SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE voice_masters SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE voice_configs SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE giveaways SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE reminders SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE timed_messages SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE guild_members SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+    UPDATE command_aliases SET deleted_at = NULL WHERE guild_id = NEW.id AND deleted_at = OLD.deleted_at;
+END;
@@ -0,0 +1,11 @@
+-- Winners drawn when a giveaway ends or is rerolled
+
+CREATE TABLE giveaway_winners(
+    giveaway_id TEXT NOT NULL, -- Giveaway ID (foreign key to giveaways table)
+    user_id TEXT NOT NULL, -- User ID of the winner
+    round INTEGER NOT NULL DEFAULT 0, -- 0 for the original draw, incremented on every reroll
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (giveaway_id) REFERENCES giveaways(giveaway_id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (giveaway_id, user_id)
+);
@@ -0,0 +1,15 @@
+-- Recurring timed messages on an interval or a cron expression
+
+ALTER TABLE timed_messages ADD COLUMN cron TEXT DEFAULT NULL;
+ALTER TABLE timed_messages ADD COLUMN paused BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE timed_messages ADD COLUMN next_run_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE timed_messages ADD COLUMN last_run_at TIMESTAMP DEFAULT NULL;
+
+-- The trigger referenced a `user_id` column timed messages don't have, failing every insert
+DROP TRIGGER IF EXISTS user_not_exists_timed_messages;
+CREATE TRIGGER user_not_exists_timed_messages
+BEFORE INSERT ON timed_messages
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.author_id);
+END;
@@ -0,0 +1,3 @@
+-- Daily birthday announcements in the guild's timezone
+
+ALTER TABLE birthday_configs ADD COLUMN last_announced_on TEXT DEFAULT NULL;
@@ -0,0 +1,23 @@
+-- Server-side dashboard sessions, the session cookie only holds an opaque token
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id TEXT PRIMARY KEY, -- SHA-256 of the token in the session cookie, hex encoded
+    user_id TEXT NOT NULL, -- Discord user who logged in
+    access_token TEXT NOT NULL, -- Discord access token, AES-GCM encrypted
+    refresh_token TEXT NOT NULL, -- Discord refresh token, AES-GCM encrypted
+    access_expires_at TEXT NOT NULL, -- When the access token has to be refreshed, RFC 3339
+    expires_at TEXT NOT NULL, -- When the session ends, RFC 3339
+    user_agent TEXT DEFAULT NULL, -- Browser the session was created from
+    last_used_at TEXT NOT NULL, -- RFC 3339
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions(user_id);
+
+DROP TRIGGER IF EXISTS user_not_exists_user_sessions;
+CREATE TRIGGER user_not_exists_user_sessions
+BEFORE INSERT ON user_sessions
+FOR EACH ROW
+BEGIN
+    INSERT OR IGNORE INTO users(id) VALUES (NEW.user_id);
+END;
@@ -1,4 +1,8 @@
 # PowerShell script to build database from schema files
+# The schema files drop every table first, only use this for throwaway local databases.
+# Deployed databases are upgraded through the versioned files in ./migrations (POST /api/migrations).
+# ./schemas is the frozen baseline (0001), every later change only lives in ./migrations, which are
+# applied on top of it and recorded in schema_migrations like POST /api/migrations would.
 param(
     [string]$OutputDb = "temp.db",
     [string]$CombinedSchema = "./schemas/schema_combined.sql"
@@ -10,6 +14,21 @@ $schemaFiles = Get-ChildItem -Path "./schemas" -Filter "*.sql" |
     Sort-Object Name |
     ForEach-Object { $_.FullName }
 
+$migrationFiles = Get-ChildItem -Path "./migrations" -Filter "*.sql" |
+    Sort-Object Name |
+    ForEach-Object { $_.FullName }
+
+# FNV-1a hash of a file, hex encoded, the checksum `Migration::checksum` records
+function Get-Fnv1a([string]$Path) {
+    $modulus = [System.Numerics.BigInteger]::Pow(2, 64)
+    $hash = [UInt64]14695981039346656037
+    foreach ($byte in [System.IO.File]::ReadAllBytes($Path)) {
+        $mixed = [System.Numerics.BigInteger]($hash -bxor [UInt64]$byte)
+        $hash = [UInt64](($mixed * 1099511628211) % $modulus)
+    }
+    $hash.ToString("x16")
+}
+
 if ($schemaFiles.Count -eq 0) {
     Write-Error "No schema files found in ./schemas directory"
     exit 1
@@ -42,6 +61,23 @@ foreach ($file in $schemaFiles) {
     $combinedContent += Get-Content $file
     $combinedContent += ""
 }
+
+# Then every migration, recorded so POST /api/migrations sees the database as up to date
+$combinedContent += "CREATE TABLE IF NOT EXISTS schema_migrations ("
+$combinedContent += "    version INTEGER PRIMARY KEY, -- Migration version, applied in ascending order"
+$combinedContent += "    name TEXT NOT NULL, -- File name of the migration"
+$combinedContent += "    checksum TEXT NOT NULL, -- Checksum of the migration file when it was applied"
+$combinedContent += "    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP"
+$combinedContent += ");"
+foreach ($file in $migrationFiles) {
+    $name = (Split-Path $file -Leaf) -replace '\.sql$', ''
+    $version = [int]($name -split '_')[0]
+    $combinedContent += "-- migration $name ---"
+    $combinedContent += Get-Content $file
+    $combinedContent += ""
+    $combinedContent += "INSERT INTO schema_migrations (version, name, checksum) VALUES ($version, '$name', '$(Get-Fnv1a $file)');"
+    $combinedContent += ""
+}
 $combinedContent | Out-File -FilePath $CombinedSchema -Encoding UTF8
 
 if (-not (Test-Path $CombinedSchema)) {
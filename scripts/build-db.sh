@@ -1,11 +1,25 @@
 #!/bin/bash
 # Bash script to build database from schema files
+# The schema files drop every table first, only use this for throwaway local databases.
+# Deployed databases are upgraded through the versioned files in ./migrations (POST /api/migrations).
+# ./schemas is the frozen baseline (0001), every later change only lives in ./migrations, which are
+# applied on top of it and recorded in schema_migrations like POST /api/migrations would.
 
 OUTPUT_DB="${1:-temp.db}"
 COMBINED_SCHEMA="${2:-./schemas/schema_combined.sql}"
 
 # Get all .sql files in schemas directory, excluding specific files
 SCHEMA_FILES=($(find ./schemas -name "*.sql" -not -name "schema_combined.sql" | sort))
+MIGRATION_FILES=($(find ./migrations -name "*.sql" | sort))
+
+# FNV-1a hash of a file, hex encoded, the checksum `Migration::checksum` records
+fnv1a() {
+    local hash=$((0xcbf29ce484222325)) byte
+    for byte in $(od -An -v -tu1 "$1"); do
+        hash=$(( (hash ^ byte) * 0x100000001b3 ))
+    done
+    printf '%016x' "$hash"
+}
 
 if [ ${#SCHEMA_FILES[@]} -eq 0 ]; then
     echo "Error: No schema files found in ./schemas directory" >&2
@@ -43,6 +57,26 @@ for file in "${SCHEMA_FILES[@]}"; do
     echo "" >> "$COMBINED_SCHEMA"
 done
 
+# Then every migration, recorded so POST /api/migrations sees the database as up to date
+cat >> "$COMBINED_SCHEMA" <<'SQL'
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INTEGER PRIMARY KEY, -- Migration version, applied in ascending order
+    name TEXT NOT NULL, -- File name of the migration
+    checksum TEXT NOT NULL, -- Checksum of the migration file when it was applied
+    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+SQL
+for file in "${MIGRATION_FILES[@]}"; do
+    name=$(basename "$file" .sql)
+    version=$((10#${name%%_*}))
+    echo "-- migration $name ---" >> "$COMBINED_SCHEMA"
+    cat "$file" >> "$COMBINED_SCHEMA"
+    echo "" >> "$COMBINED_SCHEMA"
+    echo "INSERT INTO schema_migrations (version, name, checksum) VALUES ($version, '$name', '$(fnv1a "$file")');" >> "$COMBINED_SCHEMA"
+    echo "" >> "$COMBINED_SCHEMA"
+done
+
 if [ ! -f "$COMBINED_SCHEMA" ]; then
     echo "Error: Failed to create combined schema file" >&2
     exit 1
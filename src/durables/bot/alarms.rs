@@ -1,51 +1,218 @@
-use std::{cell::RefCell, collections::HashMap};
-
+use chrono::DateTime;
 use serde::{Deserialize, Serialize};
-use worker::{SetAlarmOptions, State};
+use tracing::{error, warn};
+use worker::{Result, SqlStorage, SqlStorageValue, State, Storage};
+
+/// Delay before the first retry of a failed alarm, doubled on every attempt
+const RETRY_BASE_MS: i64 = 10_000;
+/// Longest delay between two retries
+const RETRY_MAX_MS: i64 = 60 * 60 * 1000;
+/// Attempts after which a failing alarm is dropped
+const MAX_ATTEMPTS: u32 = 8;
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS scheduled_alarms (
+    key TEXT PRIMARY KEY, -- Identifies the alarm, scheduling the same key again replaces it
+    run_at INTEGER NOT NULL, -- Unix timestamp in milliseconds
+    alarm TEXT NOT NULL, -- JSON encoded ScheduledAlarm
+    attempts INTEGER NOT NULL DEFAULT 0, -- Failed attempts so far
+    last_error TEXT DEFAULT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_scheduled_alarms_run_at ON scheduled_alarms(run_at);";
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Work scheduled in the durable object, persisted so it survives eviction and hibernation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "data")]
 pub enum ScheduledAlarm {
     DatabaseSync,
+    /// Deliver the reminder with this id
+    Reminder {
+        id: i64,
+    },
+    /// End the giveaway with this id and draw its winners
+    GiveawayEnd {
+        giveaway_id: String,
+    },
+    /// Send the timed message with this id, then schedule its next run
+    TimedMessage {
+        id: i64,
+    },
+    /// Announce the birthdays of the guild's local day, then wait for its next midnight
+    Birthdays {
+        guild_id: String,
+    },
+    /// Flag the shards that stopped sending `BotUpdate` as stale or zombie
+    ShardHealth,
     // Add more alarm types as needed
 }
 
 impl ScheduledAlarm {
-    pub fn initial_alarms() -> Vec<ScheduledAlarm> {
-        vec![
-            ScheduledAlarm::DatabaseSync,
-            // Add more initial alarms as needed
-        ]
-    }
-}
-
-pub fn default_options() -> SetAlarmOptions {
-    SetAlarmOptions {
-        allow_concurrency: Some(true),
-        allow_unconfirmed: Some(true),
-    }
-}
-
-pub async fn update_system_alarm(
-    state: &State,
-    alarms: &RefCell<HashMap<i64, Vec<ScheduledAlarm>>>,
-) -> worker::Result<()> {
-    let keys = alarms.borrow().keys().cloned().collect::<Vec<i64>>();
-    let storage = state.storage();
-    let alarm_options = SetAlarmOptions {
-        allow_concurrency: Some(true),
-        allow_unconfirmed: Some(true),
-    };
-    if let Some(&earliest_time) = keys.iter().min() {
-        if let Err(e) = storage
-            .set_alarm_with_options(earliest_time, alarm_options)
-            .await
-        {
-            let err_msg = format!("Failed to set system alarm: {}", e);
-            return Err(worker::Error::Internal((&err_msg).into()));
+    /// Storage key of the alarm, only one alarm per key is pending at a time
+    pub fn key(&self) -> String {
+        match self {
+            ScheduledAlarm::DatabaseSync => "database_sync".to_string(),
+            ScheduledAlarm::Reminder { id } => format!("reminder:{}", id),
+            ScheduledAlarm::GiveawayEnd { giveaway_id } => format!("giveaway:{}", giveaway_id),
+            ScheduledAlarm::TimedMessage { id } => format!("timed_message:{}", id),
+            ScheduledAlarm::Birthdays { guild_id } => format!("birthdays:{}", guild_id),
+            ScheduledAlarm::ShardHealth => "shard_health".to_string(),
+        }
+    }
+}
+
+/// What to do with an alarm once it was processed successfully
+#[derive(Debug)]
+pub enum AlarmOutcome {
+    Done,
+    /// Run the alarm again at this time, for recurring work
+    Reschedule(i64),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StoredAlarm {
+    pub key: String,
+    pub run_at: i64,
+    pub alarm: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+impl StoredAlarm {
+    pub fn alarm(&self) -> serde_json::Result<ScheduledAlarm> {
+        serde_json::from_str(&self.alarm)
+    }
+}
+
+/// Request body of `POST /alarms` on the durable object
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ScheduleAlarmRequest {
+    pub run_at: i64,
+    pub alarm: ScheduledAlarm,
+}
+
+/// Alarms stored in the durable object's SQLite storage
+///
+/// Processing is at least once: an entry is only removed after it succeeded, and is retried with
+/// exponential backoff when it failed, so every [`ScheduledAlarm`] handler must be idempotent.
+pub struct AlarmScheduler {
+    storage: Storage,
+}
+
+impl AlarmScheduler {
+    pub fn new(state: &State) -> Self {
+        let storage = state.storage();
+        if let Err(e) = storage.sql().exec(CREATE_TABLE, None) {
+            error!("Failed to create the scheduled alarms table: {}", e);
+        }
+        Self { storage }
+    }
+
+    fn sql(&self) -> SqlStorage {
+        self.storage.sql()
+    }
+
+    /// Store `alarm` to run at `run_at`, replacing the pending alarm with the same key
+    pub fn schedule(&self, run_at: i64, alarm: &ScheduledAlarm) -> Result<()> {
+        let json = serde_json::to_string(alarm)?;
+        self.sql().exec(
+            "INSERT INTO scheduled_alarms (key, run_at, alarm) VALUES (?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                run_at = excluded.run_at, alarm = excluded.alarm, attempts = 0, last_error = NULL",
+            vec![alarm.key().into(), run_at.into(), json.into()],
+        )?;
+        Ok(())
+    }
+
+    pub fn cancel(&self, key: &str) -> Result<()> {
+        (self.sql()).exec(
+            "DELETE FROM scheduled_alarms WHERE key = ?",
+            vec![key.into()],
+        )?;
+        Ok(())
+    }
+
+    pub fn pending(&self) -> Result<Vec<StoredAlarm>> {
+        (self.sql())
+            .exec("SELECT * FROM scheduled_alarms ORDER BY run_at", None)?
+            .to_array()
+    }
+
+    /// Alarms whose time has come, oldest first
+    pub fn due(&self, now: i64) -> Result<Vec<StoredAlarm>> {
+        (self.sql())
+            .exec(
+                "SELECT * FROM scheduled_alarms WHERE run_at <= ? ORDER BY run_at",
+                vec![now.into()],
+            )?
+            .to_array()
+    }
+
+    /// Record the outcome of a successful run
+    ///
+    /// Entries are matched on their `run_at` too, so an alarm rescheduled while it was running
+    /// isn't lost.
+    pub fn complete(&self, entry: &StoredAlarm, outcome: AlarmOutcome) -> Result<()> {
+        let bindings: Vec<SqlStorageValue> = vec![entry.key.clone().into(), entry.run_at.into()];
+        match outcome {
+            AlarmOutcome::Done => self.sql().exec(
+                "DELETE FROM scheduled_alarms WHERE key = ? AND run_at = ?",
+                bindings,
+            )?,
+            AlarmOutcome::Reschedule(next) => self.sql().exec(
+                "UPDATE scheduled_alarms SET run_at = ?, attempts = 0, last_error = NULL
+                WHERE key = ? AND run_at = ?",
+                [vec![next.into()], bindings].concat(),
+            )?,
+        };
+        Ok(())
+    }
+
+    /// Push a failed alarm back with exponential backoff, or drop it after [`MAX_ATTEMPTS`]
+    pub fn fail(&self, entry: &StoredAlarm, error: &str, now: i64) -> Result<()> {
+        let attempts = entry.attempts + 1;
+        if attempts >= MAX_ATTEMPTS {
+            error!(
+                "Dropping alarm {} after {} failed attempts: {}",
+                entry.key, attempts, error
+            );
+            return self.complete(entry, AlarmOutcome::Done);
+        }
+
+        let delay = (RETRY_BASE_MS << (attempts - 1)).min(RETRY_MAX_MS);
+        warn!(
+            "Alarm {} failed (attempt {}), retrying in {}s: {}",
+            entry.key,
+            attempts,
+            delay / 1000,
+            error
+        );
+        self.sql().exec(
+            "UPDATE scheduled_alarms SET run_at = ?, attempts = ?, last_error = ?
+            WHERE key = ? AND run_at = ?",
+            vec![
+                (now + delay).into(),
+                (attempts as i64).into(),
+                error.into(),
+                entry.key.clone().into(),
+                entry.run_at.into(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Arm the durable object alarm for the earliest pending entry, or clear it when there is none
+    pub async fn update_system_alarm(&self) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Earliest {
+            run_at: Option<i64>,
+        }
+
+        let earliest: Earliest = (self.sql())
+            .exec("SELECT MIN(run_at) AS run_at FROM scheduled_alarms", None)?
+            .one()?;
+        // A bare i64 would be read as an offset from now, not a timestamp
+        match earliest.run_at.and_then(DateTime::from_timestamp_millis) {
+            Some(run_at) => self.storage.set_alarm(run_at).await,
+            None => self.storage.delete_alarm().await,
         }
-    } else if let Err(e) = storage.delete_alarm().await {
-        let err_msg = format!("Failed to delete system alarm: {}", e);
-        return Err(worker::Error::Internal((&err_msg).into()));
     }
-    Ok(())
 }
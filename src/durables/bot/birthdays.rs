@@ -0,0 +1,60 @@
+use chrono::Utc;
+use tracing::{info, warn};
+use worker::{Result, State};
+
+use super::{SocketSendEvent, alarms::AlarmOutcome, misc};
+use crate::{
+    schema::guild::BirthdayConfigsSchema,
+    services::{birthdays, timed_messages, websocket::WsEnvelope},
+    state::database::{Database, DatabaseExt},
+};
+
+/// Delay before trying again when no bot is connected to announce the birthdays
+const BOT_OFFLINE_RETRY_MS: i64 = 60 * 1000;
+
+/// Announce the guild's birthdays of the local day, then wait for the next local midnight
+///
+/// The timezone is read on every run, so a changed timezone applies from the following day.
+pub async fn announce(
+    state: &State,
+    database: &Database,
+    guild_id: &String,
+) -> Result<AlarmOutcome> {
+    let configs: Vec<BirthdayConfigsSchema> = database
+        .execute(BirthdayConfigsSchema::get_config(guild_id))
+        .await?;
+    let Some(config) = configs.into_iter().next() else {
+        info!(
+            "Guild {} no longer has a birthday config, unscheduling",
+            guild_id
+        );
+        return Ok(AlarmOutcome::Done);
+    };
+
+    let timezone = timed_messages::guild_timezone(database, guild_id).await?;
+    let now = Utc::now();
+    let today = now.with_timezone(&timezone).date_naive();
+    let next = birthdays::next_midnight(now, timezone).timestamp_millis();
+
+    let day = today.format("%Y-%m-%d").to_string();
+    if config.last_announced_on.as_ref() == Some(&day) {
+        return Ok(AlarmOutcome::Reschedule(next));
+    }
+    // The day is only claimed once, don't claim it when nobody can announce it
+    if !misc::bot_connected(state, Some(guild_id)) {
+        warn!(
+            "No bot connected to announce birthdays of guild {}, retrying later",
+            guild_id
+        );
+        let retry = now.timestamp_millis() + BOT_OFFLINE_RETRY_MS;
+        return Ok(AlarmOutcome::Reschedule(retry.min(next)));
+    }
+
+    if let Some(announcement) = birthdays::announce(database, &config, today).await?
+        && !announcement.birthdays.is_empty()
+    {
+        let envelope = WsEnvelope::new(SocketSendEvent::BirthdayAnnouncement, announcement);
+        misc::send_to_bot(state, Some(guild_id), envelope)?;
+    }
+    Ok(AlarmOutcome::Reschedule(next))
+}
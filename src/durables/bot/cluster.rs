@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use worker::{Result, Url};
+
+/// Prefix of the websocket tag holding the cluster id of a bot connection
+const CLUSTER_TAG_PREFIX: &str = "cluster:";
+
+/// Identity of a bot process, given as `?cluster=2&shards=16-31&total=64` when it connects
+///
+/// A bot running every shard in one process can leave them out, it is then cluster 0 and owns
+/// every shard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClusterIdentity {
+    pub cluster_id: u32,
+    /// First shard run by the cluster
+    pub first_shard: u32,
+    /// Last shard run by the cluster, inclusive
+    pub last_shard: u32,
+    /// Shards of the whole bot, shards past it are dropped from the global view
+    pub total_shards: Option<u32>,
+}
+
+impl ClusterIdentity {
+    /// A cluster owning every shard
+    pub fn everything(cluster_id: u32) -> Self {
+        Self {
+            cluster_id,
+            first_shard: 0,
+            last_shard: u32::MAX,
+            total_shards: None,
+        }
+    }
+
+    /// Read the identity from the query of the websocket upgrade
+    pub fn from_url(url: &Url) -> Result<Self> {
+        let param = |name: &str| {
+            (url.query_pairs())
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let number = |name: &str, value: &str| {
+            value.trim().parse::<u32>().map_err(|_| {
+                worker::Error::RustError(format!("Invalid {} {:?} in the handshake", name, value))
+            })
+        };
+
+        let cluster_id = match param("cluster") {
+            Some(cluster) => number("cluster", &cluster)?,
+            None => 0,
+        };
+        let mut identity = Self::everything(cluster_id);
+        if let Some(total) = param("total") {
+            identity.total_shards = Some(number("total", &total)?);
+        }
+        if let Some(shards) = param("shards") {
+            let (first, last) = shards.split_once('-').unwrap_or((&shards, &shards));
+            identity.first_shard = number("shards", first)?;
+            identity.last_shard = number("shards", last)?;
+        } else if let Some(total) = identity.total_shards {
+            identity.last_shard = total.saturating_sub(1);
+        }
+
+        let past_total = (identity.total_shards).is_some_and(|total| identity.last_shard >= total);
+        if identity.first_shard > identity.last_shard || past_total {
+            return Err(worker::Error::RustError(format!(
+                "Invalid shard range {}-{} of {:?} shards in the handshake",
+                identity.first_shard, identity.last_shard, identity.total_shards
+            )));
+        }
+        Ok(identity)
+    }
+
+    pub fn owns(&self, shard_id: u32) -> bool {
+        (self.first_shard..=self.last_shard).contains(&shard_id)
+    }
+
+    /// Whether the cluster should get an event of the guild, or one tied to no guild when `None`
+    ///
+    /// Discord puts a guild on shard `(guild_id >> 22) % total_shards`. A cluster that didn't give
+    /// the total runs every shard, and an id that isn't a snowflake can't be routed, so both get
+    /// the event.
+    pub fn receives(&self, guild_id: Option<&str>) -> bool {
+        let (Some(guild_id), Some(total)) = (guild_id, self.total_shards) else {
+            return true;
+        };
+        match guild_id.parse::<u64>() {
+            Ok(guild_id) if total > 0 => self.owns(((guild_id >> 22) % u64::from(total)) as u32),
+            _ => true,
+        }
+    }
+
+    /// Websocket tag of the cluster's connections
+    pub fn tag(&self) -> String {
+        format!("{}{}", CLUSTER_TAG_PREFIX, self.cluster_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(first_shard: u32, last_shard: u32, total_shards: Option<u32>) -> ClusterIdentity {
+        ClusterIdentity {
+            cluster_id: 1,
+            first_shard,
+            last_shard,
+            total_shards,
+        }
+    }
+
+    #[test]
+    fn guild_events_go_to_the_cluster_of_their_shard() {
+        // Shard 20 of 64
+        let guild_id = (20u64 << 22 | 12345).to_string();
+        assert!(cluster(16, 31, Some(64)).receives(Some(&guild_id)));
+        assert!(!cluster(0, 15, Some(64)).receives(Some(&guild_id)));
+        assert!(!cluster(32, 63, Some(64)).receives(Some(&guild_id)));
+        // Shard 84 wraps around to 20
+        let guild_id = (84u64 << 22).to_string();
+        assert!(cluster(16, 31, Some(64)).receives(Some(&guild_id)));
+        assert!(!cluster(0, 15, Some(64)).receives(Some(&guild_id)));
+    }
+
+    #[test]
+    fn other_events_go_to_every_cluster() {
+        assert!(cluster(0, 15, Some(64)).receives(None));
+        assert!(cluster(16, 31, Some(64)).receives(None));
+    }
+
+    #[test]
+    fn unroutable_guild_events_go_to_every_cluster() {
+        let guild_id = (20u64 << 22).to_string();
+        assert!(ClusterIdentity::everything(0).receives(Some(&guild_id)));
+        assert!(cluster(0, 15, Some(64)).receives(Some("not a snowflake")));
+    }
+}
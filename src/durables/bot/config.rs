@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use strum::AsRefStr;
+use tracing::error;
+use worker::{Result, State, Storage};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS config_versions (
+    guild_id TEXT PRIMARY KEY,
+    version INTEGER NOT NULL -- Bumped on every configuration change of the guild
+);";
+
+/// Part of a guild's configuration a write changed, so the bot only drops that cache entry
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ConfigSection {
+    /// The guild row itself, on join, leave or when it is disabled
+    Guild,
+    /// Several settings at once
+    Settings,
+    Prefix,
+    Language,
+    Timezone,
+    Colour,
+    Birthday,
+    Giveaways,
+    TimedMessages,
+}
+
+/// Data of a `ConfigUpdated` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigUpdatedPayload {
+    pub guild_id: String,
+    pub section: ConfigSection,
+    /// Increases by one on every change of the guild, events older than the last seen can be ignored
+    pub version: i64,
+}
+
+/// Request body of `POST /config/{guild_id}` on the durable object
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigUpdateRequest {
+    pub section: ConfigSection,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: i64,
+}
+
+/// Configuration versions of every guild, kept in the durable object's SQLite storage
+///
+/// Versions are assigned and sent without yielding by the single threaded durable object, and a
+/// guild's events only go to the connections of the cluster running its shard, so each of them
+/// receives the events of the guild in version order. A resumed connection gets the missed ones
+/// replayed in sequence order first.
+pub struct ConfigVersions {
+    storage: Storage,
+}
+
+impl ConfigVersions {
+    pub fn new(state: &State) -> Self {
+        let storage = state.storage();
+        if let Err(e) = storage.sql().exec(CREATE_TABLE, None) {
+            error!("Failed to create the config versions table: {}", e);
+        }
+        Self { storage }
+    }
+
+    /// Next configuration version of the guild
+    pub fn bump(&self, guild_id: &str) -> Result<i64> {
+        let versions: Vec<Version> = (self.storage.sql())
+            .exec(
+                "INSERT INTO config_versions (guild_id, version) VALUES (?, 1)
+                ON CONFLICT(guild_id) DO UPDATE SET version = version + 1
+                RETURNING version",
+                vec![guild_id.into()],
+            )?
+            .to_array()?;
+        (versions.first().map(|row| row.version))
+            .ok_or_else(|| worker::Error::RustError("No config version returned".into()))
+    }
+}
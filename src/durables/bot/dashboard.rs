@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use worker::{Request, Result, State, WebSocket};
+
+use super::{ShardUpdatePayload, health::ShardUnhealthyPayload, misc::BotDurableTag};
+use crate::services::websocket::WsEnvelope;
+
+/// Header carrying the comma separated guild ids a dashboard connection is subscribed to.
+/// Set by the worker after checking the user may manage them, never taken from the client.
+pub const DASHBOARD_GUILDS_HEADER: &str = "X-Dashboard-Guilds";
+
+/// Guilds a single dashboard connection can follow, websockets take at most 10 tags
+/// and one of them marks the connection as a dashboard
+pub const MAX_DASHBOARD_GUILDS: usize = 9;
+
+/// Events pushed to dashboard connections
+#[derive(Hash, Eq, PartialEq, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum DashboardEvent {
+    /// Sent once the connection is accepted, with the guilds it follows
+    Subscribed,
+    ShardStatus,
+    SettingsUpdated,
+    GiveawayEntries,
+    /// A shard stopped reporting and turned `stale` or `zombie`
+    ShardUnhealthy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubscribedPayload {
+    pub guild_ids: Vec<String>,
+}
+
+/// Data of a `SettingsUpdated` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettingsUpdatedPayload {
+    pub guild_id: String,
+    /// Settings section that changed, `all` when several did
+    pub section: String,
+}
+
+/// Data of a `GiveawayEntries` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GiveawayEntriesPayload {
+    pub guild_id: String,
+    pub giveaway_id: String,
+    pub entries: i64,
+}
+
+/// Accept a dashboard connection, tagged with every guild it follows
+pub fn accept(state: &State, req: &Request, shards: &[ShardUpdatePayload]) -> Result<WebSocket> {
+    let guild_ids: Vec<String> = (req.headers().get(DASHBOARD_GUILDS_HEADER)?)
+        .map(|header| {
+            (header.split(',').map(str::trim))
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if guild_ids.len() > MAX_DASHBOARD_GUILDS {
+        return Err(worker::Error::RustError(format!(
+            "Dashboard connections follow at most {} guilds",
+            MAX_DASHBOARD_GUILDS
+        )));
+    }
+
+    let pair = worker::WebSocketPair::new()?;
+    let mut tags = vec![BotDurableTag::Dashboard.as_ref()];
+    tags.extend(guild_ids.iter().map(String::as_str));
+    state.accept_websocket_with_tags(&pair.server, &tags);
+
+    let subscribed = WsEnvelope::new(DashboardEvent::Subscribed, SubscribedPayload { guild_ids });
+    pair.server.send(&subscribed)?;
+    pair.server
+        .send(&WsEnvelope::new(DashboardEvent::ShardStatus, shards))?;
+    Ok(pair.client)
+}
+
+pub fn is_dashboard(state: &State, ws: &WebSocket) -> bool {
+    (state.get_tags(ws).iter()).any(|tag| tag == BotDurableTag::Dashboard.as_ref())
+}
+
+/// Push the latest shard status to every dashboard
+pub fn push_shard_status(state: &State, shards: &[ShardUpdatePayload]) {
+    broadcast(state, &WsEnvelope::new(DashboardEvent::ShardStatus, shards));
+}
+
+/// Tell every dashboard a shard stopped reporting
+pub fn push_shard_unhealthy(state: &State, unhealthy: &ShardUnhealthyPayload) {
+    broadcast(
+        state,
+        &WsEnvelope::new(DashboardEvent::ShardUnhealthy, unhealthy),
+    );
+}
+
+fn broadcast(state: &State, envelope: &WsEnvelope<DashboardEvent>) {
+    let message = match serde_json::to_string(envelope) {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("Failed to serialize {:?}: {}", envelope.event, e);
+            return;
+        }
+    };
+    for ws in state.get_websockets_with_tag(BotDurableTag::Dashboard.as_ref()) {
+        if let Err(e) = ws.send_with_str(&message) {
+            warn!("Failed to push {:?} to dashboard: {}", envelope.event, e);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use strum::AsRefStr;
+use worker::{Result, State, Url, WebSocket, WebSocketIncomingMessage};
+
+use super::SocketSendEvent;
+use crate::services::websocket::WsEnvelope;
+
+/// Prefix of the websocket tag holding the encoding of a bot connection
+const ENCODING_TAG_PREFIX: &str = "encoding:";
+
+/// Frame format of a bot connection, chosen with `?encoding=` when it connects
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, AsRefStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum GatewayEncoding {
+    /// Text frames holding JSON
+    #[default]
+    Json,
+    /// Binary frames, see [`WsEnvelope::to_bincode`]
+    Bincode,
+}
+
+impl GatewayEncoding {
+    pub fn from_url(url: &Url) -> Result<Self> {
+        match url.query_pairs().find(|(key, _)| key == "encoding") {
+            Some((_, encoding)) => serde_json::from_value(encoding.clone().into()).map_err(|_| {
+                worker::Error::RustError(format!("Unsupported gateway encoding {:?}", encoding))
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Websocket tag of the connections using this encoding
+    pub fn tag(self) -> String {
+        format!("{}{}", ENCODING_TAG_PREFIX, self.as_ref())
+    }
+
+    /// Encoding a bot connection negotiated, from its tag
+    pub fn of(state: &State, ws: &WebSocket) -> Self {
+        let bincode = Self::Bincode.tag();
+        match state.get_tags(ws).contains(&bincode) {
+            true => Self::Bincode,
+            false => Self::Json,
+        }
+    }
+
+    pub fn send<T: Serialize>(self, ws: &WebSocket, envelope: &WsEnvelope<T>) -> Result<()> {
+        match self {
+            Self::Json => ws.send(envelope),
+            Self::Bincode => ws.send_with_bytes(to_bytes(envelope)?),
+        }
+    }
+
+    /// Send an event recorded for replays, which is kept as JSON
+    pub fn send_recorded(self, ws: &WebSocket, message: &str) -> Result<()> {
+        match self {
+            Self::Json => ws.send_with_str(message),
+            Self::Bincode => {
+                let envelope: WsEnvelope<SocketSendEvent> = serde_json::from_str(message)?;
+                ws.send_with_bytes(to_bytes(&envelope)?)
+            }
+        }
+    }
+}
+
+pub fn to_bytes<T: Serialize>(envelope: &WsEnvelope<T>) -> Result<Vec<u8>> {
+    (envelope.to_bincode())
+        .map_err(|e| worker::Error::RustError(format!("Failed to encode frame: {}", e)))
+}
+
+/// Decode a frame from the bot, text frames are JSON and binary frames bincode
+pub fn decode<T: DeserializeOwned>(message: WebSocketIncomingMessage) -> Result<WsEnvelope<T>> {
+    match message {
+        WebSocketIncomingMessage::String(text) => Ok(serde_json::from_str(&text)?),
+        WebSocketIncomingMessage::Binary(bytes) => WsEnvelope::from_bincode(&bytes)
+            .map_err(|e| worker::Error::RustError(format!("Failed to decode frame: {}", e))),
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use worker::{Result, SqlStorage, State, Storage};
+
+use super::{SocketSendEvent, cluster::ClusterIdentity, encoding::GatewayEncoding};
+use crate::services::websocket::WsEnvelope;
+
+/// Version of the gateway protocol, a bot may ask for one with `?v=` when connecting
+pub const PROTOCOL_VERSION: u32 = 1;
+/// How often the bot is expected to send a `Heartbeat`
+pub const HEARTBEAT_INTERVAL_MS: u64 = 30_000;
+/// Events kept for resuming, the oldest are dropped first
+const REPLAY_BUFFER_EVENTS: i64 = 1000;
+/// How long events are kept for resuming
+const REPLAY_WINDOW_MS: i64 = 10 * 60 * 1000;
+/// Sessions that can still be resumed, older ones force a full resync
+const RESUMABLE_SESSIONS: i64 = 16;
+
+const CREATE_TABLES: &str = "CREATE TABLE IF NOT EXISTS gateway_events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT, -- Sequence number of the event, never reused
+    message TEXT NOT NULL, -- Envelope as it was sent
+    guild_id TEXT, -- Guild the event belongs to, NULL when every cluster gets it
+    sent_at INTEGER NOT NULL -- Unix timestamp in milliseconds
+);
+CREATE TABLE IF NOT EXISTS gateway_sessions (
+    session_id TEXT PRIMARY KEY,
+    created_at INTEGER NOT NULL -- Unix timestamp in milliseconds
+);";
+
+/// Data of the `Ready` event, sent as soon as a bot connects
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadyPayload {
+    /// Identifies this connection, send it back in a `Resume` after reconnecting
+    pub session_id: String,
+    pub protocol_version: u32,
+    pub heartbeat_interval_ms: u64,
+    /// Sequence number of the last event sent so far
+    pub seq: i64,
+    /// Cluster the connection was accepted as, with the shards it owns
+    pub cluster: ClusterIdentity,
+    /// Format of every frame on the connection, from `Ready` on
+    pub encoding: GatewayEncoding,
+}
+
+/// Websocket attachment of a bot connection, kept by the runtime while the object hibernates
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BotConnection {
+    /// Session opened for the connection by its `Ready`
+    pub session_id: String,
+    pub cluster: ClusterIdentity,
+}
+
+/// Data of a `Resume` event, asking for the events missed since `seq`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResumePayload {
+    /// Session of the connection that was lost
+    pub session_id: String,
+    /// Sequence number of the last event the bot received
+    pub seq: i64,
+}
+
+/// Data of the `Resumed` event, sent after the missed events were replayed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResumedPayload {
+    pub replayed: usize,
+    pub seq: i64,
+}
+
+/// Data of the `InvalidSession` event, the bot has to resync everything from the API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InvalidSessionPayload {
+    pub reason: String,
+}
+
+/// Data of `Heartbeat` and `HeartbeatAck` events
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HeartbeatPayload {
+    /// Last sequence number received by the bot, or sent by the gateway
+    pub seq: i64,
+}
+
+#[derive(Deserialize)]
+struct Seq {
+    seq: i64,
+}
+
+#[derive(Deserialize)]
+struct Bounds {
+    oldest: Option<i64>,
+    last: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct StoredEvent {
+    message: String,
+    guild_id: Option<String>,
+}
+
+/// Sessions and sequenced outbound events of the bot gateway, in the durable object's SQLite storage
+///
+/// Every event sent to the bot gets the next sequence number and is kept for a while, so a bot
+/// that lost its connection can `Resume` and receive what it missed.
+pub struct Gateway {
+    storage: Storage,
+}
+
+impl Gateway {
+    /// Open the gateway storage, creating its tables
+    pub fn new(state: &State) -> Self {
+        let gateway = Self::open(state);
+        if let Err(e) = gateway.sql().exec(CREATE_TABLES, None) {
+            error!("Failed to create the gateway tables: {}", e);
+        }
+        gateway
+    }
+
+    /// Open the gateway storage, its tables are created by [`Gateway::new`]
+    pub fn open(state: &State) -> Self {
+        Self {
+            storage: state.storage(),
+        }
+    }
+
+    fn sql(&self) -> SqlStorage {
+        self.storage.sql()
+    }
+
+    /// Start a session for a new bot connection
+    pub fn open_session(
+        &self,
+        cluster: ClusterIdentity,
+        encoding: GatewayEncoding,
+        now: i64,
+    ) -> Result<ReadyPayload> {
+        let session_id = new_session_id()?;
+        self.sql().exec(
+            "INSERT INTO gateway_sessions (session_id, created_at) VALUES (?, ?)",
+            vec![session_id.clone().into(), now.into()],
+        )?;
+        self.sql().exec(
+            "DELETE FROM gateway_sessions WHERE session_id NOT IN (
+                SELECT session_id FROM gateway_sessions ORDER BY created_at DESC LIMIT ?
+            )",
+            vec![RESUMABLE_SESSIONS.into()],
+        )?;
+        Ok(ReadyPayload {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            heartbeat_interval_ms: HEARTBEAT_INTERVAL_MS,
+            seq: self.last_seq()?,
+            cluster,
+            encoding,
+        })
+    }
+
+    /// Give the envelope the next sequence number and keep it for replays, returning it serialized
+    ///
+    /// Events of a guild are only replayed to the cluster running its shard.
+    pub fn record(
+        &self,
+        envelope: &mut WsEnvelope<SocketSendEvent>,
+        guild_id: Option<&str>,
+        now: i64,
+    ) -> Result<String> {
+        let seqs: Vec<Seq> = (self.sql())
+            .exec(
+                "INSERT INTO gateway_events (message, guild_id, sent_at) VALUES ('', ?, ?)
+                RETURNING seq",
+                vec![guild_id.into(), now.into()],
+            )?
+            .to_array()?;
+        let seq = (seqs.first().map(|row| row.seq))
+            .ok_or_else(|| worker::Error::RustError("No sequence number returned".into()))?;
+
+        envelope.seq = Some(seq);
+        let message = serde_json::to_string(envelope)?;
+        self.sql().exec(
+            "UPDATE gateway_events SET message = ? WHERE seq = ?",
+            vec![message.clone().into(), seq.into()],
+        )?;
+        // The last event is always kept, it holds the current sequence number
+        self.sql().exec(
+            "DELETE FROM gateway_events WHERE seq < ? AND (seq <= ? OR sent_at < ?)",
+            vec![
+                seq.into(),
+                (seq - REPLAY_BUFFER_EVENTS).into(),
+                (now - REPLAY_WINDOW_MS).into(),
+            ],
+        )?;
+        Ok(message)
+    }
+
+    /// Events for the cluster sent after `resume.seq`, in order, or `None` when they can't all be
+    /// replayed
+    pub fn replay(
+        &self,
+        resume: &ResumePayload,
+        cluster: &ClusterIdentity,
+    ) -> Result<Option<Vec<String>>> {
+        let sessions: Vec<serde_json::Value> = (self.sql())
+            .exec(
+                "SELECT session_id FROM gateway_sessions WHERE session_id = ?",
+                vec![resume.session_id.clone().into()],
+            )?
+            .to_array()?;
+        if sessions.is_empty() {
+            info!("Unknown gateway session {}", resume.session_id);
+            return Ok(None);
+        }
+
+        let bounds = self.bounds()?;
+        let last = bounds.last.unwrap_or(0);
+        let oldest = bounds.oldest.unwrap_or(last + 1);
+        // Ahead of the gateway means its storage was lost, too far behind means events were dropped
+        if resume.seq > last || resume.seq + 1 < oldest {
+            info!(
+                "Cannot resume from {} with events {} to {} buffered",
+                resume.seq, oldest, last
+            );
+            return Ok(None);
+        }
+
+        let events: Vec<StoredEvent> = (self.sql())
+            .exec(
+                "SELECT message, guild_id FROM gateway_events WHERE seq > ? ORDER BY seq",
+                vec![resume.seq.into()],
+            )?
+            .to_array()?;
+        Ok(Some(
+            (events.into_iter())
+                .filter(|event| cluster.receives(event.guild_id.as_deref()))
+                .map(|event| event.message)
+                .collect(),
+        ))
+    }
+
+    /// Sequence number of the last event sent
+    pub fn last_seq(&self) -> Result<i64> {
+        Ok(self.bounds()?.last.unwrap_or(0))
+    }
+
+    fn bounds(&self) -> Result<Bounds> {
+        let bounds: Vec<Bounds> = (self.sql())
+            .exec(
+                "SELECT MIN(seq) AS oldest, MAX(seq) AS last FROM gateway_events",
+                None,
+            )?
+            .to_array()?;
+        Ok(bounds.into_iter().next().unwrap_or(Bounds {
+            oldest: None,
+            last: None,
+        }))
+    }
+}
+
+fn new_session_id() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| worker::Error::RustError(format!("No randomness: {}", e)))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,38 @@
+use tracing::warn;
+use worker::{Result, State};
+
+use super::{SocketSendEvent, alarms::AlarmOutcome, misc};
+use crate::{
+    schema::guild::GiveawaysSchema,
+    services::{giveaways, websocket::WsEnvelope},
+    state::database::{Database, DatabaseExt},
+};
+
+/// Delay before trying again when no bot is connected to announce the winners
+const BOT_OFFLINE_RETRY_MS: i64 = 60 * 1000;
+
+/// End a giveaway whose time is up and send the winners to the bot
+pub async fn end(state: &State, database: &Database, giveaway_id: &String) -> Result<AlarmOutcome> {
+    let giveaways: Vec<GiveawaysSchema> = database
+        .execute(GiveawaysSchema::get_giveaway(giveaway_id))
+        .await?;
+    let Some(giveaway) = giveaways.into_iter().next() else {
+        // Deleted, or its guild was removed
+        return Ok(AlarmOutcome::Done);
+    };
+    // Winners are only drawn once, don't draw them when nobody can announce them
+    if !misc::bot_connected(state, Some(&giveaway.guild_id)) {
+        warn!(
+            "No bot connected to end giveaway {}, retrying later",
+            giveaway_id
+        );
+        let now = chrono::Utc::now().timestamp_millis();
+        return Ok(AlarmOutcome::Reschedule(now + BOT_OFFLINE_RETRY_MS));
+    }
+
+    if let Some(ended) = giveaways::end_giveaway(database, giveaway_id).await? {
+        let envelope = WsEnvelope::new(SocketSendEvent::GiveawayEnded, ended);
+        misc::send_to_bot(state, Some(&giveaway.guild_id), envelope)?;
+    }
+    Ok(AlarmOutcome::Done)
+}
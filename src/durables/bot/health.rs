@@ -0,0 +1,102 @@
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+use worker::Env;
+
+use super::{ShardUpdatePayload, shards::ShardMap};
+
+/// Seconds without a `BotUpdate` before a shard is `stale`, see `SHARD_STALE_AFTER_SECONDS`
+pub const DEFAULT_STALE_AFTER_SECONDS: i64 = 90;
+/// Seconds without a `BotUpdate` before a shard is `zombie`, see `SHARD_ZOMBIE_AFTER_SECONDS`
+pub const DEFAULT_ZOMBIE_AFTER_SECONDS: i64 = 300;
+
+pub const STALE_STATUS: &str = "stale";
+pub const ZOMBIE_STATUS: &str = "zombie";
+/// Status of the shards of a cluster that disconnected
+pub const OFFLINE_STATUS: &str = "offline";
+
+/// Data of a `ShardUnhealthy` dashboard event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardUnhealthyPayload {
+    #[serde(flatten)]
+    pub shard: ShardUpdatePayload,
+    /// Status the shard had before it stopped reporting
+    pub previous_status: String,
+}
+
+/// How long a shard may go without reporting before it is flagged
+#[derive(Debug, Clone, Copy)]
+pub struct HealthThresholds {
+    pub stale_after_ms: i64,
+    pub zombie_after_ms: i64,
+}
+
+impl HealthThresholds {
+    pub fn from_env(env: &Env) -> Self {
+        let seconds = |name: &str, default: i64| {
+            (env.var(name).ok())
+                .and_then(|seconds| seconds.to_string().parse::<i64>().ok())
+                .filter(|seconds| *seconds > 0)
+                .unwrap_or(default)
+        };
+        let stale_after = seconds("SHARD_STALE_AFTER_SECONDS", DEFAULT_STALE_AFTER_SECONDS);
+        let zombie_after = seconds("SHARD_ZOMBIE_AFTER_SECONDS", DEFAULT_ZOMBIE_AFTER_SECONDS);
+        Self {
+            stale_after_ms: stale_after * 1000,
+            // A shard is always stale before it turns into a zombie
+            zombie_after_ms: zombie_after.max(stale_after) * 1000,
+        }
+    }
+
+    /// Status the shard should have at `now`, `None` while it is still reporting
+    fn status_at(&self, last_seen: i64, now: i64) -> Option<&'static str> {
+        match now - last_seen {
+            silent if silent >= self.zombie_after_ms => Some(ZOMBIE_STATUS),
+            silent if silent >= self.stale_after_ms => Some(STALE_STATUS),
+            _ => None,
+        }
+    }
+}
+
+/// Flag the shards that stopped reporting, returning the ones whose status changed
+pub fn check(
+    shards: &RefCell<ShardMap>,
+    thresholds: &HealthThresholds,
+    now: i64,
+) -> Vec<ShardUnhealthyPayload> {
+    let mut unhealthy = Vec::new();
+    for shard in shards.borrow_mut().values_mut() {
+        let Some(last_seen) = shard.last_seen else {
+            continue;
+        };
+        // Known to be down, nothing to detect
+        if shard.status == OFFLINE_STATUS {
+            continue;
+        }
+        let Some(status) = thresholds.status_at(last_seen, now) else {
+            continue;
+        };
+        if shard.status != status {
+            let previous_status = std::mem::replace(&mut shard.status, status.to_string());
+            unhealthy.push(ShardUnhealthyPayload {
+                shard: shard.clone(),
+                previous_status,
+            });
+        }
+    }
+    unhealthy
+}
+
+/// When the next shard crosses a threshold, `None` once every shard is a zombie or offline
+pub fn next_check(shards: &RefCell<ShardMap>, thresholds: &HealthThresholds) -> Option<i64> {
+    (shards.borrow().values())
+        .filter(|shard| shard.status != ZOMBIE_STATUS && shard.status != OFFLINE_STATUS)
+        .filter_map(|shard| {
+            let last_seen = shard.last_seen?;
+            Some(match shard.status.as_str() {
+                STALE_STATUS => last_seen + thresholds.zombie_after_ms,
+                _ => last_seen + thresholds.stale_after_ms,
+            })
+        })
+        .min()
+}
@@ -1,5 +1,13 @@
 use strum::AsRefStr;
-use worker::{Result, State};
+use worker::{Result, State, WebSocket};
+
+use super::{
+    SocketSendEvent,
+    cluster::ClusterIdentity,
+    encoding::{self, GatewayEncoding},
+    gateway::{BotConnection, Gateway},
+};
+use crate::services::websocket::WsEnvelope;
 
 #[derive(AsRefStr)]
 pub enum BotDurableTag {
@@ -9,17 +17,69 @@ pub enum BotDurableTag {
     Dashboard,
 }
 
-pub fn send_to_bot(state: State, message: &str) -> Result<()> {
+pub fn is_bot(state: &State, ws: &WebSocket) -> bool {
+    (state.get_tags(ws).iter()).any(|tag| tag == BotDurableTag::Bot.as_ref())
+}
+
+/// Identity the bot connection gave in its handshake
+pub fn cluster_of(ws: &WebSocket) -> ClusterIdentity {
+    match ws.deserialize_attachment::<BotConnection>() {
+        Ok(Some(connection)) => connection.cluster,
+        // Connections accepted before attachments were set own every shard
+        _ => ClusterIdentity::everything(0),
+    }
+}
+
+/// Bot connections of the cluster running the guild's shard, or every one when `None`
+fn bot_connections(state: &State, guild_id: Option<&str>) -> Vec<WebSocket> {
     let connections = state.get_websockets_with_tag(BotDurableTag::Bot.as_ref());
-    for ws in connections.iter() {
-        if let Err(e) = ws.send_with_str(message) {
-            tracing::error!("Failed to send message to bot: {}", e);
+    (connections.into_iter())
+        .filter(|ws| cluster_of(ws).receives(guild_id))
+        .collect()
+}
+
+/// Whether a bot connection would receive an event of the guild, or any event when `None`
+pub fn bot_connected(state: &State, guild_id: Option<&str>) -> bool {
+    !bot_connections(state, guild_id).is_empty()
+}
+
+/// Send a sequenced event to the cluster running the guild's shard, or to every bot connection
+/// when it isn't tied to a guild, returning how many received it
+///
+/// The event is kept for replays even when no bot is connected, callers that retry on their
+/// own should check [`bot_connected`] first.
+pub fn send_to_bot(
+    state: &State,
+    guild_id: Option<&str>,
+    mut envelope: WsEnvelope<SocketSendEvent>,
+) -> Result<usize> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let message = Gateway::open(state).record(&mut envelope, guild_id, now)?;
+    let connections = bot_connections(state, guild_id);
+    let encodings: Vec<GatewayEncoding> = (connections.iter())
+        .map(|ws| GatewayEncoding::of(state, ws))
+        .collect();
+    // Encoded once for every binary connection
+    let binary = (encodings.contains(&GatewayEncoding::Bincode))
+        .then(|| encoding::to_bytes(&envelope))
+        .transpose()?;
+
+    let mut sent = 0;
+    for (ws, encoding) in connections.iter().zip(encodings) {
+        let result = match (encoding, &binary) {
+            (GatewayEncoding::Bincode, Some(bytes)) => ws.send_with_bytes(bytes),
+            _ => ws.send_with_str(&message),
+        };
+        match result {
+            Ok(()) => sent += 1,
+            Err(e) => tracing::error!("Failed to send message to bot: {}", e),
         }
     }
-    Ok(())
+    Ok(sent)
 }
 
-pub fn send_to_guild(state: State, guild_id: &str, message: &str) -> Result<()> {
+/// Send a message to every dashboard following the guild
+pub fn send_to_guild(state: &State, guild_id: &str, message: &str) -> Result<()> {
     let connections = state.get_websockets_with_tag(guild_id);
     for ws in connections.iter() {
         if let Err(e) = ws.send_with_str(message) {
@@ -1,20 +1,54 @@
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::HashMap};
-use tracing::info;
+use std::cell::RefCell;
+use tracing::{info, warn};
 
 use worker::{
-    DurableObject, Env, Method, Request, Response, Result, State, Stub, WebSocket,
+    DurableObject, Env, Method, Request, RequestInit, Response, Result, State, Stub, WebSocket,
     WebSocketIncomingMessage, WebSocketPair, durable_object,
 };
 
-use crate::services::websocket::WsEnvelope;
+use crate::{services::websocket::WsEnvelope, state::database::Database};
 
 mod alarms;
+mod birthdays;
+mod cluster;
+mod config;
+mod dashboard;
+mod encoding;
+mod gateway;
+mod giveaways;
+mod health;
 mod misc;
+mod reminders;
+mod rpc;
+mod shard_history;
+mod shard_state;
 mod shards;
+mod timed_messages;
 
-use alarms::ScheduledAlarm;
+use alarms::{AlarmOutcome, AlarmScheduler, ScheduleAlarmRequest};
+use config::{ConfigUpdateRequest, ConfigVersions};
+use gateway::{
+    BotConnection, Gateway, HeartbeatPayload, InvalidSessionPayload, ResumePayload, ResumedPayload,
+};
+use health::HealthThresholds;
+use shard_history::ShardHistory;
+use shard_state::ShardState;
+use shards::ShardMap;
 
+pub use alarms::ScheduledAlarm;
+pub use cluster::ClusterIdentity;
+pub use config::{ConfigSection, ConfigUpdatedPayload};
+pub use dashboard::{
+    DASHBOARD_GUILDS_HEADER, DashboardEvent, GiveawayEntriesPayload, MAX_DASHBOARD_GUILDS,
+    SettingsUpdatedPayload,
+};
+pub use encoding::GatewayEncoding;
+pub use gateway::{PROTOCOL_VERSION, ReadyPayload};
+pub use health::ShardUnhealthyPayload;
+pub use reminders::{ReminderDuePayload, ReminderTarget};
+pub use rpc::{RpcError, RpcErrorCode, RpcMethod, RpcRequest, RpcResponse};
+pub use shard_history::{HistoryWindow, ShardHistoryPayload, ShardSample, StatusTransition};
 pub use shards::{PingPayload, ShardUpdatePayload};
 
 const DURABLE_NAME: &str = "BOTROOM";
@@ -23,8 +57,13 @@ const DURABLE_NAME: &str = "BOTROOM";
 pub struct BotDurable {
     state: State,
     env: Env,
-    alarms: RefCell<HashMap<i64, Vec<ScheduledAlarm>>>,
-    shards: RefCell<Vec<ShardUpdatePayload>>,
+    scheduler: AlarmScheduler,
+    config_versions: ConfigVersions,
+    gateway: Gateway,
+    shard_history: ShardHistory,
+    shard_state: ShardState,
+    /// Cached view of `shard_state`, read back when the object wakes up
+    shards: RefCell<ShardMap>,
     avg_latency: RefCell<Option<i64>>,
 }
 
@@ -36,21 +75,209 @@ impl super::DurableFetch for BotDurable {
 }
 
 impl BotDurable {
-    fn handle_event(&self, ws: WebSocket, envelope: WsEnvelope<SocketReceiveEvent>) -> Result<()> {
+    /// The bot's durable object, named after its token
+    pub fn bot_stub(env: &Env) -> Result<Stub> {
+        let bot_token = env.secret("DISCORD_BOT_TOKEN")?.to_string();
+        <Self as super::DurableFetch>::fetch_object(env, &bot_token)
+    }
+
+    /// Run `alarm` at `run_at` (Unix milliseconds), replacing the pending alarm with the same key
+    pub async fn schedule_alarm(env: &Env, run_at: i64, alarm: ScheduledAlarm) -> Result<()> {
+        let body = serde_json::to_string(&ScheduleAlarmRequest { run_at, alarm })?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post).with_body(Some(body.into()));
+        Self::durable_request(env, "/alarms", &init).await
+    }
+
+    /// Drop the pending alarm stored under `key`, if any
+    pub async fn cancel_alarm(env: &Env, key: &str) -> Result<()> {
+        let mut init = RequestInit::new();
+        init.with_method(Method::Delete);
+        let path = format!("/alarms/{}", urlencoding::encode(key));
+        Self::durable_request(env, &path, &init).await
+    }
+
+    /// Push an event to the cluster running the guild's shard, or to every connected bot when
+    /// it isn't tied to a guild
+    pub async fn send_event(
+        env: &Env,
+        guild_id: Option<&str>,
+        envelope: &WsEnvelope<SocketSendEvent>,
+    ) -> Result<()> {
+        let body = serde_json::to_string(envelope)?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post).with_body(Some(body.into()));
+        let path = match guild_id {
+            Some(guild_id) => format!("/events?guild={}", urlencoding::encode(guild_id)),
+            None => "/events".to_string(),
+        };
+        Self::durable_request(env, &path, &init).await
+    }
+
+    /// Tell the bot a section of the guild's configuration changed, with the guild's next version
+    pub async fn publish_config_update(
+        env: &Env,
+        guild_id: &str,
+        section: ConfigSection,
+    ) -> Result<()> {
+        let body = serde_json::to_string(&ConfigUpdateRequest { section })?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post).with_body(Some(body.into()));
+        let path = format!("/config/{}", urlencoding::encode(guild_id));
+        Self::durable_request(env, &path, &init).await
+    }
+
+    /// Push an event to the dashboards following the guild, if any
+    pub async fn send_dashboard_event(
+        env: &Env,
+        guild_id: &str,
+        envelope: &WsEnvelope<DashboardEvent>,
+    ) -> Result<()> {
+        let body = serde_json::to_string(envelope)?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post).with_body(Some(body.into()));
+        let path = format!("/dashboard/{}", urlencoding::encode(guild_id));
+        Self::durable_request(env, &path, &init).await
+    }
+
+    /// Latency, status and member count history of every shard over `window`
+    pub async fn shard_history(
+        env: &Env,
+        window: HistoryWindow,
+    ) -> Result<Vec<ShardHistoryPayload>> {
+        let window = serde_json::to_value(window)?;
+        let url = format!(
+            "https://{}/history?window={}",
+            DURABLE_NAME,
+            window.as_str().unwrap_or_default()
+        );
+        let mut response = Self::bot_stub(env)?.fetch_with_str(&url).await?;
+        match response.status_code() {
+            200..=299 => response.json().await,
+            status => Err(worker::Error::RustError(format!(
+                "Durable object answered {} to /history",
+                status
+            ))),
+        }
+    }
+
+    async fn durable_request(env: &Env, path: &str, init: &RequestInit) -> Result<()> {
+        let request = Request::new_with_init(&format!("https://{}{}", DURABLE_NAME, path), init)?;
+        let response = Self::bot_stub(env)?.fetch_with_request(request).await?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(worker::Error::RustError(format!(
+                "Durable object answered {} to {}",
+                status, path
+            ))),
+        }
+    }
+
+    fn database(&self) -> Database {
+        Database::new(&self.env, "DB")
+    }
+
+    async fn handle_event(
+        &self,
+        ws: WebSocket,
+        envelope: WsEnvelope<SocketReceiveEvent>,
+    ) -> Result<()> {
         let payload = envelope.data;
+        let encoding = GatewayEncoding::of(&self.state, &ws);
         match envelope.event {
             SocketReceiveEvent::BotUpdate => {
-                if let Some(pong_message) =
-                    shards::handle_ping_shard(&self.avg_latency, &self.shards, payload)
-                {
-                    ws.send(&pong_message)?;
+                let cluster = misc::cluster_of(&ws);
+                if let Some(pong_message) = shards::handle_ping_shard(
+                    &self.avg_latency,
+                    &self.shards,
+                    &self.shard_history,
+                    &self.shard_state,
+                    &cluster,
+                    payload,
+                ) {
+                    encoding.send(&ws, &pong_message)?;
+                }
+                dashboard::push_shard_status(&self.state, &shards::snapshot(&self.shards));
+                // Pushed back on every update, so it only runs once a shard goes quiet
+                let thresholds = HealthThresholds::from_env(&self.env);
+                if let Some(at) = health::next_check(&self.shards, &thresholds) {
+                    self.scheduler.schedule(at, &ScheduledAlarm::ShardHealth)?;
+                    self.scheduler.update_system_alarm().await?;
+                }
+            }
+            SocketReceiveEvent::Heartbeat => {
+                let seq = self.gateway.last_seq()?;
+                let ack = WsEnvelope::new(SocketSendEvent::HeartbeatAck, HeartbeatPayload { seq });
+                encoding.send(&ws, &ack)?;
+            }
+            SocketReceiveEvent::Resume => {
+                let resume: ResumePayload = serde_json::from_value(payload)?;
+                let Some(missed) = self.gateway.replay(&resume, &misc::cluster_of(&ws))? else {
+                    let reason = "Missed events are no longer available".to_string();
+                    let invalid = InvalidSessionPayload { reason };
+                    let invalid = WsEnvelope::new(SocketSendEvent::InvalidSession, invalid);
+                    encoding.send(&ws, &invalid)?;
+                    return Ok(());
+                };
+                // Sent back to back without yielding, so no new event can slip in between
+                for message in missed.iter() {
+                    encoding.send_recorded(&ws, message)?;
                 }
+                let resumed = ResumedPayload {
+                    replayed: missed.len(),
+                    seq: self.gateway.last_seq()?,
+                };
+                info!(
+                    "Bot resumed session {}, replayed {} event(s)",
+                    resume.session_id, resumed.replayed
+                );
+                encoding.send(&ws, &WsEnvelope::new(SocketSendEvent::Resumed, resumed))?;
             }
+            SocketReceiveEvent::Request => {
+                let response =
+                    rpc::handle_request(&self.database(), envelope.nonce.as_ref(), payload).await;
+                // Answers only go to the caller and aren't sequenced, a lost one is simply retried
+                let mut reply = WsEnvelope::new(SocketSendEvent::Response, response);
+                reply.nonce = envelope.nonce;
+                encoding.send(&ws, &reply)?;
+            }
+            SocketReceiveEvent::ReminderAck => {
+                let id = reminders::acknowledge(&self.database(), payload).await?;
+                self.scheduler
+                    .cancel(&ScheduledAlarm::Reminder { id }.key())?;
+                self.scheduler.update_system_alarm().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark the shards of a cluster offline once its last connection closed
+    fn cluster_disconnected(&self, ws: &WebSocket) -> Result<()> {
+        if !misc::is_bot(&self.state, ws) {
+            return Ok(());
+        }
+        let cluster = misc::cluster_of(ws);
+        // The closing socket may still be listed, anything more is a newer connection
+        if self.state.get_websockets_with_tag(&cluster.tag()).len() > 1 {
+            return Ok(());
         }
+        let offline = shards::mark_offline(&self.shards, cluster.cluster_id);
+        if offline.is_empty() {
+            return Ok(());
+        }
+
+        warn!(
+            "Cluster {} disconnected, its shards are offline",
+            cluster.cluster_id
+        );
+        let now = chrono::Utc::now().timestamp_millis();
+        self.shard_state.save(&offline)?;
+        self.shard_history.record(&offline, now)?;
+        dashboard::push_shard_status(&self.state, &shards::snapshot(&self.shards));
         Ok(())
     }
 
-    fn websocket_upgrade(&self, req: Request) -> Result<(WebSocket, Option<i64>)> {
+    fn websocket_upgrade(&self, req: Request) -> Result<WebSocket> {
         let name = self.state.id().to_string();
         info!(
             "Handling WebSocket upgrade for BotDurable with name: {}",
@@ -63,23 +290,52 @@ impl BotDurable {
 
         match user_agent.split('.').collect::<Vec<&str>>().as_slice() {
             ["DiscordBot"] => {
-                // initialize the alarms if this is the first connection
-                let timestamp = None;
-                // let timestamp = if self.alarms.borrow().is_empty() {
-                //     let execute_time = chrono::Utc::now().timestamp_millis() + 60000; // 1 minute from now
-                //     let initial_alarms = ScheduledAlarm::initial_alarms();
-                //     (self.alarms.borrow_mut()).insert(execute_time, initial_alarms);
-                //     Some(execute_time)
-                // } else {
-                //     None
-                // };
+                let url = req.url()?;
+                let version = url.query_pairs().find(|(key, _)| key == "v");
+                if let Some((_, version)) = version
+                    && version != PROTOCOL_VERSION.to_string()
+                {
+                    let msg = format!(
+                        "Unsupported gateway version {}, expected {}",
+                        version, PROTOCOL_VERSION
+                    );
+                    tracing::error!("{}", msg);
+                    return Err(worker::Error::Internal((&msg).into()));
+                }
+
+                let cluster = ClusterIdentity::from_url(&url).inspect_err(|e| {
+                    tracing::error!("Rejected bot connection: {}", e);
+                })?;
+                let encoding = GatewayEncoding::from_url(&url).inspect_err(|e| {
+                    tracing::error!("Rejected bot connection: {}", e);
+                })?;
 
                 let ws = WebSocketPair::new()?;
                 let server = ws.server;
-                self.state
-                    .accept_websocket_with_tags(&server, &[misc::BotDurableTag::Bot.as_ref()]);
-                Ok((ws.client, timestamp))
+                let tags = [
+                    misc::BotDurableTag::Bot.as_ref(),
+                    &cluster.tag(),
+                    &encoding.tag(),
+                ];
+                self.state.accept_websocket_with_tags(&server, &tags);
+                let now = chrono::Utc::now().timestamp_millis();
+                let ready = self.gateway.open_session(cluster, encoding, now)?;
+                // Read back from the socket itself, so it survives hibernation
+                server.serialize_attachment(BotConnection {
+                    session_id: ready.session_id.clone(),
+                    cluster: ready.cluster.clone(),
+                })?;
+                info!(
+                    "Cluster {} connected with gateway session {}, shards {}-{}",
+                    ready.cluster.cluster_id,
+                    ready.session_id,
+                    ready.cluster.first_shard,
+                    ready.cluster.last_shard
+                );
+                encoding.send(&server, &WsEnvelope::new(SocketSendEvent::Ready, ready))?;
+                Ok(ws.client)
             }
+            ["Dashboard"] => dashboard::accept(&self.state, &req, &shards::snapshot(&self.shards)),
             _ => {
                 let msg = format!("Unrecognized User-Agent: {}", user_agent);
                 tracing::error!("{}", msg);
@@ -89,79 +345,163 @@ impl BotDurable {
     }
 
     async fn handle_system_alarm(&self) -> Result<()> {
-        let current_time = chrono::Utc::now().timestamp_millis();
-
-        let mut removed_alarms = Vec::new();
-        let keys: Vec<i64> = self.alarms.borrow().keys().cloned().collect();
-
-        for &time in keys.iter() {
-            if time <= current_time {
-                let alarms_at_time = self.alarms.borrow().get(&time).cloned();
-                if let Some(alarms_at_time) = alarms_at_time {
-                    for alarm in alarms_at_time.into_iter() {
-                        if let Err(e) = self.process_alarm(alarm).await {
-                            tracing::error!("Failed to process alarm: {}", e);
-                        }
-                    }
-                    removed_alarms.push(time);
+        let now = chrono::Utc::now().timestamp_millis();
+
+        for entry in self.scheduler.due(now)? {
+            let alarm = match entry.alarm() {
+                Ok(alarm) => alarm,
+                Err(e) => {
+                    tracing::error!("Dropping unreadable alarm {}: {}", entry.key, e);
+                    self.scheduler.complete(&entry, AlarmOutcome::Done)?;
+                    continue;
                 }
+            };
+            match self.process_alarm(alarm).await {
+                Ok(outcome) => self.scheduler.complete(&entry, outcome)?,
+                Err(e) => self.scheduler.fail(&entry, &e.to_string(), now)?,
             }
         }
 
-        // Remove processed alarms
-        for time in removed_alarms {
-            self.alarms.borrow_mut().remove(&time);
-        }
-
-        alarms::update_system_alarm(&self.state, &self.alarms).await
+        self.scheduler.update_system_alarm().await
     }
 
-    async fn process_alarm(&self, alarm: ScheduledAlarm) -> worker::Result<()> {
-        // Placeholder for processing logic
-        // This function should handle the alarm based on its type and perform necessary actions
+    /// Flag the shards that went quiet, then wait for the next one to cross a threshold
+    fn check_shard_health(&self) -> Result<AlarmOutcome> {
+        let thresholds = HealthThresholds::from_env(&self.env);
+        let now = chrono::Utc::now().timestamp_millis();
+        let unhealthy = health::check(&self.shards, &thresholds, now);
+        if !unhealthy.is_empty() {
+            let flagged: Vec<ShardUpdatePayload> =
+                unhealthy.iter().map(|shard| shard.shard.clone()).collect();
+            self.shard_state.save(&flagged)?;
+            self.shard_history.record(&flagged, now)?;
+            for shard in unhealthy.iter() {
+                warn!(
+                    "Shard {} is {}, last seen at {:?}",
+                    shard.shard.shard_id, shard.shard.status, shard.shard.last_seen
+                );
+                dashboard::push_shard_unhealthy(&self.state, shard);
+            }
+            dashboard::push_shard_status(&self.state, &shards::snapshot(&self.shards));
+        }
+        Ok(match health::next_check(&self.shards, &thresholds) {
+            Some(at) => AlarmOutcome::Reschedule(at),
+            None => AlarmOutcome::Done,
+        })
+    }
 
-        let result: (i64, ScheduledAlarm) = match alarm {
-            _ => Err(worker::Error::Internal("Unrecognized alarm type".into())),
-        }?;
-        let (next_execute_time, next_alarm) = result;
-        self.alarms
-            .borrow_mut()
-            .entry(next_execute_time)
-            .or_default()
-            .push(next_alarm);
-        Ok(())
+    /// Run a single alarm, which may be delivered more than once
+    async fn process_alarm(&self, alarm: ScheduledAlarm) -> Result<AlarmOutcome> {
+        match alarm {
+            // Nothing is synchronised yet, kept so entries scheduled by older builds drain
+            ScheduledAlarm::DatabaseSync => Ok(AlarmOutcome::Done),
+            ScheduledAlarm::Reminder { id } => {
+                reminders::deliver(&self.state, &self.database(), id).await
+            }
+            ScheduledAlarm::GiveawayEnd { giveaway_id } => {
+                giveaways::end(&self.state, &self.database(), &giveaway_id).await
+            }
+            ScheduledAlarm::TimedMessage { id } => {
+                timed_messages::run(&self.state, &self.database(), id).await
+            }
+            ScheduledAlarm::Birthdays { guild_id } => {
+                birthdays::announce(&self.state, &self.database(), &guild_id).await
+            }
+            ScheduledAlarm::ShardHealth => self.check_shard_health(),
+        }
     }
 }
 
 impl DurableObject for BotDurable {
     fn new(state: State, env: Env) -> Self {
         let name = state.id().to_string();
+        // Runs again every time the object wakes up from hibernation
+        let shard_state = ShardState::new(&state);
+        let (shards, avg_latency) = shard_state.load().unwrap_or_else(|e| {
+            tracing::error!("Failed to load the stored shard state: {}", e);
+            (ShardMap::new(), None)
+        });
         BotDurable {
+            scheduler: AlarmScheduler::new(&state),
+            config_versions: ConfigVersions::new(&state),
+            gateway: Gateway::new(&state),
+            shard_history: ShardHistory::new(&state),
+            shard_state,
             state,
             env,
-            alarms: HashMap::new().into(),
-            shards: Vec::new().into(),
-            avg_latency: None.into(),
+            shards: shards.into(),
+            avg_latency: avg_latency.into(),
         }
     }
 
-    async fn fetch(&self, req: Request) -> Result<Response> {
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
         if let Ok(Some(upgrade)) = req.headers().get("Upgrade")
             && upgrade.to_lowercase() == "websocket"
         {
-            // if let Some(timestamp) = timestamp {
-            //     let alarm_options = alarms::default_options();
-            //     (self.state.storage())
-            //         .set_alarm_with_options(timestamp, alarm_options)
-            //         .await?;
-            // }
-            let (ws, timestamp) = self.websocket_upgrade(req)?;
+            let ws = self.websocket_upgrade(req)?;
             return Response::from_websocket(ws);
         }
         let url = req.url()?;
         let method = req.method();
         match (method, url.path()) {
             (Method::Get, "/status") => shards::get_shards(&self.shards),
+            (Method::Get, "/history") => {
+                let window = match url.query_pairs().find(|(key, _)| key == "window") {
+                    Some((_, window)) => match serde_json::from_value(window.into()) {
+                        Ok(window) => window,
+                        Err(_) => return Response::error("Window must be 1h, 24h or 7d", 400),
+                    },
+                    None => HistoryWindow::default(),
+                };
+                let now = chrono::Utc::now().timestamp_millis();
+                Response::from_json(&self.shard_history.window(window, now)?)
+            }
+            (Method::Get, "/alarms") => Response::from_json(&self.scheduler.pending()?),
+            (Method::Post, "/alarms") => {
+                let request: ScheduleAlarmRequest = req.json().await?;
+                self.scheduler.schedule(request.run_at, &request.alarm)?;
+                self.scheduler.update_system_alarm().await?;
+                Response::ok("Alarm scheduled")
+            }
+            (Method::Post, "/events") => {
+                let envelope: WsEnvelope<SocketSendEvent> = req.json().await?;
+                let guild_id = url.query_pairs().find(|(key, _)| key == "guild");
+                let guild_id = guild_id.as_ref().map(|(_, guild_id)| guild_id.as_ref());
+                match misc::send_to_bot(&self.state, guild_id, envelope)? {
+                    0 => Ok(Response::ok("Event kept until the bot resumes")?.with_status(202)),
+                    _ => Response::ok("Event sent"),
+                }
+            }
+            (Method::Post, path) if path.starts_with("/config/") => {
+                let guild_id = urlencoding::decode(&path["/config/".len()..])
+                    .map_err(|e| worker::Error::RustError(e.to_string()))?
+                    .into_owned();
+                let request: ConfigUpdateRequest = req.json().await?;
+                // Versioned and sent without yielding, so events leave in version order
+                let version = self.config_versions.bump(&guild_id)?;
+                let payload = ConfigUpdatedPayload {
+                    guild_id,
+                    section: request.section,
+                    version,
+                };
+                let envelope = WsEnvelope::new(SocketSendEvent::ConfigUpdated, &payload);
+                misc::send_to_bot(&self.state, Some(&payload.guild_id), envelope)?;
+                Response::from_json(&payload)
+            }
+            (Method::Post, path) if path.starts_with("/dashboard/") => {
+                let guild_id = urlencoding::decode(&path["/dashboard/".len()..])
+                    .map_err(|e| worker::Error::RustError(e.to_string()))?;
+                let message = req.text().await?;
+                misc::send_to_guild(&self.state, &guild_id, &message)?;
+                Response::ok("Event sent")
+            }
+            (Method::Delete, path) if path.starts_with("/alarms/") => {
+                let key = urlencoding::decode(&path["/alarms/".len()..])
+                    .map_err(|e| worker::Error::RustError(e.to_string()))?;
+                self.scheduler.cancel(&key)?;
+                self.scheduler.update_system_alarm().await?;
+                Response::ok("Alarm cancelled")
+            }
             _ => Response::error("Not Found", 404),
         }
     }
@@ -171,28 +511,33 @@ impl DurableObject for BotDurable {
         ws: WebSocket,
         message: WebSocketIncomingMessage,
     ) -> Result<()> {
-        if let WebSocketIncomingMessage::String(text) = message {
-            return match serde_json::from_str(&text) {
-                Ok(envelope) => self.handle_event(ws, envelope),
-                Err(e) => {
-                    tracing::error!("Failed to parse message: {}", e);
-                    Err(worker::Error::Internal("Invalid message format".into()))
-                }
-            };
+        // Dashboards only listen, events are reserved to the bot
+        if dashboard::is_dashboard(&self.state, &ws) {
+            return Ok(());
+        }
+        match encoding::decode(message) {
+            Ok(envelope) => self.handle_event(ws, envelope).await,
+            Err(e) => {
+                tracing::error!("Failed to parse message: {}", e);
+                Err(worker::Error::Internal("Invalid message format".into()))
+            }
         }
-        Ok(())
     }
 
     async fn alarm(&self) -> Result<Response> {
-        // if let Err(e) = self.handle_system_alarm().await {
-        //     tracing::error!("Failed to handle system alarm: {}", e);
-        // }
+        if let Err(e) = self.handle_system_alarm().await {
+            tracing::error!("Failed to handle system alarm: {}", e);
+            // Entries stay stored, make sure they are picked up again
+            if let Err(e) = self.scheduler.update_system_alarm().await {
+                tracing::error!("Failed to re-arm system alarm: {}", e);
+            }
+        }
         Response::ok("Alarm handled")
     }
 
     async fn websocket_close(
         &self,
-        _: worker::WebSocket,
+        ws: worker::WebSocket,
         code: usize,
         reason: String,
         was_clean: bool,
@@ -203,7 +548,7 @@ impl DurableObject for BotDurable {
             reason,
             was_clean
         );
-        Ok(())
+        self.cluster_disconnected(&ws)
     }
 
     async fn websocket_error(&self, _: WebSocket, error: worker::Error) -> Result<()> {
@@ -212,14 +557,35 @@ impl DurableObject for BotDurable {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Serialize, Default)]
+/// Events sent to the bot, binary frames encode them by position so new ones go last
+#[derive(Hash, Eq, PartialEq, Serialize, Deserialize, Default)]
 pub enum SocketSendEvent {
+    /// First event of every bot connection, with its gateway session
     #[default]
     Ready,
     BotPong,
+    ReminderDue,
+    GiveawayEnded,
+    TimedMessageDue,
+    BirthdayAnnouncement,
+    ConfigUpdated,
+    HeartbeatAck,
+    Resumed,
+    /// The bot can't resume and has to resync everything from the API
+    InvalidSession,
+    /// Answer to a `Request`, carrying its nonce
+    Response,
 }
 
+/// Events sent by the bot, binary frames encode them by position so new ones go last
 #[derive(Hash, Eq, PartialEq, Deserialize)]
 pub enum SocketReceiveEvent {
     BotUpdate,
+    ReminderAck,
+    /// Keeps the connection alive, answered with `HeartbeatAck`
+    Heartbeat,
+    /// Sent after reconnecting, answered with the missed events then `Resumed`, or `InvalidSession`
+    Resume,
+    /// Calls an [`RpcMethod`], answered with a `Response` carrying the same nonce
+    Request,
 }
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+use worker::{Result, State};
+
+use super::{SocketSendEvent, alarms::AlarmOutcome, misc};
+use crate::{
+    schema::guild::RemindersSchema,
+    services::websocket::WsEnvelope,
+    state::database::{Database, DatabaseExt},
+};
+
+/// Time the bot has to acknowledge a reminder before it is sent again
+const ACK_TIMEOUT_MS: i64 = 60 * 1000;
+/// Delay before trying again when no bot is connected
+const BOT_OFFLINE_RETRY_MS: i64 = 60 * 1000;
+
+/// Where the bot should send a reminder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReminderTarget {
+    Channel { channel_id: String },
+    Dm { user_id: String },
+}
+
+/// Data of a `ReminderDue` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReminderDuePayload {
+    pub id: i64,
+    pub user_id: String,
+    pub guild_id: String,
+    pub message: String,
+    pub remind_at: String,
+    pub target: ReminderTarget,
+}
+
+impl From<RemindersSchema> for ReminderDuePayload {
+    fn from(reminder: RemindersSchema) -> Self {
+        let target = match reminder.channel_id {
+            Some(channel_id) => ReminderTarget::Channel { channel_id },
+            None => ReminderTarget::Dm {
+                user_id: reminder.user_id.clone(),
+            },
+        };
+        ReminderDuePayload {
+            id: reminder.id,
+            user_id: reminder.user_id,
+            guild_id: reminder.guild_id,
+            message: reminder.message,
+            remind_at: reminder.remind_at,
+            target,
+        }
+    }
+}
+
+/// Data of a `ReminderAck` event, sent by the bot once the reminder was posted
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReminderAckPayload {
+    pub id: i64,
+}
+
+/// Push a due reminder to the bot
+///
+/// The reminder stays stored and is sent again after [`ACK_TIMEOUT_MS`] until the bot acknowledges
+/// it, so the bot has to ignore ids it already delivered.
+pub async fn deliver(state: &State, database: &Database, id: i64) -> Result<AlarmOutcome> {
+    let reminders: Vec<RemindersSchema> =
+        database.execute(RemindersSchema::get_reminder(id)).await?;
+    let Some(reminder) = reminders.into_iter().next() else {
+        // Cancelled, or its guild was removed
+        info!("Reminder {} no longer exists, skipping", id);
+        return Ok(AlarmOutcome::Done);
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+    // Retried here, so don't fill the replay buffer with copies while the bot is away
+    if !misc::bot_connected(state, Some(&reminder.guild_id)) {
+        warn!(
+            "No bot connected to deliver reminder {}, retrying later",
+            id
+        );
+        return Ok(AlarmOutcome::Reschedule(now + BOT_OFFLINE_RETRY_MS));
+    }
+    let payload = ReminderDuePayload::from(reminder);
+    let envelope = WsEnvelope::new(SocketSendEvent::ReminderDue, &payload);
+    misc::send_to_bot(state, Some(&payload.guild_id), envelope)?;
+    Ok(AlarmOutcome::Reschedule(now + ACK_TIMEOUT_MS))
+}
+
+/// Remove a reminder the bot delivered, returning the id so its alarm can be cancelled
+pub async fn acknowledge(database: &Database, payload: Value) -> Result<i64> {
+    let ack: ReminderAckPayload = serde_json::from_value(payload)?;
+    let _: Vec<RemindersSchema> = database
+        .execute(RemindersSchema::delete_reminder(ack.id))
+        .await?;
+    info!("Reminder {} delivered", ack.id);
+    Ok(ack.id)
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::{error, warn};
+
+use crate::{
+    schema::guild::{PermissionRolesSchema, PermissionUsersSchema, SettingsBody, UserLevelsSchema},
+    services::cache::{SettingsCache, SettingsSection},
+    state::database::{Database, DatabaseExt},
+};
+
+/// Methods the bot can call over the gateway with a `Request`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcMethod {
+    /// [`GuildParams`], answered with the guild's settings, unset ones are `null`
+    GetGuildSettings,
+    /// [`ResolvePermissionsParams`], answered with [`ResolvedPermissions`]
+    ResolvePermissions,
+    /// [`AwardXpParams`], answered with the member's new level row
+    AwardXp,
+}
+
+/// Data of a `Request` event, the envelope's `nonce` identifies it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcRequest {
+    pub method: RpcMethod,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Data of a `Response` event, with either a `result` or an `error`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcError {
+    pub code: RpcErrorCode,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcErrorCode {
+    /// Missing nonce, or a method that doesn't exist
+    InvalidRequest,
+    /// The params don't match the method
+    InvalidParams,
+    /// The method failed, it may succeed when called again
+    Internal,
+}
+
+impl RpcError {
+    fn new(code: RpcErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GuildParams {
+    pub guild_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolvePermissionsParams {
+    pub guild_id: String,
+    pub user_id: String,
+    /// Roles the member has, the bot knows them from the gateway
+    #[serde(default)]
+    pub role_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolvedPermissions {
+    /// Permission keys granted to the member or one of their roles, sorted
+    pub permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AwardXpParams {
+    pub guild_id: String,
+    pub user_id: String,
+    pub xp: i32,
+}
+
+/// Answer a `Request` from the bot, failures are returned to the bot rather than raised
+pub async fn handle_request(
+    database: &Database,
+    nonce: Option<&String>,
+    payload: Value,
+) -> RpcResponse {
+    match dispatch(database, nonce, payload).await {
+        Ok(result) => RpcResponse {
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => {
+            warn!("Request {:?} failed: {:?}", nonce, e);
+            RpcResponse {
+                result: None,
+                error: Some(e),
+            }
+        }
+    }
+}
+
+async fn dispatch(database: &Database, nonce: Option<&String>, payload: Value) -> RpcResult {
+    if nonce.is_none() {
+        return Err(RpcError::new(
+            RpcErrorCode::InvalidRequest,
+            "Requests need a nonce",
+        ));
+    }
+    let request: RpcRequest = serde_json::from_value(payload)
+        .map_err(|e| RpcError::new(RpcErrorCode::InvalidRequest, e.to_string()))?;
+    match request.method {
+        RpcMethod::GetGuildSettings => {
+            handle_get_guild_settings(database, params(request.params)?).await
+        }
+        RpcMethod::ResolvePermissions => {
+            handle_resolve_permissions(database, params(request.params)?).await
+        }
+        RpcMethod::AwardXp => handle_award_xp(database, params(request.params)?).await,
+    }
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(RpcErrorCode::InvalidParams, e.to_string()))
+}
+
+fn internal(context: &str, e: impl std::fmt::Display) -> RpcError {
+    error!("{}: {}", context, e);
+    RpcError::new(RpcErrorCode::Internal, context)
+}
+
+fn to_result(value: impl Serialize) -> RpcResult {
+    serde_json::to_value(value).map_err(|e| internal("Failed to serialize the result", e))
+}
+
+async fn handle_get_guild_settings(database: &Database, params: GuildParams) -> RpcResult {
+    let cache = SettingsCache::new();
+    if let Some(settings) = cache
+        .get::<SettingsBody>(&params.guild_id, SettingsSection::All)
+        .await
+    {
+        return to_result(settings);
+    }
+
+    let settings: Vec<SettingsBody> = (database.execute(SettingsBody::get(&params.guild_id)).await)
+        .map_err(|e| internal("Failed to get settings", e))?;
+    match settings.into_iter().next() {
+        Some(settings) => {
+            cache
+                .put(&params.guild_id, SettingsSection::All, &settings)
+                .await;
+            to_result(settings)
+        }
+        None => to_result(SettingsBody {
+            prefix: None,
+            language: None,
+            timezone: None,
+            colour: None,
+        }),
+    }
+}
+
+async fn handle_resolve_permissions(
+    database: &Database,
+    params: ResolvePermissionsParams,
+) -> RpcResult {
+    let query = PermissionUsersSchema::granted_to_user(&params.guild_id, &params.user_id);
+    let granted: Vec<PermissionUsersSchema> = (database.execute(query).await)
+        .map_err(|e| internal("Failed to get user permissions", e))?;
+    let mut permissions: BTreeSet<String> = granted.into_iter().map(|row| row.permission).collect();
+
+    if !params.role_ids.is_empty() {
+        let query = PermissionRolesSchema::granted_to_roles(&params.guild_id, &params.role_ids);
+        let granted: Vec<PermissionRolesSchema> = (database.execute(query).await)
+            .map_err(|e| internal("Failed to get role permissions", e))?;
+        permissions.extend(granted.into_iter().map(|row| row.permission));
+    }
+
+    to_result(ResolvedPermissions {
+        permissions: permissions.into_iter().collect(),
+    })
+}
+
+async fn handle_award_xp(database: &Database, params: AwardXpParams) -> RpcResult {
+    if params.xp <= 0 {
+        return Err(RpcError::new(
+            RpcErrorCode::InvalidParams,
+            "xp must be positive",
+        ));
+    }
+    let query = UserLevelsSchema::award_xp(&params.user_id, &params.guild_id, params.xp);
+    let levels: Vec<UserLevelsSchema> =
+        (database.execute(query).await).map_err(|e| internal("Failed to award xp", e))?;
+    match levels.into_iter().next() {
+        Some(level) => to_result(level),
+        None => Err(internal("Failed to award xp", "no row returned")),
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use worker::{Result, SqlStorage, State, Storage};
+
+use super::ShardUpdatePayload;
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+/// Every update is kept this long, then only the hourly aggregates remain
+const RAW_RETENTION_MS: i64 = 24 * HOUR_MS;
+/// Hourly aggregates and status transitions are kept this long
+const HISTORY_RETENTION_MS: i64 = 7 * 24 * HOUR_MS;
+
+const CREATE_TABLES: &str = "CREATE TABLE IF NOT EXISTS shard_samples (
+    shard_id INTEGER NOT NULL,
+    recorded_at INTEGER NOT NULL, -- Unix timestamp in milliseconds
+    status TEXT NOT NULL,
+    latency_ms INTEGER DEFAULT NULL,
+    members INTEGER NOT NULL,
+    PRIMARY KEY (shard_id, recorded_at)
+);
+CREATE INDEX IF NOT EXISTS idx_shard_samples_recorded_at ON shard_samples(recorded_at);
+CREATE TABLE IF NOT EXISTS shard_samples_hourly (
+    shard_id INTEGER NOT NULL,
+    hour INTEGER NOT NULL, -- Start of the hour, Unix timestamp in milliseconds
+    latency_total INTEGER NOT NULL DEFAULT 0, -- Sum of the reported latencies, for the average
+    latency_samples INTEGER NOT NULL DEFAULT 0, -- Updates that reported a latency
+    min_latency_ms INTEGER DEFAULT NULL,
+    max_latency_ms INTEGER DEFAULT NULL,
+    members INTEGER NOT NULL, -- Last reported member count of the hour
+    PRIMARY KEY (shard_id, hour)
+);
+CREATE TABLE IF NOT EXISTS shard_status_changes (
+    shard_id INTEGER NOT NULL,
+    changed_at INTEGER NOT NULL, -- Unix timestamp in milliseconds
+    status TEXT NOT NULL,
+    PRIMARY KEY (shard_id, changed_at)
+);";
+
+/// Time span of `GET /history` on the durable object
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryWindow {
+    #[default]
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+}
+
+impl HistoryWindow {
+    pub fn span_ms(self) -> i64 {
+        match self {
+            HistoryWindow::Hour => HOUR_MS,
+            HistoryWindow::Day => 24 * HOUR_MS,
+            HistoryWindow::Week => 7 * 24 * HOUR_MS,
+        }
+    }
+
+    /// Samples are averaged over buckets of this size, so every window returns a similar amount
+    pub fn bucket_ms(self) -> i64 {
+        match self {
+            HistoryWindow::Hour => 60 * 1000,
+            HistoryWindow::Day => 15 * 60 * 1000,
+            HistoryWindow::Week => HOUR_MS,
+        }
+    }
+}
+
+/// History of a single shard over a [`HistoryWindow`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardHistoryPayload {
+    pub shard_id: u32,
+    pub samples: Vec<ShardSample>,
+    /// Status changes in the window, preceded by the status the shard had when it started
+    pub transitions: Vec<StatusTransition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardSample {
+    /// Start of the bucket, Unix timestamp in milliseconds
+    pub at: i64,
+    /// Average latency of the bucket, `None` when the shard reported none
+    pub latency_ms: Option<f64>,
+    pub min_latency_ms: Option<i64>,
+    pub max_latency_ms: Option<i64>,
+    pub members: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusTransition {
+    /// Unix timestamp in milliseconds
+    pub at: i64,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+struct SampleRow {
+    shard_id: u32,
+    at: i64,
+    latency_ms: Option<f64>,
+    min_latency_ms: Option<i64>,
+    max_latency_ms: Option<i64>,
+    members: i64,
+}
+
+#[derive(Deserialize)]
+struct TransitionRow {
+    shard_id: u32,
+    at: i64,
+    status: String,
+}
+
+/// Shard latency, status and member counts over time, in the durable object's SQLite storage
+///
+/// Each update is kept for a day, hourly aggregates and status transitions for a week.
+pub struct ShardHistory {
+    storage: Storage,
+}
+
+impl ShardHistory {
+    pub fn new(state: &State) -> Self {
+        let storage = state.storage();
+        if let Err(e) = storage.sql().exec(CREATE_TABLES, None) {
+            error!("Failed to create the shard history tables: {}", e);
+        }
+        Self { storage }
+    }
+
+    fn sql(&self) -> SqlStorage {
+        self.storage.sql()
+    }
+
+    /// Store the shards of a `BotUpdate` and drop what is past retention
+    pub fn record(&self, shards: &[ShardUpdatePayload], now: i64) -> Result<()> {
+        let hour = now - now.rem_euclid(HOUR_MS);
+        for shard in shards {
+            let shard_id = shard.shard_id as i64;
+            let latency = shard.latency_ms.map(|latency| latency as i64);
+            let members = shard.members as i64;
+            self.sql().exec(
+                "INSERT OR REPLACE INTO shard_samples
+                    (shard_id, recorded_at, status, latency_ms, members)
+                VALUES (?, ?, ?, ?, ?)",
+                vec![
+                    shard_id.into(),
+                    now.into(),
+                    shard.status.clone().into(),
+                    latency.into(),
+                    members.into(),
+                ],
+            )?;
+            self.sql().exec(
+                "INSERT INTO shard_samples_hourly
+                    (shard_id, hour, latency_total, latency_samples,
+                    min_latency_ms, max_latency_ms, members)
+                VALUES (?1, ?2, COALESCE(?3, 0), ?3 IS NOT NULL, ?3, ?3, ?4)
+                ON CONFLICT(shard_id, hour) DO UPDATE SET
+                    latency_total = latency_total + excluded.latency_total,
+                    latency_samples = latency_samples + excluded.latency_samples,
+                    min_latency_ms = MIN(
+                        COALESCE(min_latency_ms, excluded.min_latency_ms),
+                        COALESCE(excluded.min_latency_ms, min_latency_ms)
+                    ),
+                    max_latency_ms = MAX(
+                        COALESCE(max_latency_ms, excluded.max_latency_ms),
+                        COALESCE(excluded.max_latency_ms, max_latency_ms)
+                    ),
+                    members = excluded.members",
+                vec![shard_id.into(), hour.into(), latency.into(), members.into()],
+            )?;
+            // Only kept when the status differs from the last one stored for the shard
+            self.sql().exec(
+                "INSERT OR IGNORE INTO shard_status_changes (shard_id, changed_at, status)
+                SELECT ?1, ?2, ?3
+                WHERE ?3 IS NOT (
+                    SELECT status FROM shard_status_changes
+                    WHERE shard_id = ?1 ORDER BY changed_at DESC LIMIT 1
+                )",
+                vec![shard_id.into(), now.into(), shard.status.clone().into()],
+            )?;
+        }
+
+        self.sql().exec(
+            "DELETE FROM shard_samples WHERE recorded_at < ?",
+            vec![(now - RAW_RETENTION_MS).into()],
+        )?;
+        self.sql().exec(
+            "DELETE FROM shard_samples_hourly WHERE hour < ?",
+            vec![(now - HISTORY_RETENTION_MS).into()],
+        )?;
+        // The last status of every shard stays, later changes are compared against it
+        self.sql().exec(
+            "DELETE FROM shard_status_changes AS old WHERE changed_at < ? AND changed_at < (
+                SELECT MAX(changed_at) FROM shard_status_changes WHERE shard_id = old.shard_id
+            )",
+            vec![(now - HISTORY_RETENTION_MS).into()],
+        )?;
+        Ok(())
+    }
+
+    /// History of every shard over `window`, ordered by shard
+    pub fn window(&self, window: HistoryWindow, now: i64) -> Result<Vec<ShardHistoryPayload>> {
+        let since = now - window.span_ms();
+        let bucket = window.bucket_ms();
+        let samples: Vec<SampleRow> = match window {
+            HistoryWindow::Week => self.sql().exec(
+                "SELECT shard_id, hour AS at,
+                    CAST(latency_total AS REAL) / NULLIF(latency_samples, 0) AS latency_ms,
+                    min_latency_ms, max_latency_ms, members
+                FROM shard_samples_hourly WHERE hour >= ? ORDER BY shard_id, hour",
+                vec![(since - since.rem_euclid(bucket)).into()],
+            )?,
+            HistoryWindow::Hour | HistoryWindow::Day => self.sql().exec(
+                "SELECT shard_id, (recorded_at / ?1) * ?1 AS at,
+                    AVG(latency_ms) AS latency_ms, MIN(latency_ms) AS min_latency_ms,
+                    MAX(latency_ms) AS max_latency_ms, MAX(members) AS members
+                FROM shard_samples WHERE recorded_at >= ?2
+                GROUP BY shard_id, at ORDER BY shard_id, at",
+                vec![bucket.into(), since.into()],
+            )?,
+        }
+        .to_array()?;
+        let transitions: Vec<TransitionRow> = (self.sql())
+            .exec(
+                "SELECT shard_id, changed_at AS at, status FROM shard_status_changes AS change
+                WHERE changed_at >= ?1 OR changed_at = (
+                    SELECT MAX(changed_at) FROM shard_status_changes
+                    WHERE shard_id = change.shard_id AND changed_at < ?1
+                )
+                ORDER BY shard_id, changed_at",
+                vec![since.into()],
+            )?
+            .to_array()?;
+
+        let mut history: Vec<ShardHistoryPayload> = Vec::new();
+        for row in samples {
+            shard_entry(&mut history, row.shard_id)
+                .samples
+                .push(ShardSample {
+                    at: row.at,
+                    latency_ms: row.latency_ms,
+                    min_latency_ms: row.min_latency_ms,
+                    max_latency_ms: row.max_latency_ms,
+                    members: row.members,
+                });
+        }
+        for row in transitions {
+            shard_entry(&mut history, row.shard_id)
+                .transitions
+                .push(StatusTransition {
+                    at: row.at,
+                    status: row.status,
+                });
+        }
+        Ok(history)
+    }
+}
+
+/// Entry of the shard in `history`, kept sorted by shard id
+fn shard_entry(history: &mut Vec<ShardHistoryPayload>, shard_id: u32) -> &mut ShardHistoryPayload {
+    let index = match history.binary_search_by_key(&shard_id, |shard| shard.shard_id) {
+        Ok(index) => index,
+        Err(index) => {
+            let shard = ShardHistoryPayload {
+                shard_id,
+                samples: Vec::new(),
+                transitions: Vec::new(),
+            };
+            history.insert(index, shard);
+            index
+        }
+    };
+    &mut history[index]
+}
@@ -0,0 +1,107 @@
+use serde::Deserialize;
+use tracing::error;
+use worker::{Result, SqlStorage, State, Storage};
+
+use super::{ShardUpdatePayload, shards::ShardMap};
+
+const AVG_LATENCY_KEY: &str = "avg_latency";
+
+const CREATE_TABLES: &str = "CREATE TABLE IF NOT EXISTS shard_state (
+    shard_id INTEGER PRIMARY KEY,
+    shard TEXT NOT NULL -- JSON encoded ShardUpdatePayload
+);
+CREATE TABLE IF NOT EXISTS gateway_values (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL -- JSON encoded
+);";
+
+#[derive(Deserialize)]
+struct StoredShard {
+    shard: String,
+}
+
+#[derive(Deserialize)]
+struct StoredValue {
+    value: String,
+}
+
+/// Latest shard view of the gateway, in the durable object's SQLite storage
+///
+/// The object keeps the view in memory and writes every change here, so it is read back
+/// when the runtime wakes the object up after hibernating or evicting it.
+pub struct ShardState {
+    storage: Storage,
+}
+
+impl ShardState {
+    pub fn new(state: &State) -> Self {
+        let storage = state.storage();
+        if let Err(e) = storage.sql().exec(CREATE_TABLES, None) {
+            error!("Failed to create the shard state tables: {}", e);
+        }
+        Self { storage }
+    }
+
+    fn sql(&self) -> SqlStorage {
+        self.storage.sql()
+    }
+
+    /// Shards and average latency stored before the object went away
+    pub fn load(&self) -> Result<(ShardMap, Option<i64>)> {
+        let stored: Vec<StoredShard> = (self.sql())
+            .exec("SELECT shard FROM shard_state ORDER BY shard_id", None)?
+            .to_array()?;
+        let mut shards = ShardMap::new();
+        for row in stored {
+            match serde_json::from_str::<ShardUpdatePayload>(&row.shard) {
+                Ok(shard) => {
+                    shards.insert(shard.shard_id, shard);
+                }
+                Err(e) => error!("Dropping unreadable stored shard: {}", e),
+            }
+        }
+
+        let latency: Vec<StoredValue> = (self.sql())
+            .exec(
+                "SELECT value FROM gateway_values WHERE key = ?",
+                vec![AVG_LATENCY_KEY.into()],
+            )?
+            .to_array()?;
+        let avg_latency = (latency.first()).and_then(|row| serde_json::from_str(&row.value).ok());
+        Ok((shards, avg_latency))
+    }
+
+    /// Store the latest state of these shards
+    pub fn save(&self, shards: &[ShardUpdatePayload]) -> Result<()> {
+        for shard in shards {
+            self.sql().exec(
+                "INSERT OR REPLACE INTO shard_state (shard_id, shard) VALUES (?, ?)",
+                vec![
+                    (shard.shard_id as i64).into(),
+                    serde_json::to_string(shard)?.into(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Forget the shards past `total`, after the bot was resharded
+    pub fn retain_below(&self, total: u32) -> Result<()> {
+        self.sql().exec(
+            "DELETE FROM shard_state WHERE shard_id >= ?",
+            vec![(total as i64).into()],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_avg_latency(&self, avg_latency: Option<i64>) -> Result<()> {
+        self.sql().exec(
+            "INSERT OR REPLACE INTO gateway_values (key, value) VALUES (?, ?)",
+            vec![
+                AVG_LATENCY_KEY.into(),
+                serde_json::to_string(&avg_latency)?.into(),
+            ],
+        )?;
+        Ok(())
+    }
+}
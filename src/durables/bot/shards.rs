@@ -1,10 +1,14 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::BTreeMap};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::error;
+use tracing::{error, warn};
 use worker::{Env, Response};
 
+use super::{
+    cluster::ClusterIdentity, health::OFFLINE_STATUS, shard_history::ShardHistory,
+    shard_state::ShardState,
+};
 use crate::{durables::bot::SocketSendEvent, services::websocket::WsEnvelope};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -13,8 +17,17 @@ pub struct ShardUpdatePayload {
     pub status: String,
     pub latency_ms: Option<u32>,
     pub members: u32,
+    /// When the last `BotUpdate` for this shard arrived, Unix timestamp in milliseconds
+    #[serde(default)]
+    pub last_seen: Option<i64>,
+    /// Cluster reporting the shard, set from the connection it came from
+    #[serde(default)]
+    pub cluster_id: Option<u32>,
 }
 
+/// Latest update of every shard of every cluster
+pub type ShardMap = BTreeMap<u32, ShardUpdatePayload>;
+
 impl Default for ShardUpdatePayload {
     fn default() -> Self {
         ShardUpdatePayload {
@@ -22,6 +35,8 @@ impl Default for ShardUpdatePayload {
             status: "unknown".to_string(),
             latency_ms: None,
             members: 0,
+            last_seen: None,
+            cluster_id: None,
         }
     }
 }
@@ -40,12 +55,26 @@ struct ShardPingPayload {
 
 pub fn handle_ping_shard(
     latency: &RefCell<Option<i64>>,
-    shards: &RefCell<Vec<ShardUpdatePayload>>,
+    shards: &RefCell<ShardMap>,
+    history: &ShardHistory,
+    store: &ShardState,
+    cluster: &ClusterIdentity,
     payload: Value,
 ) -> Option<WsEnvelope<SocketSendEvent>> {
     match serde_json::from_value::<ShardPingPayload>(payload) {
         Ok(data) => {
-            handle_shard_update(shards, data.shards);
+            let now = chrono::Utc::now().timestamp_millis();
+            let updates = owned_shards(cluster, data.shards, now);
+            if let Err(e) = history.record(&updates, now) {
+                error!("Failed to record shard history: {}", e);
+            }
+            if let Err(e) = store.save(&updates) {
+                error!("Failed to store shard state: {}", e);
+            }
+            handle_shard_update(shards, store, cluster, updates);
+            if let Err(e) = store.save_avg_latency(data.ping.avg_ping) {
+                error!("Failed to store average latency: {}", e);
+            }
             handle_ping(latency, data.ping)
         }
         Err(e) => {
@@ -55,22 +84,61 @@ pub fn handle_ping_shard(
     }
 }
 
+/// Keep the updates of shards the cluster runs, stamped with the cluster and time they came in
+fn owned_shards(
+    cluster: &ClusterIdentity,
+    updates: Vec<ShardUpdatePayload>,
+    now: i64,
+) -> Vec<ShardUpdatePayload> {
+    (updates.into_iter())
+        .filter(|update| {
+            let owned = cluster.owns(update.shard_id);
+            if !owned {
+                warn!(
+                    "Cluster {} reported shard {} outside of its range {}-{}",
+                    cluster.cluster_id, update.shard_id, cluster.first_shard, cluster.last_shard
+                );
+            }
+            owned
+        })
+        .map(|mut update| {
+            update.last_seen = Some(now);
+            update.cluster_id = Some(cluster.cluster_id);
+            update
+        })
+        .collect()
+}
+
 fn handle_shard_update(
-    shards: &RefCell<Vec<ShardUpdatePayload>>,
-    new_shards: Vec<ShardUpdatePayload>,
+    shards: &RefCell<ShardMap>,
+    store: &ShardState,
+    cluster: &ClusterIdentity,
+    updates: Vec<ShardUpdatePayload>,
 ) {
     let mut shards = shards.borrow_mut();
-    if shards.is_empty() {
-        *shards = new_shards;
-    } else {
-        for update in new_shards.into_iter() {
-            if let Some(existing) = shards.get_mut(update.shard_id as usize) {
-                *existing = update;
-            } else {
-                shards.push(update);
-            }
+    if let Some(total) = cluster.total_shards {
+        // Shards left over from before the bot was resharded
+        shards.retain(|shard_id, _| *shard_id < total);
+        if let Err(e) = store.retain_below(total) {
+            error!("Failed to drop stored shards past {}: {}", total, e);
         }
     }
+    for update in updates.into_iter() {
+        shards.insert(update.shard_id, update);
+    }
+}
+
+/// Mark the shards of a cluster that disconnected offline, returning the ones that changed
+pub fn mark_offline(shards: &RefCell<ShardMap>, cluster_id: u32) -> Vec<ShardUpdatePayload> {
+    let mut changed = Vec::new();
+    for shard in shards.borrow_mut().values_mut() {
+        if shard.cluster_id == Some(cluster_id) && shard.status != OFFLINE_STATUS {
+            shard.status = OFFLINE_STATUS.to_string();
+            shard.latency_ms = None;
+            changed.push(shard.clone());
+        }
+    }
+    changed
 }
 
 fn handle_ping(
@@ -81,7 +149,11 @@ fn handle_ping(
     Some(WsEnvelope::new(SocketSendEvent::BotPong, payload))
 }
 
-pub fn get_shards(shards: &RefCell<Vec<ShardUpdatePayload>>) -> worker::Result<Response> {
-    let shards = shards.borrow();
-    Response::from_json(&*shards)
+/// Every shard, ordered by id
+pub fn snapshot(shards: &RefCell<ShardMap>) -> Vec<ShardUpdatePayload> {
+    shards.borrow().values().cloned().collect()
+}
+
+pub fn get_shards(shards: &RefCell<ShardMap>) -> worker::Result<Response> {
+    Response::from_json(&snapshot(shards))
 }
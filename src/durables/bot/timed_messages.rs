@@ -0,0 +1,74 @@
+use chrono::Utc;
+use tracing::{error, info, warn};
+use worker::{Result, State};
+
+use super::{SocketSendEvent, alarms::AlarmOutcome, misc};
+use crate::{
+    schema::guild::TimedMessagesSchema,
+    services::{
+        timed_messages::{self, Schedule, TimedMessageDuePayload},
+        websocket::WsEnvelope,
+    },
+    state::database::{Database, DatabaseExt},
+};
+
+/// Send a due timed message to the bot and move it to its next run
+///
+/// The run is recorded before the message is sent, so a failing alarm never posts it twice.
+pub async fn run(state: &State, database: &Database, id: i64) -> Result<AlarmOutcome> {
+    let messages: Vec<TimedMessagesSchema> = database
+        .execute(TimedMessagesSchema::get_timed_message(id))
+        .await?;
+    let Some(message) = messages.into_iter().next() else {
+        info!("Timed message {} no longer exists, unscheduling", id);
+        return Ok(AlarmOutcome::Done);
+    };
+    let (false, Some(current)) = (message.paused, message.next_run_at.clone()) else {
+        return Ok(AlarmOutcome::Done);
+    };
+    let Some(due) = timed_messages::parse_time(&current) else {
+        error!("Timed message {} has an invalid next run: {}", id, current);
+        return Ok(AlarmOutcome::Done);
+    };
+    let now = Utc::now();
+    if due > now {
+        // Moved later since this alarm was set
+        return Ok(AlarmOutcome::Reschedule(due.timestamp_millis()));
+    }
+
+    let schedule = match Schedule::of(&message) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            error!("Timed message {} has an invalid schedule: {}", id, e);
+            return Ok(AlarmOutcome::Done);
+        }
+    };
+    let timezone = timed_messages::guild_timezone(database, &message.guild_id).await?;
+    let Some(next) = schedule.next_run(due, now, timezone) else {
+        warn!("Timed message {} never runs again", id);
+        return Ok(AlarmOutcome::Done);
+    };
+
+    let record =
+        TimedMessagesSchema::record_run(id, &current, &now.to_rfc3339(), &next.to_rfc3339());
+    let recorded: Vec<TimedMessagesSchema> = database.execute(record).await?;
+    if recorded.is_empty() {
+        // Paused or skipped meanwhile, whoever did it scheduled the next run
+        return Ok(AlarmOutcome::Done);
+    }
+
+    if misc::bot_connected(state, Some(&message.guild_id)) {
+        let envelope = WsEnvelope::new(
+            SocketSendEvent::TimedMessageDue,
+            TimedMessageDuePayload::from(&message),
+        );
+        misc::send_to_bot(state, Some(&message.guild_id), envelope)?;
+    } else {
+        // Posting a backlog of stale messages later would be worse than missing one
+        warn!(
+            "No bot connected to send timed message {}, skipping this run",
+            id
+        );
+    }
+    Ok(AlarmOutcome::Reschedule(next.timestamp_millis()))
+}
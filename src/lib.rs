@@ -16,7 +16,7 @@ use tracing_subscriber::{
     util::SubscriberInitExt,
 };
 use tracing_web::{performance_layer, MakeConsoleWriter};
-use worker::{event, Context, Env, HttpRequest, Result};
+use worker::{event, Context, Env, HttpRequest, Result, ScheduleContext, ScheduledEvent};
 
 use crate::state::{database::Database, server_info::ServerInfo};
 pub mod durables;
@@ -68,6 +68,14 @@ async fn fetch(req: HttpRequest, env: Env, _ctx: Context) -> Result<Response<Bod
     Ok(app.call(req).await?)
 }
 
+#[event(scheduled)]
+async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    let database = Database::new(&env, "DB");
+    // Errors are already logged, the next run retries
+    let _ = services::retention::purge_deleted_guilds(&env, &database).await;
+    let _ = services::session::purge_expired(&database).await;
+}
+
 /// Helper macro to count the number of expressions at compile time
 #[macro_export]
 macro_rules! count {
@@ -1,16 +1,17 @@
 use axum::{extract::Request, middleware::Next, response::Response, Extension};
-use cookie::Cookie;
 use reqwest::StatusCode;
 use tracing::{error, warn};
 use worker::Env;
 
 use crate::{
     services::{
-        auth::{add_success_cookies, DiscordAPIClient, DiscordCookie},
+        auth::{remove_session_cookies, DiscordAPIClient, DiscordCookie},
         cookie::CookieJar,
         get_discord_env,
+        session::{self, SESSION_COOKIE},
     },
     state::{
+        database::Database,
         server_info::ServerInfoArc,
         user::{RequestedUser, User},
     },
@@ -19,60 +20,48 @@ use crate::{
 #[worker::send]
 pub async fn middleware(
     Extension(env): Extension<Env>,
+    Extension(database): Extension<Database>,
     Extension(server_info): Extension<ServerInfoArc>,
     Extension(requested_user): Extension<RequestedUser>,
     jar: CookieJar,
     mut req: Request,
     next: Next,
-) -> Result<(Option<(CookieJar, CookieJar)>, Response), (Option<(CookieJar, CookieJar)>, StatusCode)>
-{
+) -> Result<(Option<CookieJar>, Response), (Option<CookieJar>, StatusCode)> {
     if let RequestedUser::Bot(_) = requested_user {
         return Ok((None, next.run(req).await));
     }
 
-    match jar
-        .get(&DiscordCookie::AccessToken.to_string())
-        .map(|c| c.value().to_string())
-    {
-        Some(token) => {
-            let user = User::new(token);
-            req.extensions_mut()
-                .insert(RequestedUser::UserWithToken(user));
-            Ok((None, next.run(req).await))
+    let Some(token) = jar.get(SESSION_COOKIE).map(|c| c.value().to_string()) else {
+        // Logins from before sessions kept the raw Discord tokens in cookies
+        if jar.get(&DiscordCookie::AccessToken.to_string()).is_some()
+            || jar.get(&DiscordCookie::RefreshToken.to_string()).is_some()
+        {
+            warn!("Clearing Discord token cookies of a login made before sessions");
+            return Ok((Some(remove_session_cookies(&jar)), next.run(req).await));
         }
-        None => {
-            let Some(refresh_token) = jar
-                .get(&DiscordCookie::RefreshToken.to_string())
-                .map(|c| c.value().to_string())
-            else {
-                warn!("No access token or refresh token found in cookies");
-                return Ok((None, next.run(req).await));
-            };
-            let Ok((client_id, client_secret)) = get_discord_env(&env) else {
-                error!("Failed to get Discord environment variables");
-                return Err((None, StatusCode::INTERNAL_SERVER_ERROR));
-            };
-            let redirect_uri = format!("{}/api/auth/redirect", server_info.api_host());
+        return Ok((None, next.run(req).await));
+    };
 
-            let discord_api =
-                DiscordAPIClient::new(client_id.clone(), client_secret.clone(), redirect_uri);
+    let Ok((client_id, client_secret)) = get_discord_env(&env) else {
+        error!("Failed to get Discord environment variables");
+        return Err((None, StatusCode::INTERNAL_SERVER_ERROR));
+    };
+    let redirect_uri = format!("{}/api/auth/redirect", server_info.api_host());
+    let discord_api = DiscordAPIClient::new(client_id, client_secret, redirect_uri);
 
-            let token = discord_api
-                .refresh_access_token(&refresh_token)
-                .await
-                .map_err(|e| {
-                    error!("Failed to refresh access token: {}", e);
-                    (None, StatusCode::UNAUTHORIZED)
-                })?;
-            let cookies = DiscordAPIClient::set_cookies(token.clone());
+    let session = session::resolve(&env, &database, &discord_api, &token)
+        .await
+        .map_err(|e| {
+            error!("Failed to resolve session: {}", e);
+            (None, StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+    let Some(session) = session else {
+        warn!("Session cookie doesn't match an active session");
+        return Ok((Some(remove_session_cookies(&jar)), next.run(req).await));
+    };
 
-            let user = User::new(token.access_token().to_string());
-            req.extensions_mut()
-                .insert(RequestedUser::UserWithToken(user));
-            Ok((
-                Some(add_success_cookies(&jar, cookies)),
-                next.run(req).await,
-            ))
-        }
-    }
+    let user = User::new(session.access_token, session.id, session.user_id);
+    req.extensions_mut()
+        .insert(RequestedUser::UserWithToken(user));
+    Ok((None, next.run(req).await))
 }
@@ -1,15 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use axum::{extract::Request, middleware::Next, response::Response, Extension};
 use reqwest::StatusCode;
-use worker::Env;
+use tracing::{Instrument, error, info_span};
+use worker::{Env, send::SendFuture};
 
 use crate::state::{database::Database, user::RequestedUser};
 
+/// Whether this isolate found the D1 schema at the version it was compiled against
+static SCHEMA_READY: AtomicBool = AtomicBool::new(false);
+
+/// Still served when the schema is out of date, so pending migrations can be applied
+const MIGRATIONS_PATH: &str = "/api/migrations";
+
 pub async fn middleware(
     Extension(env): Extension<Env>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, String)> {
-    request.extensions_mut().insert(Database::new(&env, "DB"));
-    let response = next.run(request).await;
+    let database = Database::new(&env, "DB");
+    // Fail closed, queries written against a newer schema would misbehave on an older one.
+    // Checked until it passes, then never again for the lifetime of the isolate.
+    if !SCHEMA_READY.load(Ordering::Relaxed) && request.uri().path() != MIGRATIONS_PATH {
+        if let Err(e) = SendFuture::new(database.check_schema_version()).await {
+            error!("Refusing request, {}", e);
+            return Err((StatusCode::SERVICE_UNAVAILABLE, e.to_string()));
+        }
+        SCHEMA_READY.store(true, Ordering::Relaxed);
+    }
+    // Query spans are nested in this one, so slow queries can be traced back to their route
+    let span = info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path()
+    );
+    request.extensions_mut().insert(database);
+    let response = next.run(request).instrument(span).await;
     Ok(response)
 }
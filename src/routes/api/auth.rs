@@ -1,10 +1,10 @@
 use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract::Query,
-    http::StatusCode,
+    extract::{Path, Query},
+    http::{header::USER_AGENT, HeaderMap, StatusCode},
     response::Redirect,
-    routing::{get, post},
+    routing::{delete, get},
     Extension, Json, Router,
 };
 use cookie::{time::Duration, Cookie};
@@ -13,12 +13,13 @@ use worker::{console_error, console_log, Env};
 
 use crate::{
     services::{
-        auth::{remove_error_cookies, DiscordAPIClient, DiscordOAuth2, DiscordOAuth2Scope},
+        auth::{remove_session_cookies, DiscordAPIClient, DiscordOAuth2, DiscordOAuth2Scope},
         cookie::CookieJar,
         get_discord_env,
+        session::{self, SessionInfo, SESSION_COOKIE},
         user::{DiscordUser, DiscordUserApi},
     },
-    state::{server_info::ServerInfoArc, user::RequestedUser},
+    state::{database::Database, server_info::ServerInfoArc, user::RequestedUser},
     DASHBOARD_URL,
 };
 
@@ -28,6 +29,18 @@ pub fn router() -> Router {
         .route("/redirect", get(redirect))
         .route("/status", get(status))
         .route("/logout", get(logout))
+        .route("/sessions", get(get_sessions))
+        .route("/sessions/{id}", delete(delete_session))
+}
+
+/// Client for the OAuth2 endpoints of Discord, with the credentials of the environment
+fn discord_client(env: &Env, server_info: &ServerInfoArc) -> Option<DiscordAPIClient> {
+    let Ok((client_id, client_secret)) = get_discord_env(env) else {
+        error!("Failed to get Discord environment variables");
+        return None;
+    };
+    let redirect_uri = format!("{}/api/auth/redirect", server_info.api_host());
+    Some(DiscordAPIClient::new(client_id, client_secret, redirect_uri))
 }
 
 async fn login(
@@ -72,19 +85,19 @@ async fn login(
 #[axum::debug_handler]
 async fn redirect(
     Extension(env): Extension<Env>,
+    Extension(database): Extension<Database>,
     Extension(server_info): Extension<ServerInfoArc>,
     Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
     jar: CookieJar,
-) -> Result<(CookieJar, CookieJar, Redirect), Redirect> {
+) -> Result<(CookieJar, Redirect), Redirect> {
     let webpage = server_info.webpage();
     let dashboard = format!("{}/dashboard", webpage);
 
-    let Ok((client_id, client_secret)) = get_discord_env(&env) else {
-        error!("Failed to get Discord environment variables");
+    let Some(discord_api) = discord_client(&env, &server_info) else {
         return Err(Redirect::temporary(webpage));
     };
 
-    let redirect_uri = format!("{}/api/auth/redirect", server_info.api_host());
     let code = match params.get("code") {
         Some(code) => code,
         None => {
@@ -93,11 +106,6 @@ async fn redirect(
         }
     };
 
-    let discord_api = DiscordAPIClient::new(
-        client_id.clone(),
-        client_secret.clone(),
-        redirect_uri.clone(),
-    );
     let token = match discord_api.get_access_token(code.clone()).await {
         Ok(token) => token,
         Err(e) => {
@@ -106,13 +114,27 @@ async fn redirect(
         }
     };
 
-    let cookies = DiscordAPIClient::set_cookies(token);
+    let authorization = format!("Bearer {}", token.access_token());
+    let user = match DiscordUserApi::new(authorization).get_user().await {
+        Ok(user) => user,
+        Err(e) => {
+            error!("Failed to fetch user data: {}", e);
+            return Err(Redirect::to(webpage));
+        }
+    };
+
+    let user_agent = (headers.get(USER_AGENT))
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let cookie = match session::create(&env, &database, &token, user.id, user_agent).await {
+        Ok(cookie) => cookie,
+        Err(e) => {
+            error!("Failed to create session: {}", e);
+            return Err(Redirect::to(webpage));
+        }
+    };
 
-    Ok((
-        jar.clone().add(cookies[0].clone()),
-        jar.clone().add(cookies[1].clone()),
-        Redirect::to(&dashboard),
-    ))
+    Ok((jar.add(cookie), Redirect::to(&dashboard)))
 }
 
 #[worker::send]
@@ -120,7 +142,7 @@ async fn redirect(
 async fn status(
     Extension(requested_user): Extension<RequestedUser>,
     jar: CookieJar,
-) -> Result<Json<DiscordUser>, (Option<(CookieJar, CookieJar)>, StatusCode)> {
+) -> Result<Json<DiscordUser>, (Option<CookieJar>, StatusCode)> {
     let user = match requested_user {
         RequestedUser::UserWithToken(user) => user,
         _ => {
@@ -135,20 +157,95 @@ async fn status(
         Ok(user) => user,
         Err(e) => {
             error!("Failed to fetch user data: {}", e);
-            return Err((Some(remove_error_cookies(&jar)), StatusCode::UNAUTHORIZED));
+            return Err((Some(remove_session_cookies(&jar)), StatusCode::UNAUTHORIZED));
         }
     };
 
     Ok(Json(user))
 }
 
+/// End the session of the request, revoking its tokens at Discord
+#[worker::send]
+#[axum::debug_handler]
 async fn logout(
     Extension(env): Extension<Env>,
+    Extension(database): Extension<Database>,
+    Extension(server_info): Extension<ServerInfoArc>,
     jar: CookieJar,
-) -> ((CookieJar, CookieJar), Redirect) {
+) -> (CookieJar, Redirect) {
     let webpage = env
         .var("DASHBOARD_URL")
         .map(|s| s.to_string())
         .unwrap_or_else(|_| DASHBOARD_URL.into());
-    (remove_error_cookies(&jar), Redirect::to(&webpage))
+
+    let token = jar.get(SESSION_COOKIE).map(|c| c.value().to_string());
+    if let (Some(token), Some(discord_api)) = (token, discord_client(&env, &server_info))
+        && let Err(e) = session::end(&env, &database, &discord_api, &token).await
+    {
+        error!("Failed to end session: {}", e);
+    }
+    (remove_session_cookies(&jar), Redirect::to(&webpage))
+}
+
+/// Sessions of the logged in user, most recently used first
+#[worker::send]
+#[axum::debug_handler]
+async fn get_sessions(
+    Extension(database): Extension<Database>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<Vec<SessionInfo>>, (StatusCode, String)> {
+    let RequestedUser::UserWithToken(user) = requested_user else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Must be authenticated to list sessions".into(),
+        ));
+    };
+
+    let sessions = session::list(&database, user.user_id(), user.session_id())
+        .await
+        .map_err(|e| {
+            error!("Failed to list sessions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to list sessions".to_string(),
+            )
+        })?;
+    Ok(Json(sessions))
+}
+
+/// End one of the sessions of the logged in user, e.g. a login on another device
+#[worker::send]
+#[axum::debug_handler]
+async fn delete_session(
+    Extension(env): Extension<Env>,
+    Extension(database): Extension<Database>,
+    Extension(server_info): Extension<ServerInfoArc>,
+    Extension(requested_user): Extension<RequestedUser>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let RequestedUser::UserWithToken(user) = requested_user else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Must be authenticated to end sessions".into(),
+        ));
+    };
+    let Some(discord_api) = discord_client(&env, &server_info) else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "".into()));
+    };
+
+    let ended = session::end_user_session(&env, &database, &discord_api, &id, user.user_id())
+        .await
+        .map_err(|e| {
+            error!("Failed to end session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to end session".to_string(),
+            )
+        })?;
+    if !ended {
+        warn!("User {} tried to end an unknown session", user.user_id());
+        return Err((StatusCode::NOT_FOUND, "Session not found".into()));
+    }
+    info!("User {} ended one of their sessions", user.user_id());
+    Ok(StatusCode::NO_CONTENT)
 }
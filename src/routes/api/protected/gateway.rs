@@ -1,18 +1,31 @@
 use axum::{
+    Extension,
     body::Body,
-    extract::{Path, Request},
+    extract::{Path, Query, Request},
     http::Response,
-    Extension,
 };
-use reqwest::{header::USER_AGENT, StatusCode};
-use tracing::error;
+use reqwest::{StatusCode, header::USER_AGENT};
+use serde::Deserialize;
+use tracing::{error, info, warn};
 use worker::Env;
 
 use crate::{
-    durables::{bot::BotDurable, DurableFetch},
+    durables::{
+        DurableFetch,
+        bot::{BotDurable, DASHBOARD_GUILDS_HEADER, MAX_DASHBOARD_GUILDS},
+    },
+    services::guilds::DiscordGuildHTTP,
     state::user::RequestedUser,
 };
 
+#[derive(Deserialize)]
+pub struct DashboardGatewayQuery {
+    /// Comma separated guild ids to follow, at most [`MAX_DASHBOARD_GUILDS`]
+    ///
+    /// When omitted, the oldest guilds the user manages are followed, up to the same limit.
+    pub guilds: Option<String>,
+}
+
 #[worker::send]
 #[axum::debug_handler]
 pub async fn handle_websocket(
@@ -62,3 +75,140 @@ pub async fn handle_websocket(
 
     Ok(response.into())
 }
+
+/// Open a live connection for the dashboard, following the guilds the user may manage
+#[worker::send]
+#[axum::debug_handler]
+pub async fn handle_dashboard_websocket(
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+    Query(query): Query<DashboardGatewayQuery>,
+    req: Request,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let RequestedUser::UserWithToken(user) = requested_user else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Must be authenticated to open the dashboard gateway".into(),
+        ));
+    };
+    let Ok(bot_token) = env.secret("DISCORD_BOT_TOKEN").map(|s| s.to_string()) else {
+        error!("Failed to get bot token");
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "".into()));
+    };
+
+    let bot_client = DiscordGuildHTTP::new(format!("Bot {}", bot_token));
+    let user_client = DiscordGuildHTTP::new(format!("Bearer {}", user.access_token()));
+    let managed: Vec<String> = (bot_client.get_mutual_guilds(user_client).await)
+        .map_err(|e| {
+            error!("Failed to fetch mutual guilds: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch mutual guilds".into(),
+            )
+        })?
+        .into_iter()
+        .filter(|guild| guild.can_manage())
+        .map(|guild| guild.id)
+        .collect();
+
+    let guild_ids: Vec<String> = match &query.guilds {
+        Some(requested) => {
+            let requested: Vec<&str> = requested.split(',').map(str::trim).collect();
+            if let Some(denied) = requested
+                .iter()
+                .find(|id| !managed.iter().any(|m| m == *id))
+            {
+                warn!("Dashboard gateway requested unmanaged guild {}", denied);
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    format!("Missing permission to manage guild {}", denied),
+                ));
+            }
+            requested.into_iter().map(str::to_string).collect()
+        }
+        None => default_guilds(managed),
+    };
+    if guild_ids.len() > MAX_DASHBOARD_GUILDS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "A connection follows at most {} guilds, {} were requested",
+                MAX_DASHBOARD_GUILDS,
+                guild_ids.len()
+            ),
+        ));
+    }
+
+    let mut new_req = crate::copy_request(&req, None).map_err(|e| {
+        error!("Failed to copy request: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "".into())
+    })?;
+    let headers = new_req.headers_mut().map_err(|e| {
+        error!("Failed to get headers: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "".into())
+    })?;
+    // Both are trusted by the durable object, never forward the client's own values
+    let set_headers = headers
+        .set(USER_AGENT.as_str(), "Dashboard")
+        .and_then(|_| headers.set(DASHBOARD_GUILDS_HEADER, &guild_ids.join(",")));
+    if let Err(err) = set_headers {
+        error!("Failed to set dashboard headers: {}", err);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "".into()));
+    }
+
+    let stub = BotDurable::bot_stub(&env).map_err(|e| {
+        error!("Failed to fetch bot durable object: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "".into())
+    })?;
+    let response = stub.fetch_with_request(new_req).await.map_err(|e| {
+        error!("Failed to fetch from durable object: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "".into())
+    })?;
+
+    Ok(response.into())
+}
+
+/// The guilds followed when the dashboard doesn't pick them: the oldest ones, so reconnecting
+/// always follows the same guilds
+fn default_guilds(mut managed: Vec<String>) -> Vec<String> {
+    // Snowflakes grow with time, compare them as numbers
+    managed.sort_by(|a, b| (a.len(), a).cmp(&(b.len(), b)));
+    if managed.len() > MAX_DASHBOARD_GUILDS {
+        info!(
+            "Dashboard gateway follows {} of {} managed guilds",
+            MAX_DASHBOARD_GUILDS,
+            managed.len()
+        );
+        managed.truncate(MAX_DASHBOARD_GUILDS);
+    }
+    managed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn default_guilds_are_the_oldest() {
+        let managed: Vec<String> = (0..MAX_DASHBOARD_GUILDS + 3)
+            .rev()
+            .map(|i| (100 + i).to_string())
+            .collect();
+        let expected: Vec<String> = (0..MAX_DASHBOARD_GUILDS)
+            .map(|i| (100 + i).to_string())
+            .collect();
+        assert_eq!(default_guilds(managed), expected);
+    }
+
+    #[test]
+    fn default_guilds_compare_ids_as_numbers() {
+        assert_eq!(
+            default_guilds(ids(&["900", "1000", "99"])),
+            ids(&["99", "900", "1000"])
+        );
+    }
+}
@@ -2,17 +2,22 @@ use axum::{
     Extension, Json,
     extract::{Path, Query},
 };
-use chrono::Datelike;
+use chrono::{Datelike, Utc};
 use reqwest::StatusCode;
 use sea_query::{Alias, Expr, SelectStatement};
 use serde::Deserialize;
-use tracing::error;
+use tracing::{error, warn};
+use worker::Env;
 
 use crate::{
+    durables::bot::{BotDurable, ConfigSection, ScheduledAlarm},
     schema::{
-        guild,
+        SoftDelete, guild,
+        guild::BirthdayConfigsSchema,
         user::{BirthdaySchema, Birthdays},
     },
+    services::{birthdays, config, timed_messages},
+    snowflake_protection,
     state::database::{Database, DatabaseExt},
 };
 
@@ -34,15 +39,118 @@ pub async fn upcoming(
     let birthdays = get_upcoming_birthdays_query(&guild_id, offset, limit);
     let birthdays: Vec<BirthdaySchema> = (database.execute(birthdays).await).map_err(|e| {
         error!("Failed to get upcoming birthdays: {:?}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to get upcoming birthdays".to_string(),
-        )
+        (e.status(), "Failed to get upcoming birthdays".to_string())
     })?;
 
     Ok(Json(birthdays))
 }
 
+#[derive(Deserialize)]
+pub struct BirthdayConfigRequest {
+    /// Channel to announce birthdays in, `None` lets the bot pick one
+    pub channel_id: Option<String>,
+    /// Template of the announcement, see [`birthdays::render_message`]
+    pub message: Option<String>,
+}
+
+#[worker::send]
+#[axum::debug_handler]
+pub async fn get_config(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<BirthdayConfigsSchema>, (StatusCode, String)> {
+    let query = BirthdayConfigsSchema::get_config(&guild_id);
+    let configs: Vec<BirthdayConfigsSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get birthday config: {:?}", e);
+        (e.status(), "Failed to get birthday config".to_string())
+    })?;
+    (configs.into_iter().next()).map(Json).ok_or((
+        StatusCode::NOT_FOUND,
+        "Birthday config not found".to_string(),
+    ))
+}
+
+/// Store the guild's birthday config and schedule its announcements from the next local midnight
+#[worker::send]
+#[axum::debug_handler]
+pub async fn set_config(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Json(body): Json<BirthdayConfigRequest>,
+) -> Result<Json<BirthdayConfigsSchema>, (StatusCode, String)> {
+    if let Some(channel_id) = &body.channel_id {
+        snowflake_protection!(channel_id);
+    }
+
+    let query = BirthdayConfigsSchema::insert_or_update(&guild_id, body.channel_id, body.message);
+    let configs: Vec<BirthdayConfigsSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to set birthday config: {:?}", e);
+        (e.status(), "Failed to set birthday config".to_string())
+    })?;
+    let Some(config) = configs.into_iter().next() else {
+        error!("Failed to set birthday config: No results returned");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to set birthday config".to_string(),
+        ));
+    };
+
+    let timezone = (timed_messages::guild_timezone(&database, &guild_id).await).map_err(|e| {
+        error!("Failed to get timezone of guild {}: {:?}", guild_id, e);
+        (e.status(), "Failed to get guild timezone".to_string())
+    })?;
+    let run_at = birthdays::next_midnight(Utc::now(), timezone).timestamp_millis();
+    let alarm = ScheduledAlarm::Birthdays {
+        guild_id: guild_id.clone(),
+    };
+    // Scheduling again replaces the pending run, setting the config a second time is harmless
+    if let Err(e) = BotDurable::schedule_alarm(&env, run_at, alarm).await {
+        error!(
+            "Failed to schedule birthday announcements of guild {}: {}",
+            guild_id, e
+        );
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to schedule birthday announcements".to_string(),
+        ));
+    }
+
+    config::publish(&env, &guild_id, ConfigSection::Birthday).await;
+    Ok(Json(config))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+pub async fn delete_config(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+) -> Result<Json<BirthdayConfigsSchema>, (StatusCode, String)> {
+    let query = BirthdayConfigsSchema::delete_config(&guild_id);
+    let removed: Vec<BirthdayConfigsSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to delete birthday config: {:?}", e);
+        (e.status(), "Failed to delete birthday config".to_string())
+    })?;
+    let Some(config) = removed.into_iter().next() else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Birthday config not found".to_string(),
+        ));
+    };
+
+    // The alarm unschedules itself when the config is gone
+    let key = ScheduledAlarm::Birthdays {
+        guild_id: guild_id.clone(),
+    }
+    .key();
+    if let Err(e) = BotDurable::cancel_alarm(&env, &key).await {
+        warn!("Failed to cancel birthday announcements: {}", e);
+    }
+    config::publish(&env, &guild_id, ConfigSection::Birthday).await;
+    Ok(Json(config))
+}
+
 /// Get upcoming birthdays for a specific guild
 ///
 /// Get the current date, and you want to order them by month then day. For birthdays that already past, these will be also ordered by month and day, but for the next year.
@@ -81,6 +189,7 @@ fn get_upcoming_birthdays_query(guild_id: &str, offset: u64, limit: u64) -> Sele
             Expr::col(bday_user_id_col).equals(user_id_col),
         )
         .and_where(Expr::col(guild_id_col).eq(guild_id))
+        .and_where(SoftDelete::live(guild::Members::Table))
         .order_by(birthday_year, sea_query::Order::Asc)
         .order_by(bday_month_col, sea_query::Order::Asc)
         .order_by(bday_day_col, sea_query::Order::Asc)
@@ -0,0 +1,333 @@
+use axum::{
+    Extension, Json, Router,
+    extract::Path,
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+use worker::Env;
+
+use crate::{
+    durables::bot::{
+        BotDurable, ConfigSection, DashboardEvent, GiveawayEntriesPayload, ScheduledAlarm,
+        SocketSendEvent,
+    },
+    schema::{
+        guild::GiveawaysSchema,
+        user::{GiveawayEntriesSchema, GiveawayEntryCount, GiveawayWinnersSchema},
+    },
+    services::{
+        config, dashboard,
+        giveaways::{self, GiveawayEndedPayload},
+        websocket::WsEnvelope,
+    },
+    snowflake_protection,
+    state::{
+        database::{Database, DatabaseExt, DbResult},
+        user::RequestedUser,
+    },
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(get_giveaways).post(create_giveaway))
+        .route("/{giveaway_id}", get(get_giveaway))
+        .route("/{giveaway_id}/end", post(end_giveaway))
+        .route("/{giveaway_id}/reroll", post(reroll_giveaway))
+        .route(
+            "/{giveaway_id}/entries/{user_id}",
+            post(add_entry).delete(remove_entry),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+struct NewGiveaway {
+    giveaway_id: String,
+    channel_id: String,
+    message_id: String,
+    prize: String,
+    end_time: DateTime<Utc>,
+    winners_count: i32,
+    host_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RerollBody {
+    /// Winners to draw, one when missing
+    count: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct GiveawayWithWinners {
+    giveaway: GiveawaysSchema,
+    winners: Vec<GiveawayWinnersSchema>,
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn get_giveaways(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<Vec<GiveawaysSchema>>, (StatusCode, String)> {
+    let query = GiveawaysSchema::get_guild_giveaways(&guild_id);
+    let giveaways: Vec<GiveawaysSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get giveaways: {:?}", e);
+        (e.status(), "Failed to get giveaways".to_string())
+    })?;
+    Ok(Json(giveaways))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn get_giveaway(
+    Path((guild_id, giveaway_id)): Path<(String, String)>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<GiveawayWithWinners>, (StatusCode, String)> {
+    let giveaway = find_giveaway(&database, &guild_id, &giveaway_id).await?;
+    let query = GiveawayWinnersSchema::get_winners(&giveaway_id);
+    let winners: Vec<GiveawayWinnersSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get giveaway winners: {:?}", e);
+        (e.status(), "Failed to get giveaway winners".to_string())
+    })?;
+    Ok(Json(GiveawayWithWinners { giveaway, winners }))
+}
+
+/// Store a giveaway and schedule its end in the bot's durable object
+#[worker::send]
+#[axum::debug_handler]
+async fn create_giveaway(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+    Json(body): Json<NewGiveaway>,
+) -> Result<Json<GiveawaysSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Create Giveaway")?;
+    let channel_id = body.channel_id;
+    snowflake_protection!(channel_id);
+    let host_id = body.host_id;
+    snowflake_protection!(host_id);
+    if body.winners_count < 1 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A giveaway needs at least one winner".to_string(),
+        ));
+    }
+    debug!(
+        "Creating giveaway {} in guild {} ending at {}",
+        body.giveaway_id, guild_id, body.end_time
+    );
+
+    let insert = GiveawaysSchema::insert(
+        &body.giveaway_id,
+        &guild_id,
+        &channel_id,
+        &body.message_id,
+        &body.prize,
+        &body.end_time,
+        body.winners_count,
+        &host_id,
+    );
+    let result: Vec<GiveawaysSchema> = (database.execute(insert).await).map_err(|e| {
+        error!("Failed to create giveaway: {:?}", e);
+        (e.status(), "Failed to create giveaway".to_string())
+    })?;
+    let Some(giveaway) = result.into_iter().next() else {
+        error!("Failed to create giveaway: No results returned");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create giveaway".to_string(),
+        ));
+    };
+
+    let alarm = ScheduledAlarm::GiveawayEnd {
+        giveaway_id: giveaway.giveaway_id.clone(),
+    };
+    let run_at = body.end_time.timestamp_millis();
+    if let Err(e) = BotDurable::schedule_alarm(&env, run_at, alarm).await {
+        error!(
+            "Failed to schedule end of giveaway {}: {}",
+            giveaway.giveaway_id, e
+        );
+        // A giveaway that never ends is worse than a failed request
+        let rollback: DbResult<Vec<GiveawaysSchema>> = database
+            .execute(GiveawaysSchema::delete_giveaway(&giveaway.giveaway_id))
+            .await;
+        if let Err(e) = rollback {
+            error!(
+                "Failed to remove unscheduled giveaway {}: {:?}",
+                giveaway.giveaway_id, e
+            );
+        }
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to schedule giveaway".to_string(),
+        ));
+    }
+
+    config::publish(&env, &guild_id, ConfigSection::Giveaways).await;
+    Ok(Json(giveaway))
+}
+
+/// End a running giveaway before its end time
+#[worker::send]
+#[axum::debug_handler]
+async fn end_giveaway(
+    Path((guild_id, giveaway_id)): Path<(String, String)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<GiveawayEndedPayload>, (StatusCode, String)> {
+    requested_user.bot_protection("End Giveaway")?;
+    find_giveaway(&database, &guild_id, &giveaway_id).await?;
+
+    let ended = (giveaways::end_giveaway(&database, &giveaway_id).await).map_err(|e| {
+        error!("Failed to end giveaway {}: {:?}", giveaway_id, e);
+        (e.status(), "Failed to end giveaway".to_string())
+    })?;
+    let Some(ended) = ended else {
+        return Err((StatusCode::CONFLICT, "Giveaway already ended".to_string()));
+    };
+
+    let key = ScheduledAlarm::GiveawayEnd {
+        giveaway_id: giveaway_id.clone(),
+    }
+    .key();
+    if let Err(e) = BotDurable::cancel_alarm(&env, &key).await {
+        // The alarm finds the giveaway ended and does nothing
+        warn!("Failed to cancel end of giveaway {}: {}", giveaway_id, e);
+    }
+    announce(&env, &ended).await;
+
+    config::publish(&env, &guild_id, ConfigSection::Giveaways).await;
+    Ok(Json(ended))
+}
+
+/// Draw new winners of an ended giveaway, excluding everyone who already won
+#[worker::send]
+#[axum::debug_handler]
+async fn reroll_giveaway(
+    Path((guild_id, giveaway_id)): Path<(String, String)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+    body: Option<Json<RerollBody>>,
+) -> Result<Json<GiveawayEndedPayload>, (StatusCode, String)> {
+    requested_user.bot_protection("Reroll Giveaway")?;
+    find_giveaway(&database, &guild_id, &giveaway_id).await?;
+    let count = body.unwrap_or_default().count.unwrap_or(1) as usize;
+
+    let rerolled = giveaways::reroll_giveaway(&database, &giveaway_id, count).await;
+    let rerolled = rerolled.map_err(|e| {
+        error!("Failed to reroll giveaway {}: {:?}", giveaway_id, e);
+        (e.status(), "Failed to reroll giveaway".to_string())
+    })?;
+    let Some(rerolled) = rerolled else {
+        return Err((
+            StatusCode::CONFLICT,
+            "Giveaway has not ended yet".to_string(),
+        ));
+    };
+    announce(&env, &rerolled).await;
+
+    config::publish(&env, &guild_id, ConfigSection::Giveaways).await;
+    Ok(Json(rerolled))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn add_entry(
+    Path((guild_id, giveaway_id, user_id)): Path<(String, String, String)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<(), (StatusCode, String)> {
+    requested_user.bot_protection("Enter Giveaway")?;
+    snowflake_protection!(user_id);
+    let giveaway = find_giveaway(&database, &guild_id, &giveaway_id).await?;
+    if giveaway.ended {
+        return Err((StatusCode::CONFLICT, "Giveaway already ended".to_string()));
+    }
+
+    let insert = GiveawayEntriesSchema::insert(&giveaway_id, &user_id);
+    let _: () = (database.execute(insert).await).map_err(|e| {
+        error!("Failed to enter giveaway: {:?}", e);
+        (e.status(), "Failed to enter giveaway".to_string())
+    })?;
+    push_entry_count(&database, &env, &guild_id, &giveaway_id).await;
+    Ok(())
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn remove_entry(
+    Path((guild_id, giveaway_id, user_id)): Path<(String, String, String)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<(), (StatusCode, String)> {
+    requested_user.bot_protection("Leave Giveaway")?;
+    find_giveaway(&database, &guild_id, &giveaway_id).await?;
+
+    let delete = GiveawayEntriesSchema::delete_entry(&giveaway_id, &user_id);
+    let removed: Vec<GiveawayEntriesSchema> = (database.execute(delete).await).map_err(|e| {
+        error!("Failed to leave giveaway: {:?}", e);
+        (e.status(), "Failed to leave giveaway".to_string())
+    })?;
+    if removed.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "User has not entered".to_string()));
+    }
+    push_entry_count(&database, &env, &guild_id, &giveaway_id).await;
+    Ok(())
+}
+
+/// The giveaway, as long as it belongs to the guild
+async fn find_giveaway(
+    database: &Database,
+    guild_id: &String,
+    giveaway_id: &String,
+) -> Result<GiveawaysSchema, (StatusCode, String)> {
+    let query = GiveawaysSchema::get_giveaway(giveaway_id);
+    let giveaways: Vec<GiveawaysSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get giveaway {}: {:?}", giveaway_id, e);
+        (e.status(), "Failed to get giveaway".to_string())
+    })?;
+    (giveaways.into_iter())
+        .find(|giveaway| giveaway.guild_id == *guild_id)
+        .ok_or((StatusCode::NOT_FOUND, "Giveaway not found".to_string()))
+}
+
+/// Send the new number of entrants to the guild's dashboards
+async fn push_entry_count(database: &Database, env: &Env, guild_id: &str, giveaway_id: &str) {
+    let query = GiveawayEntriesSchema::count_entries(giveaway_id);
+    let counts: DbResult<Vec<GiveawayEntryCount>> = database.execute(query).await;
+    let entries = match counts {
+        Ok(counts) => counts.first().map_or(0, |count| count.entries),
+        Err(e) => {
+            warn!(
+                "Failed to count entries of giveaway {}: {:?}",
+                giveaway_id, e
+            );
+            return;
+        }
+    };
+    let payload = GiveawayEntriesPayload {
+        guild_id: guild_id.to_string(),
+        giveaway_id: giveaway_id.to_string(),
+        entries,
+    };
+    dashboard::push(env, guild_id, DashboardEvent::GiveawayEntries, payload).await;
+}
+
+/// Send the winners to the bot, they stay readable from `GET /{giveaway_id}` if it is offline
+async fn announce(env: &Env, payload: &GiveawayEndedPayload) {
+    let envelope = WsEnvelope::new(SocketSendEvent::GiveawayEnded, payload);
+    if let Err(e) = BotDurable::send_event(env, Some(&payload.giveaway.guild_id), &envelope).await {
+        warn!(
+            "Failed to announce giveaway {}: {}",
+            payload.giveaway.giveaway_id, e
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
+    durables::bot::ConfigSection,
     schema::guild::GuildSchema,
+    services::{cache::SettingsCache, config},
     state::{
-        database::{Database, DatabaseExt},
+        database::{Database, DatabaseExt, DbResult},
         user::RequestedUser,
     },
 };
@@ -14,6 +16,7 @@ use reqwest::StatusCode;
 
 use serde::Deserialize;
 use tracing::{debug, error, warn};
+use worker::Env;
 
 // ! FOR GUILDS 0 if FALSE, 1 if TRUE
 // ! This is used to enable or disable features for a guild
@@ -28,7 +31,7 @@ pub async fn get(
         Ok(guild) => guild,
         Err(e) => {
             warn!("Failed to get guild: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(e.status());
         }
     };
     if guild.is_empty() {
@@ -53,6 +56,7 @@ pub async fn create(
     Path(guild_id): Path<String>,
     Query(params): Query<NewGuildQuery>,
     Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
     Extension(requested_user): Extension<RequestedUser>,
 ) -> Result<(), (StatusCode, String)> {
     debug!("Creating new guild with ID: {}", guild_id);
@@ -66,12 +70,15 @@ pub async fn create(
         .map_err(|e| {
             error!("Failed to create guild entry: {:?}", e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to create guild entry".to_string(),
             )
         })?;
 
+    // Rejoining restores a soft deleted guild's settings
+    SettingsCache::new().invalidate(&guild_id).await;
     debug!("Guild created successfully with ID: {}", guild_id);
+    config::publish(&env, &guild_id, ConfigSection::Guild).await;
     Ok(())
 }
 
@@ -85,29 +92,34 @@ pub struct GuildQuery {
 pub async fn disable(
     Path(guild_id): Path<String>,
     Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
     Extension(requested_user): Extension<RequestedUser>,
     Query(params): Query<GuildQuery>,
 ) -> Result<(), (StatusCode, String)> {
     requested_user.bot_protection("Disable Guild")?;
     let unavailable = params.unavailable.unwrap_or(false); // Default to false if not provided, don't want to accidentally delete guilds if the parameter is missing
     if unavailable {
-        let delete_query = GuildSchema::delete(&guild_id);
-        let _guilds: Vec<GuildSchema> = (database.execute(delete_query).await).map_err(|e| {
-            warn!("Failed to get guild for deletion: {:?}", e);
+        // Soft delete, the configuration comes back if the bot rejoins before it is purged
+        let delete_query = GuildSchema::soft_delete(&guild_id);
+        let _: () = (database.execute(delete_query).await).map_err(|e| {
+            warn!("Failed to delete guild entry: {:?}", e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to delete guild entry".to_string(),
             )
         })?;
+        SettingsCache::new().invalidate(&guild_id).await;
     } else {
         let disable_query = GuildSchema::disable(&guild_id);
-        if let Err(e) = database.execute(disable_query).await {
+        let disabled: DbResult<()> = database.execute(disable_query).await;
+        if let Err(e) = disabled {
             warn!("Failed to disable guild entry: {:?}", e);
             return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to disable guild entry".to_string(),
             ));
         }
     }
+    config::publish(&env, &guild_id, ConfigSection::Guild).await;
     Ok(())
 }
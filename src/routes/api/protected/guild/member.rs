@@ -31,7 +31,7 @@ async fn add_members(
     let _: () = (database.execute(insert_statement).await).map_err(|e| {
         error!("Failed to add member to guild: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to add member to guild".to_string(),
         )
     })?;
@@ -53,7 +53,7 @@ async fn add_member(
     let _: () = (database.execute(insert_statement).await).map_err(|e| {
         error!("Failed to add member to guild: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to add member to guild".to_string(),
         )
     })?;
@@ -75,7 +75,7 @@ async fn delete_member(
     let _: () = (database.execute(delete_statement).await).map_err(|e| {
         error!("Failed to delete member from guild: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to delete member from guild".to_string(),
         )
     })?;
@@ -98,7 +98,7 @@ async fn delete_members(
     let _: () = (database.execute(delete_statement).await).map_err(|e| {
         error!("Failed to delete members from guild: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to delete members from guild".to_string(),
         )
     })?;
@@ -2,18 +2,26 @@ use axum::{Router, routing::get};
 
 mod birthday;
 mod configuration;
+mod giveaway;
 mod info;
 mod member;
 mod settings;
 mod shard;
-
-pub(super) use settings::SettingsBody;
+mod timed_message;
 
 pub fn router() -> Router {
     Router::new()
         .route("/", get(info::get).post(info::create).delete(info::disable))
         .route("/birthday", get(birthday::upcoming))
+        .route(
+            "/birthday/config",
+            get(birthday::get_config)
+                .post(birthday::set_config)
+                .delete(birthday::delete_config),
+        )
         .route("/shard", get(shard::get))
+        .nest("/giveaways", giveaway::router())
+        .nest("/timed-messages", timed_message::router())
         .nest("/member", member::router())
         .nest("/settings", settings::router())
 }
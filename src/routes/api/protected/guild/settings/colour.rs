@@ -1,4 +1,4 @@
 use crate::create_settings_path;
 use crate::schema::guild::ColourSchema;
 
-create_settings_path!(ColourSchema);
+create_settings_path!(ColourSchema, Colour);
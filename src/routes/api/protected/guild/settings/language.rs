@@ -1,4 +1,4 @@
 use crate::create_settings_path;
 use crate::schema::guild::LanguageSchema;
 
-create_settings_path!(LanguageSchema);
+create_settings_path!(LanguageSchema, Language);
@@ -1,16 +1,12 @@
 use axum::{Extension, Json, Router, extract::Path, routing::get};
 use reqwest::StatusCode;
-use sea_query::{DeleteStatement, Expr, InsertStatement, SelectStatement};
-use serde::{Deserialize, Serialize};
+use worker::Env;
 
-use crate::{
-    schema::guild::{
-        ColourSchema, Colours, LanguageSchema, Languages, PrefixSchema, Prefixes, TimezoneSchema,
-        Timezones,
-    },
-    services::streaming::StreamableSchema,
-    state::database::{Database, DatabaseExt},
-};
+use crate::durables::bot::ConfigSection;
+use crate::schema::guild::SettingsBody;
+use crate::services::cache::{SettingsCache, SettingsSection};
+use crate::services::{config, dashboard};
+use crate::state::database::{Database, DatabaseExt};
 
 mod colour;
 mod language;
@@ -45,103 +41,30 @@ pub fn router() -> Router {
         )
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SettingsBody {
-    pub prefix: Option<String>,
-    pub language: Option<String>,
-    pub timezone: Option<String>,
-    pub colour: Option<String>,
-}
-
-impl SettingsBody {
-    pub fn get(guild_id: &str) -> SelectStatement {
-        let prefix_col = (Prefixes::Table, Prefixes::GuildId);
-        let language_col = (Languages::Table, Languages::GuildId);
-        let timezone_col = (Timezones::Table, Timezones::GuildId);
-        let colour_col = (Colours::Table, Colours::GuildId);
-        SelectStatement::new()
-            .column(Prefixes::Prefix)
-            .from(Prefixes::Table)
-            .and_where(Expr::col(prefix_col).eq(guild_id))
-            .left_join(Languages::Table, Expr::col(prefix_col).equals(language_col))
-            .left_join(Timezones::Table, Expr::col(prefix_col).equals(timezone_col))
-            .left_join(Colours::Table, Expr::col(prefix_col).equals(colour_col))
-            .to_owned()
-    }
-
-    pub fn set(guild_id: &str, data: &SettingsBody) -> Vec<InsertStatement> {
-        let mut queries = Vec::new();
-        if let Some(prefix) = &data.prefix {
-            queries.push(PrefixSchema::insert(guild_id, prefix));
-        }
-        if let Some(language) = &data.language {
-            queries.push(LanguageSchema::insert(guild_id, language));
-        }
-        if let Some(timezone) = &data.timezone {
-            queries.push(TimezoneSchema::insert(guild_id, timezone));
-        }
-        if let Some(colour) = &data.colour {
-            queries.push(ColourSchema::insert(guild_id, colour));
-        }
-        queries
-    }
-
-    pub fn delete(guild_id: &str, data: &SettingsBody) -> Vec<DeleteStatement> {
-        let mut queries = Vec::new();
-        if data.prefix.is_none() {
-            queries.push(PrefixSchema::delete(guild_id));
-        }
-        if data.language.is_none() {
-            queries.push(LanguageSchema::delete(guild_id));
-        }
-        if data.timezone.is_none() {
-            queries.push(TimezoneSchema::delete(guild_id));
-        }
-        if data.colour.is_none() {
-            queries.push(ColourSchema::delete(guild_id));
-        }
-        queries
-    }
-}
-
-impl StreamableSchema for SettingsBody {
-    fn all_by_batch(batch_size: u64, offset: u64) -> sea_query::SelectStatement {
-        let prefix_col = (Prefixes::Table, Prefixes::GuildId);
-        let language_col = (Languages::Table, Languages::GuildId);
-        let timezone_col = (Timezones::Table, Timezones::GuildId);
-        let colour_col = (Colours::Table, Colours::GuildId);
-        SelectStatement::new()
-            .column(Prefixes::Prefix)
-            .from(Prefixes::Table)
-            .left_join(Languages::Table, Expr::col(prefix_col).equals(language_col))
-            .left_join(Timezones::Table, Expr::col(prefix_col).equals(timezone_col))
-            .left_join(Colours::Table, Expr::col(prefix_col).equals(colour_col))
-            .limit(batch_size)
-            .offset(offset)
-            .to_owned()
-    }
-}
-
+/// Every setting of a guild, unset ones are `null`
+///
+/// Served from [`SettingsCache`]. Writes invalidate it in the data center that handled them,
+/// other data centers may keep serving the previous settings for up to
+/// [`CACHE_TTL_SECONDS`](crate::services::cache::CACHE_TTL_SECONDS).
 #[worker::send]
 #[axum::debug_handler]
 pub async fn get_settings(
     Path(guild_id): Path<String>,
     Extension(database): Extension<Database>,
 ) -> Result<Json<SettingsBody>, (StatusCode, String)> {
+    let cache = SettingsCache::new();
+    if let Some(settings) = cache.get(&guild_id, SettingsSection::All).await {
+        return Ok(Json(settings));
+    }
+
     let query = SettingsBody::get(&guild_id);
-    let settings: Vec<SettingsBody> = (database.execute(query).await).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get settings: {:?}", e),
-        )
-    })?;
+    let settings: Vec<SettingsBody> = (database.execute(query).await)
+        .map_err(|e| (e.status(), format!("Failed to get settings: {}", e)))?;
     if let Some(settings) = settings.first() {
+        cache.put(&guild_id, SettingsSection::All, settings).await;
         Ok(Json(settings.clone()))
     } else {
-        Err((
-            StatusCode::NOT_FOUND,
-            "{} not found for this guild".to_string(),
-        ))
+        Err((StatusCode::NOT_FOUND, "Guild not found".to_string()))
     }
 }
 
@@ -150,15 +73,17 @@ pub async fn get_settings(
 pub async fn set_settings(
     Path(guild_id): Path<String>,
     Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
     Json(new_data): Json<SettingsBody>,
 ) -> Result<(), (StatusCode, String)> {
     let queries = SettingsBody::set(&guild_id, &new_data);
-    let _: () = database.batch(&queries).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to set settings: {:?}", e),
-        )
-    })?;
+    let _: () = database
+        .batch(&queries)
+        .await
+        .map_err(|e| (e.status(), format!("Failed to set settings: {}", e)))?;
+    SettingsCache::new().invalidate(&guild_id).await;
+    config::publish(&env, &guild_id, ConfigSection::Settings).await;
+    dashboard::settings_updated(&env, &guild_id, SettingsSection::All).await;
     Ok(())
 }
 
@@ -167,40 +92,56 @@ pub async fn set_settings(
 pub async fn delete_settings(
     Path(guild_id): Path<String>,
     Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
     Json(data): Json<SettingsBody>,
 ) -> Result<(), (StatusCode, String)> {
     let queries = SettingsBody::delete(&guild_id, &data);
-    let _: () = database.batch(&queries).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete settings: {:?}", e),
-        )
-    })?;
+    let _: () = database
+        .batch(&queries)
+        .await
+        .map_err(|e| (e.status(), format!("Failed to delete settings: {}", e)))?;
+    SettingsCache::new().invalidate(&guild_id).await;
+    config::publish(&env, &guild_id, ConfigSection::Settings).await;
+    dashboard::settings_updated(&env, &guild_id, SettingsSection::All).await;
     Ok(())
 }
 
 #[macro_export]
 macro_rules! create_settings_path {
-    ($struct:ident) => {
+    ($struct:ident, $section:ident) => {
         use axum::{Extension, Json, extract::Path};
         use reqwest::StatusCode;
+        use worker::Env;
+        use $crate::durables::bot::ConfigSection;
+        use $crate::services::cache::{SettingsCache, SettingsSection};
+        use $crate::services::{config, dashboard};
         use $crate::state::database::{Database, DatabaseExt};
 
+        /// Served from [`SettingsCache`], other data centers may keep serving the previous value
+        /// for up to [`CACHE_TTL_SECONDS`](crate::services::cache::CACHE_TTL_SECONDS) after a write
         #[worker::send]
         #[axum::debug_handler]
         pub async fn get(
             Path(guild_id): Path<String>,
             Extension(database): Extension<Database>,
         ) -> Result<Json<$struct>, (StatusCode, String)> {
+            let cache = SettingsCache::new();
+            if let Some(cached) = cache.get(&guild_id, SettingsSection::$section).await {
+                return Ok(Json(cached));
+            }
+
             let query = $struct::get(&guild_id);
             let prefix: Vec<$struct> = (database.execute(query).await).map_err(|e| {
                 (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to get {}: {:?}", stringify!($struct), e),
+                    e.status(),
+                    format!("Failed to get {}: {}", stringify!($struct), e),
                 )
             })?;
 
             if let Some(prefix) = prefix.first() {
+                cache
+                    .put(&guild_id, SettingsSection::$section, prefix)
+                    .await;
                 Ok(Json(prefix.clone()))
             } else {
                 Err((
@@ -215,15 +156,19 @@ macro_rules! create_settings_path {
         pub async fn set(
             Path(guild_id): Path<String>,
             Extension(database): Extension<Database>,
+            Extension(env): Extension<Env>,
             Json(new_data): Json<String>,
         ) -> Result<(), (StatusCode, String)> {
             let query = $struct::insert(&guild_id, &new_data);
             let _: () = (database.execute(query).await).map_err(|e| {
                 (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to set {}: {:?}", stringify!($struct), e),
+                    e.status(),
+                    format!("Failed to set {}: {}", stringify!($struct), e),
                 )
             })?;
+            SettingsCache::new().invalidate(&guild_id).await;
+            config::publish(&env, &guild_id, ConfigSection::$section).await;
+            dashboard::settings_updated(&env, &guild_id, SettingsSection::$section).await;
 
             Ok(())
         }
@@ -233,14 +178,18 @@ macro_rules! create_settings_path {
         pub async fn delete(
             Path(guild_id): Path<String>,
             Extension(database): Extension<Database>,
+            Extension(env): Extension<Env>,
         ) -> Result<(), (StatusCode, String)> {
             let query = $struct::delete(&guild_id);
             let _: () = (database.execute(query).await).map_err(|e| {
                 (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to delete {}: {:?}", stringify!($struct), e),
+                    e.status(),
+                    format!("Failed to delete {}: {}", stringify!($struct), e),
                 )
             })?;
+            SettingsCache::new().invalidate(&guild_id).await;
+            config::publish(&env, &guild_id, ConfigSection::$section).await;
+            dashboard::settings_updated(&env, &guild_id, SettingsSection::$section).await;
 
             Ok(())
         }
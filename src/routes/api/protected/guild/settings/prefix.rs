@@ -1,4 +1,4 @@
 use crate::create_settings_path;
 use crate::schema::guild::PrefixSchema;
 
-create_settings_path!(PrefixSchema);
+create_settings_path!(PrefixSchema, Prefix);
@@ -1,4 +1,4 @@
 use crate::create_settings_path;
 use crate::schema::guild::TimezoneSchema;
 
-create_settings_path!(TimezoneSchema);
+create_settings_path!(TimezoneSchema, Timezone);
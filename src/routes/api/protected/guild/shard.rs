@@ -24,7 +24,7 @@ pub async fn get(
         .map_err(|e| {
             warn!("Failed to get shard for guild {}: {:?}", guild_id, e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to get shard for guild".to_string(),
             )
         })?;
@@ -0,0 +1,332 @@
+use axum::{
+    Extension, Json, Router,
+    extract::Path,
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tracing::{debug, error, warn};
+use worker::Env;
+
+use crate::{
+    durables::bot::{BotDurable, ConfigSection, ScheduledAlarm},
+    schema::guild::TimedMessagesSchema,
+    services::{
+        config,
+        timed_messages::{self, Schedule},
+    },
+    snowflake_protection,
+    state::{
+        database::{Database, DatabaseExt, DbResult},
+        user::RequestedUser,
+    },
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(get_messages).post(create_message))
+        .route("/{message_id}", get(get_message).delete(delete_message))
+        .route("/{message_id}/pause", post(pause_message))
+        .route("/{message_id}/resume", post(resume_message))
+        .route("/{message_id}/skip", post(skip_message))
+}
+
+#[derive(Debug, Deserialize)]
+struct NewTimedMessage {
+    channel_id: String,
+    message: String,
+    /// Seconds between each message, mutually exclusive with `cron`
+    interval: Option<i64>,
+    /// Cron expression evaluated in the guild's timezone
+    cron: Option<String>,
+    author_id: String,
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn get_messages(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<Vec<TimedMessagesSchema>>, (StatusCode, String)> {
+    let query = TimedMessagesSchema::get_guild_messages(&guild_id);
+    let messages: Vec<TimedMessagesSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get timed messages: {:?}", e);
+        (e.status(), "Failed to get timed messages".to_string())
+    })?;
+    Ok(Json(messages))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn get_message(
+    Path((guild_id, message_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    let message = find_message(&database, &guild_id, message_id).await?;
+    Ok(Json(message))
+}
+
+/// Store a timed message and schedule its first run in the bot's durable object
+#[worker::send]
+#[axum::debug_handler]
+async fn create_message(
+    Path(guild_id): Path<String>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+    Json(body): Json<NewTimedMessage>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Create Timed Message")?;
+    let channel_id = body.channel_id;
+    snowflake_protection!(channel_id);
+    let author_id = body.author_id;
+    snowflake_protection!(author_id);
+
+    let schedule = Schedule::new(body.interval, body.cron.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let now = Utc::now();
+    let next_run_at = first_run(&database, &guild_id, &schedule, now).await?;
+    debug!(
+        "Creating timed message in guild {}, first run at {}",
+        guild_id, next_run_at
+    );
+
+    let insert = TimedMessagesSchema::insert(
+        &guild_id,
+        &channel_id,
+        &body.message,
+        body.interval.unwrap_or_default(),
+        body.cron,
+        &author_id,
+        &next_run_at.to_rfc3339(),
+    );
+    let result: Vec<TimedMessagesSchema> = (database.execute(insert).await).map_err(|e| {
+        error!("Failed to create timed message: {:?}", e);
+        (e.status(), "Failed to create timed message".to_string())
+    })?;
+    let Some(message) = result.into_iter().next() else {
+        error!("Failed to create timed message: No results returned");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create timed message".to_string(),
+        ));
+    };
+
+    if let Err(e) = schedule_run(&env, message.id, next_run_at).await {
+        error!("Failed to schedule timed message {}: {}", message.id, e);
+        // A stored message that never runs is worse than a failed request
+        let rollback: DbResult<Vec<TimedMessagesSchema>> = database
+            .execute(TimedMessagesSchema::delete_timed_message(message.id))
+            .await;
+        if let Err(e) = rollback {
+            error!(
+                "Failed to remove unscheduled timed message {}: {:?}",
+                message.id, e
+            );
+        }
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to schedule timed message".to_string(),
+        ));
+    }
+
+    config::publish(&env, &guild_id, ConfigSection::TimedMessages).await;
+    Ok(Json(message))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+async fn delete_message(
+    Path((guild_id, message_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Delete Timed Message")?;
+    let query = TimedMessagesSchema::delete_guild_message(message_id, &guild_id);
+    let removed: Vec<TimedMessagesSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to delete timed message: {:?}", e);
+        (e.status(), "Failed to delete timed message".to_string())
+    })?;
+    let Some(message) = removed.into_iter().next() else {
+        return Err((StatusCode::NOT_FOUND, "Timed message not found".to_string()));
+    };
+
+    // The alarm unschedules itself when the message is gone
+    cancel_run(&env, message.id).await;
+    config::publish(&env, &guild_id, ConfigSection::TimedMessages).await;
+    Ok(Json(message))
+}
+
+/// Stop sending a message, keeping it stored
+#[worker::send]
+#[axum::debug_handler]
+async fn pause_message(
+    Path((guild_id, message_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Pause Timed Message")?;
+    find_message(&database, &guild_id, message_id).await?;
+
+    let message = update_message(
+        &database,
+        TimedMessagesSchema::set_paused(message_id, true, None),
+    )
+    .await?;
+    cancel_run(&env, message_id).await;
+    config::publish(&env, &guild_id, ConfigSection::TimedMessages).await;
+    Ok(Json(message))
+}
+
+/// Send a paused message again, from its next run after now
+#[worker::send]
+#[axum::debug_handler]
+async fn resume_message(
+    Path((guild_id, message_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Resume Timed Message")?;
+    let message = find_message(&database, &guild_id, message_id).await?;
+    if !message.paused {
+        return Err((
+            StatusCode::CONFLICT,
+            "Timed message is not paused".to_string(),
+        ));
+    }
+
+    let schedule = Schedule::of(&message).map_err(|e| {
+        error!(
+            "Timed message {} has an invalid schedule: {}",
+            message_id, e
+        );
+        (StatusCode::UNPROCESSABLE_ENTITY, e)
+    })?;
+    let next_run_at = first_run(&database, &guild_id, &schedule, Utc::now()).await?;
+    let update = TimedMessagesSchema::set_paused(message_id, false, Some(next_run_at.to_rfc3339()));
+    let message = update_message(&database, update).await?;
+
+    schedule_run(&env, message_id, next_run_at)
+        .await
+        .map_err(|e| {
+            error!("Failed to schedule timed message {}: {}", message_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to schedule timed message".to_string(),
+            )
+        })?;
+    config::publish(&env, &guild_id, ConfigSection::TimedMessages).await;
+    Ok(Json(message))
+}
+
+/// Skip the upcoming run, moving the message to the run after it
+#[worker::send]
+#[axum::debug_handler]
+async fn skip_message(
+    Path((guild_id, message_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<TimedMessagesSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Skip Timed Message")?;
+    let message = find_message(&database, &guild_id, message_id).await?;
+    let (false, Some(current)) = (message.paused, message.next_run_at.clone()) else {
+        return Err((StatusCode::CONFLICT, "Timed message is paused".to_string()));
+    };
+    let due = timed_messages::parse_time(&current).unwrap_or_else(Utc::now);
+
+    let schedule = Schedule::of(&message).map_err(|e| {
+        error!(
+            "Timed message {} has an invalid schedule: {}",
+            message_id, e
+        );
+        (StatusCode::UNPROCESSABLE_ENTITY, e)
+    })?;
+    let timezone = (timed_messages::guild_timezone(&database, &guild_id).await).map_err(|e| {
+        error!("Failed to get timezone of guild {}: {:?}", guild_id, e);
+        (e.status(), "Failed to get guild timezone".to_string())
+    })?;
+    let Some(next_run_at) = schedule.next_run(due, due.max(Utc::now()), timezone) else {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Timed message has no run after the next one".to_string(),
+        ));
+    };
+
+    let update = TimedMessagesSchema::set_next_run(message_id, &current, &next_run_at.to_rfc3339());
+    let message = update_message(&database, update).await?;
+    schedule_run(&env, message_id, next_run_at)
+        .await
+        .map_err(|e| {
+            error!("Failed to schedule timed message {}: {}", message_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to schedule timed message".to_string(),
+            )
+        })?;
+    config::publish(&env, &guild_id, ConfigSection::TimedMessages).await;
+    Ok(Json(message))
+}
+
+/// The timed message, as long as it belongs to the guild
+async fn find_message(
+    database: &Database,
+    guild_id: &String,
+    message_id: i64,
+) -> Result<TimedMessagesSchema, (StatusCode, String)> {
+    let query = TimedMessagesSchema::get_timed_message(message_id);
+    let messages: Vec<TimedMessagesSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get timed message {}: {:?}", message_id, e);
+        (e.status(), "Failed to get timed message".to_string())
+    })?;
+    (messages.into_iter())
+        .find(|message| message.guild_id == *guild_id)
+        .ok_or((StatusCode::NOT_FOUND, "Timed message not found".to_string()))
+}
+
+/// Run an update returning the message, a conflict when it changed in the meantime
+async fn update_message(
+    database: &Database,
+    update: sea_query::UpdateStatement,
+) -> Result<TimedMessagesSchema, (StatusCode, String)> {
+    let updated: Vec<TimedMessagesSchema> = (database.execute(update).await).map_err(|e| {
+        error!("Failed to update timed message: {:?}", e);
+        (e.status(), "Failed to update timed message".to_string())
+    })?;
+    updated.into_iter().next().ok_or((
+        StatusCode::CONFLICT,
+        "Timed message changed, try again".to_string(),
+    ))
+}
+
+async fn first_run(
+    database: &Database,
+    guild_id: &str,
+    schedule: &Schedule,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, (StatusCode, String)> {
+    let timezone = (timed_messages::guild_timezone(database, guild_id).await).map_err(|e| {
+        error!("Failed to get timezone of guild {}: {:?}", guild_id, e);
+        (e.status(), "Failed to get guild timezone".to_string())
+    })?;
+    schedule.next_run(now, now, timezone).ok_or((
+        StatusCode::BAD_REQUEST,
+        "Cron expression never matches".to_string(),
+    ))
+}
+
+async fn schedule_run(env: &Env, id: i64, run_at: DateTime<Utc>) -> worker::Result<()> {
+    let alarm = ScheduledAlarm::TimedMessage { id };
+    BotDurable::schedule_alarm(env, run_at.timestamp_millis(), alarm).await
+}
+
+async fn cancel_run(env: &Env, id: i64) {
+    let key = ScheduledAlarm::TimedMessage { id }.key();
+    if let Err(e) = BotDurable::cancel_alarm(env, &key).await {
+        warn!("Failed to cancel run of timed message {}: {}", id, e);
+    }
+}
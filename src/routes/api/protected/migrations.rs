@@ -0,0 +1,45 @@
+use axum::{Extension, Json};
+use reqwest::StatusCode;
+use tracing::{error, info};
+
+use crate::state::{
+    database::{Database, MigrationStatus},
+    user::RequestedUser,
+};
+
+/// Compare the migrations applied to D1 with the ones bundled in this build
+#[worker::send]
+#[axum::debug_handler]
+pub async fn status(
+    Extension(database): Extension<Database>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<MigrationStatus>, (StatusCode, String)> {
+    requested_user.bot_protection("Migration Status")?;
+
+    let status = database.migration_status().await.map_err(|e| {
+        error!("Failed to get migration status: {}", e);
+        (e.status(), "Failed to get migration status".to_string())
+    })?;
+    Ok(Json(status))
+}
+
+/// Apply every pending migration, in order
+#[worker::send]
+#[axum::debug_handler]
+pub async fn apply(
+    Extension(database): Extension<Database>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<MigrationStatus>, (StatusCode, String)> {
+    requested_user.bot_protection("Apply Migrations")?;
+    info!("Applying pending migrations");
+
+    let status = database.apply_migrations().await.map_err(|e| {
+        error!("Failed to apply migrations: {}", e);
+        (e.status(), e.to_string())
+    })?;
+    info!(
+        "Database schema is now at version {}",
+        status.current_version
+    );
+    Ok(Json(status))
+}
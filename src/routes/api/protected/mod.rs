@@ -1,5 +1,6 @@
 mod gateway;
 mod guild;
+mod migrations;
 mod streams;
 mod user;
 
@@ -13,5 +14,13 @@ pub fn router() -> Router {
         .nest("/user/{user_id}", user::router())
         .nest("/stream", streams::router())
         .route("/gateway/{bot_id}", get(gateway::handle_websocket))
+        .route(
+            "/dashboard/gateway",
+            get(gateway::handle_dashboard_websocket),
+        )
+        .route(
+            "/migrations",
+            get(migrations::status).post(migrations::apply),
+        )
         .layer(middleware::from_fn(ware::api_protect::middleware))
 }
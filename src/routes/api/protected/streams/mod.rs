@@ -1,3 +1,4 @@
+use axum::extract::Query;
 use axum::routing::get;
 use axum::{Extension, response::IntoResponse};
 use axum::{Router, middleware};
@@ -8,10 +9,12 @@ use tracing::debug;
 
 use crate::middleware::bot_only;
 
-use crate::routes::api::protected::guild::SettingsBody;
 use crate::schema::AfkStatusSchema;
+use crate::schema::guild::GuildSettingsSchema;
 use crate::schema::user::BirthdaySchema;
-use crate::{services::streaming::setup_stream, state::database::Database};
+use crate::services::cache::SettingsCache;
+use crate::services::streaming::{StreamQuery, setup_cached_stream, setup_stream};
+use crate::state::database::Database;
 
 pub fn router() -> Router {
     Router::new()
@@ -22,15 +25,29 @@ pub fn router() -> Router {
 }
 
 macro_rules! setup_stream_route {
+    ($name:expr, $schema:ty, cached = $cache_key:expr) => {
+        paste! {
+            #[worker::send]
+            #[axum::debug_handler]
+            pub async fn [<get_all_ $name:lower>](
+                Extension(database): Extension<Database>,
+                Query(query): Query<StreamQuery>,
+            ) -> Result<impl IntoResponse, (StatusCode, String)> {
+                debug!("Fetching all {} from the database", $name);
+                setup_cached_stream::<$schema>($name, database, query, $cache_key).await
+            }
+        }
+    };
     ($name:expr, $schema:ty) => {
         paste! {
             #[worker::send]
             #[axum::debug_handler]
             pub async fn [<get_all_ $name:lower>](
                 Extension(database): Extension<Database>,
+                Query(query): Query<StreamQuery>,
             ) -> Result<impl IntoResponse, (StatusCode, String)> {
                 debug!("Fetching all {} from the database", $name);
-                setup_stream::<$schema>($name, database)
+                setup_stream::<$schema>($name, database, query)
             }
         }
     };
@@ -38,4 +55,8 @@ macro_rules! setup_stream_route {
 
 setup_stream_route!("AFKs", AfkStatusSchema);
 setup_stream_route!("Birthdays", BirthdaySchema);
-setup_stream_route!("GuildSettings", SettingsBody);
+setup_stream_route!(
+    "GuildSettings",
+    GuildSettingsSchema,
+    cached = SettingsCache::STREAM_KEY
+);
@@ -2,7 +2,7 @@ use crate::{
     schema::{AfkStatusSchema, user::AfkConfigSchema},
     snowflake_protection,
     state::{
-        database::{Database, DatabaseExt},
+        database::{Database, DatabaseExt, DbError, UnitOfWork},
         user::RequestedUser,
     },
 };
@@ -11,7 +11,6 @@ use axum::{
     extract::{Path, Query},
 };
 use reqwest::StatusCode;
-use sea_query::QueryStatement;
 use serde::Deserialize;
 use tracing::{debug, error, warn};
 
@@ -46,7 +45,7 @@ pub async fn set(
     let result: Vec<AfkStatusSchema> = (database.execute(afk_query).await).map_err(|e| {
         warn!("Failed to set AFK status: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to set AFK status".to_string(),
         )
     })?;
@@ -67,7 +66,7 @@ pub async fn get(
     let users: Vec<AfkStatusSchema> = database.execute(user_query).await.map_err(|e| {
         warn!("Failed to get AFK status: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to get AFK status".to_string(),
         )
     })?;
@@ -94,7 +93,7 @@ pub async fn remove(
     let users: Vec<AfkStatusSchema> = database.execute(afk_query).await.map_err(|e| {
         warn!("Failed to remove AFK status: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to remove AFK status".to_string(),
         )
     })?;
@@ -136,45 +135,41 @@ pub async fn set_config(
 
     let per_guild = body.per_guild;
     let default_reason = body.default_reason.clone();
-    let get_config = QueryStatement::Select(AfkConfigSchema::get(&user_id));
-    let insert_config = QueryStatement::Insert(AfkConfigSchema::insert(
+
+    let mut work = UnitOfWork::new();
+    let old_config = work.add::<AfkConfigSchema>(AfkConfigSchema::get(&user_id));
+    let new_config = work.add::<AfkConfigSchema>(AfkConfigSchema::insert(
         &user_id,
         &per_guild,
         &default_reason,
     ));
 
-    let results = database
-        .simple_batch_mixed::<AfkConfigSchema, AfkConfigSchema, (), ()>(&[
-            get_config,
-            insert_config,
-        ])
-        .await
-        .map_err(|e| {
-            error!("Failed to set AFK config for user_id: {}\n{:?}", user_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to set AFK config".into(),
-            )
-        })?;
-
-    // list of 1 element: new_config only
-    // list of 2 elements: old_config, new_config
-
-    if let Some(new) = results.insert.and_then(|v| v.first().cloned()) {
-        let old = results.select.and_then(|v| v.first().cloned());
+    let results = database.commit(work).await.map_err(|e| {
+        error!("Failed to set AFK config for user_id: {}\n{:?}", user_id, e);
+        (e.status(), "Failed to set AFK config".into())
+    })?;
+
+    let read_error = |e: DbError| {
+        error!("Failed to read AFK config for user_id: {}\n{:?}", user_id, e);
+        (e.status(), "Failed to set AFK config".to_string())
+    };
+    let old = results.first(&old_config).map_err(&read_error)?;
+    let new = results.first(&new_config).map_err(&read_error)?;
+
+    if let Some(new) = new {
         return Ok(Json(AfkConfigResponse {
-            old_config: old.clone(),
-            new_config: new.clone(),
+            old_config: old,
+            new_config: new,
         }));
     }
     error!(
         "Failed to set AFK config for user_id: {}. No results returned.",
         user_id
     );
-    return Err((
+    Err((
         StatusCode::INTERNAL_SERVER_ERROR,
         "Failed to set AFK config: No results returned".to_string(),
-    ));
+    ))
 }
 
 #[worker::send]
@@ -186,7 +181,7 @@ pub async fn get_config(
     let config_query = AfkConfigSchema::get(&user_id);
     let results: Vec<AfkConfigSchema> = database.execute(config_query).await.map_err(|e| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             format!("Failed to get AFK config: {:?}", e),
         )
     })?;
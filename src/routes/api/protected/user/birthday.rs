@@ -34,7 +34,7 @@ pub async fn set(
     let result: Vec<BirthdaySchema> = (database.execute(insert_statement).await).map_err(|e| {
         error!("Failed to set birthday: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to set birthday".to_string(),
         )
     })?;
@@ -56,7 +56,7 @@ pub async fn get(
     let birthday: Vec<BirthdaySchema> = (database.execute(birthday).await).map_err(|e| {
         error!("Failed to get birthday: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to get birthday".to_string(),
         )
     })?;
@@ -74,7 +74,7 @@ pub async fn delete_birthday(
     let result: Vec<BirthdaySchema> = (database.execute(delete_statement).await).map_err(|e| {
         error!("Failed to delete birthday: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to delete birthday".to_string(),
         )
     })?;
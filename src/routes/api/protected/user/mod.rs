@@ -1,11 +1,17 @@
-use axum::{Router, routing::get};
+use axum::{
+    Router,
+    routing::{delete, get},
+};
 
 mod afk;
 mod birthday;
+mod reminder;
 
 pub fn router() -> Router {
     Router::new()
         .route("/afk", get(afk::get).post(afk::set).delete(afk::remove))
         .route("/afk/config", get(afk::get_config).post(afk::set_config))
         .route("/birthday", get(birthday::get).post(birthday::set))
+        .route("/reminders", get(reminder::get).post(reminder::create))
+        .route("/reminders/{reminder_id}", delete(reminder::remove))
 }
@@ -0,0 +1,129 @@
+use axum::{Extension, Json, extract::Path};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tracing::{debug, error, warn};
+use worker::Env;
+
+use crate::{
+    durables::bot::{BotDurable, ScheduledAlarm},
+    schema::guild::RemindersSchema,
+    snowflake_protection,
+    state::{
+        database::{Database, DatabaseExt, DbResult},
+        user::RequestedUser,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct NewReminder {
+    guild_id: String,
+    /// Channel to send the reminder in, DMs when missing
+    channel_id: Option<String>,
+    message: String,
+    remind_at: DateTime<Utc>,
+}
+
+#[worker::send]
+#[axum::debug_handler]
+pub async fn get(
+    Path(user_id): Path<String>,
+    Extension(database): Extension<Database>,
+) -> Result<Json<Vec<RemindersSchema>>, (StatusCode, String)> {
+    let query = RemindersSchema::get_user_reminders(&user_id);
+    let reminders: Vec<RemindersSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to get reminders: {:?}", e);
+        (e.status(), "Failed to get reminders".to_string())
+    })?;
+    Ok(Json(reminders))
+}
+
+/// Store a reminder and schedule its delivery in the bot's durable object
+#[worker::send]
+#[axum::debug_handler]
+pub async fn create(
+    Path(user_id): Path<String>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+    Json(body): Json<NewReminder>,
+) -> Result<Json<RemindersSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Create Reminder")?;
+    let guild_id = body.guild_id;
+    snowflake_protection!(guild_id);
+    if let Some(channel_id) = &body.channel_id {
+        snowflake_protection!(channel_id);
+    }
+    debug!(
+        "Creating reminder for user_id: {} at {}",
+        user_id, body.remind_at
+    );
+
+    let insert = RemindersSchema::insert(
+        &user_id,
+        &guild_id,
+        body.channel_id,
+        &body.message,
+        &body.remind_at,
+    );
+    let result: Vec<RemindersSchema> = (database.execute(insert).await).map_err(|e| {
+        error!("Failed to create reminder: {:?}", e);
+        (e.status(), "Failed to create reminder".to_string())
+    })?;
+    let Some(reminder) = result.into_iter().next() else {
+        error!("Failed to create reminder: No results returned");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create reminder".to_string(),
+        ));
+    };
+
+    let alarm = ScheduledAlarm::Reminder { id: reminder.id };
+    let run_at = body.remind_at.timestamp_millis();
+    if let Err(e) = BotDurable::schedule_alarm(&env, run_at, alarm).await {
+        error!("Failed to schedule reminder {}: {}", reminder.id, e);
+        // A stored reminder that never fires is worse than a failed request
+        let rollback: DbResult<Vec<RemindersSchema>> = database
+            .execute(RemindersSchema::delete_reminder(reminder.id))
+            .await;
+        if let Err(e) = rollback {
+            error!(
+                "Failed to remove unscheduled reminder {}: {:?}",
+                reminder.id, e
+            );
+        }
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to schedule reminder".to_string(),
+        ));
+    }
+
+    Ok(Json(reminder))
+}
+
+#[worker::send]
+#[axum::debug_handler]
+pub async fn remove(
+    Path((user_id, reminder_id)): Path<(String, i64)>,
+    Extension(database): Extension<Database>,
+    Extension(env): Extension<Env>,
+    Extension(requested_user): Extension<RequestedUser>,
+) -> Result<Json<RemindersSchema>, (StatusCode, String)> {
+    requested_user.bot_protection("Remove Reminder")?;
+    let query = RemindersSchema::delete_user_reminder(reminder_id, &user_id);
+    let removed: Vec<RemindersSchema> = (database.execute(query).await).map_err(|e| {
+        error!("Failed to remove reminder: {:?}", e);
+        (e.status(), "Failed to remove reminder".to_string())
+    })?;
+    let Some(reminder) = removed.into_iter().next() else {
+        return Err((StatusCode::NOT_FOUND, "Reminder not found".to_string()));
+    };
+
+    // Delivery skips reminders that no longer exist, so a failure here only leaves a no-op alarm
+    let key = ScheduledAlarm::Reminder { id: reminder.id }.key();
+    if let Err(e) = BotDurable::cancel_alarm(&env, &key).await {
+        warn!("Failed to cancel alarm of reminder {}: {}", reminder.id, e);
+    }
+
+    Ok(Json(reminder))
+}
@@ -1,6 +1,6 @@
 use axum::{
     Extension, Json, Router,
-    extract::{Path, Request},
+    extract::{Path, Query, Request},
     routing::{get, post},
 };
 use reqwest::StatusCode;
@@ -11,7 +11,7 @@ use worker::{Env, Stub};
 use crate::{
     durables::{
         DurableFetch,
-        bot::{BotDurable, ShardUpdatePayload},
+        bot::{BotDurable, HistoryWindow, ShardHistoryPayload, ShardUpdatePayload},
     },
     schema::{ShardSchema, Shards, guild::Guild},
     state::{
@@ -23,6 +23,7 @@ use crate::{
 pub fn router() -> Router {
     Router::new()
         .route("/", get(get_all_shards))
+        .route("/history", get(get_shard_history))
         .route("/{shard_id}", get(get_shard).post(set_shard_started))
         .route("/started/{count}", post(set_started_shards))
 }
@@ -56,18 +57,19 @@ async fn get_all_shards(
         .await
         .map_err(|e| {
             error!("Failed to get guild counts from database: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "".to_string())
+            (e.status(), "".to_string())
         })?;
 
     let output =
         (shards_with_guild_counts.iter())
             .map(|info| {
-                let shard = shard_datas.get(info.shard_id as usize).cloned().unwrap_or(
-                    ShardUpdatePayload {
+                let shard = (shard_datas.iter())
+                    .find(|shard| shard.shard_id == info.shard_id)
+                    .cloned()
+                    .unwrap_or(ShardUpdatePayload {
                         shard_id: info.shard_id,
                         ..Default::default()
-                    },
-                );
+                    });
                 ShardWithGuildCount {
                     shard: shard.shard_id,
                     status: shard.status.clone(),
@@ -75,6 +77,9 @@ async fn get_all_shards(
                     guilds: info.guild_count,
                     users: shard.members,
                     started: info.started_at.clone(),
+                    last_seen: (shard.last_seen)
+                        .and_then(chrono::DateTime::from_timestamp_millis)
+                        .map(|last_seen| last_seen.to_rfc3339()),
                 }
             })
             .collect::<Vec<_>>();
@@ -82,6 +87,30 @@ async fn get_all_shards(
     Ok(Json(output))
 }
 
+#[derive(serde::Deserialize)]
+struct HistoryQuery {
+    #[serde(default)]
+    window: HistoryWindow,
+}
+
+/// Latency, status transitions and member counts of every shard over the last 1h, 24h or 7d
+#[worker::send]
+#[axum::debug_handler]
+async fn get_shard_history(
+    Extension(env): Extension<Env>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<ShardHistoryPayload>>, (StatusCode, String)> {
+    info!("Fetching shard history over {:?}", query.window);
+    let history = (BotDurable::shard_history(&env, query.window).await).map_err(|e| {
+        error!("Failed to get shard history from durable object: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to get shard history".to_string(),
+        )
+    })?;
+    Ok(Json(history))
+}
+
 #[derive(serde::Deserialize)]
 pub struct ShardGuildCount {
     #[serde(rename = "id")]
@@ -147,7 +176,7 @@ async fn get_shard(
         .map_err(|e| {
             warn!("Failed to get shard for shard {}: {:?}", shard_id, e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to get shard for shard".to_string(),
             )
         })?;
@@ -180,13 +209,13 @@ async fn set_shard_started(
         )
     })?;
 
-    (database
+    let _: () = (database
         .execute(ShardSchema::set_started_at(shard_id))
         .await)
         .map_err(|e| {
             error!("Failed to set shard {} as started: {:?}", shard_id, e);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 "Failed to set shard as started".to_string(),
             )
         })?;
@@ -217,7 +246,7 @@ async fn set_started_shards(
     let _: Vec<()> = database.batch(&queries).await.map_err(|e| {
         error!("Failed to insert new shards: {:?}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status(),
             "Failed to insert new shards".to_string(),
         )
     })?;
@@ -234,4 +263,6 @@ struct ShardWithGuildCount {
     guilds: u32,
     users: u32,
     started: Option<String>,
+    /// Last report of the bot on the shard, `status` turns `stale` then `zombie` without one
+    last_seen: Option<String>,
 }
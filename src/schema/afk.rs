@@ -1,101 +1,60 @@
-use sea_query::{Expr, Iden, SimpleExpr};
+use schema_derive::Schema;
+use sea_query::{Expr, Order};
 use serde::{Deserialize, Serialize};
 
 use crate::services::streaming::StreamableSchema;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "afk_statuses",
+    iden = AfkStatus,
+    key(user_id, guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct AfkStatusSchema {
     pub user_id: String,
     pub guild_id: Option<String>,
     pub reason: String,
+    #[schema(generated)]
     pub created_at: String,
 }
 
-#[derive(Iden)]
-pub enum AfkStatus {
-    #[iden = "afk_statuses"]
-    Table,
-    #[iden = "user_id"]
-    UserId,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "reason"]
-    Reason,
-    #[iden = "created_at"]
-    CreatedAt,
-}
-
 impl AfkStatusSchema {
+    /// Set an AFK status, replacing the reason of an existing one, returning it
     pub fn insert(
         user_id: impl Into<String>,
         guild_id: &Option<String>,
         reason: impl Into<String>,
     ) -> sea_query::InsertStatement {
-        // let guild_id = guild_id.map(|g| SimpleExpr::);
-        sea_query::Query::insert()
-            .into_table(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
-            .values_panic(vec![
-                user_id.into().into(),
-                Expr::value(guild_id.clone()),
-                reason.into().into(),
-            ])
-            .to_owned()
+        Self {
+            user_id: user_id.into(),
+            guild_id: guild_id.clone(),
+            reason: reason.into(),
+            created_at: String::new(),
+        }
+        .upsert()
     }
 
     pub fn get(user_id: impl Into<String>) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .from(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
+        Self::select_all()
             .and_where(Expr::col(AfkStatus::UserId).eq(user_id.into()))
             .to_owned()
     }
 
     pub fn all() -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .from(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
-            .to_owned()
+        Self::select_all()
     }
 
     pub fn all_by_batch(batch_size: u64, offset: u64) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .from(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
+        Self::select_all()
             .limit(batch_size)
             .offset(offset)
             .to_owned()
     }
 
     pub fn get_guild(guild_id: &str) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .from(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
+        Self::select_all()
             .and_where(Expr::col(AfkStatus::GuildId).eq(guild_id))
             .to_owned()
     }
@@ -113,17 +72,29 @@ impl AfkStatusSchema {
 }
 
 impl StreamableSchema for AfkStatusSchema {
-    fn all_by_batch(batch_size: u64, offset: u64) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .from(AfkStatus::Table)
-            .columns(vec![
-                AfkStatus::UserId,
-                AfkStatus::GuildId,
-                AfkStatus::Reason,
-                AfkStatus::CreatedAt,
-            ])
-            .limit(batch_size)
-            .offset(offset)
-            .to_owned()
+    /// `(user_id, guild_id)`, with an empty guild id for global AFK statuses
+    type Cursor = (String, String);
+
+    fn batch_after(batch_size: u64, after: Option<&Self::Cursor>) -> sea_query::SelectStatement {
+        let guild_key = || Expr::col(AfkStatus::GuildId).if_null("");
+        let mut query = Self::select_all();
+        query
+            .order_by(AfkStatus::UserId, Order::Asc)
+            .order_by_expr(guild_key(), Order::Asc)
+            .limit(batch_size);
+        if let Some((user_id, guild_id)) = after {
+            query.and_where(
+                Expr::tuple([Expr::col(AfkStatus::UserId).into(), guild_key()])
+                    .gt(Expr::tuple([user_id.into(), guild_id.into()])),
+            );
+        }
+        query.to_owned()
+    }
+
+    fn cursor(&self) -> Self::Cursor {
+        (
+            self.user_id.clone(),
+            self.guild_id.clone().unwrap_or_default(),
+        )
     }
 }
@@ -1,7 +1,15 @@
-use sea_query::{Iden, InsertStatement, OnConflict, Query, UpdateStatement};
+use schema_derive::Schema;
+use sea_query::{Expr, InsertStatement, OnConflict, Query, UpdateStatement};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "user_levels",
+    iden = UserLevels,
+    key(user_id, guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct UserLevelsSchema {
     pub user_id: String,
     pub guild_id: String,
@@ -10,106 +18,60 @@ pub struct UserLevelsSchema {
     pub last_message_at: String,
 }
 
-#[derive(Iden)]
-pub enum UserLevels {
-    #[iden = "user_levels"]
-    Table,
-    #[iden = "user_id"]
-    UserId,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "level"]
-    Level,
-    #[iden = "xp"]
-    Xp,
-    #[iden = "last_message_at"]
-    LastMessageAt,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "level_configs",
+    iden = LevelConfigs,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct LevelConfigsSchema {
     pub guild_id: String,
     pub minimum_xp_gain: i32,
     pub maximum_xp_gain: i32,
     pub level_up_message: String,
     pub channel_id: Option<String>,
+    #[schema(generated)]
     pub created_at: String,
     pub updated_at: String,
 }
 
-#[derive(Iden)]
-pub enum LevelConfigs {
-    #[iden = "level_configs"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "minimum_xp_gain"]
-    MinimumXpGain,
-    #[iden = "maximum_xp_gain"]
-    MaximumXpGain,
-    #[iden = "level_up_message"]
-    LevelUpMessage,
-    #[iden = "channel_id"]
-    ChannelId,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "level_roles",
+    iden = LevelRoles,
+    key(guild_id, role_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct LevelRolesSchema {
     pub guild_id: String,
     pub role_id: String,
     pub level: i32,
     pub stackable: bool,
+    #[schema(generated)]
     pub created_at: String,
     pub updated_at: String,
 }
 
-#[derive(Iden)]
-pub enum LevelRoles {
-    #[iden = "level_roles"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "role_id"]
-    RoleId,
-    #[iden = "level"]
-    Level,
-    #[iden = "stackable"]
-    Stackable,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "level_xp_multipliers",
+    iden = LevelXpMultipliers,
+    key(guild_id, role_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct LevelXpMultipliersSchema {
     pub guild_id: String,
     pub multiplier: f64,
     pub role_id: Option<String>,
+    #[schema(generated)]
     pub created_at: String,
     pub updated_at: String,
 }
 
-#[derive(Iden)]
-pub enum LevelXpMultipliers {
-    #[iden = "level_xp_multipliers"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "multiplier"]
-    Multiplier,
-    #[iden = "role_id"]
-    RoleId,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
-}
-
 // Implementations
 impl UserLevelsSchema {
     pub fn insert_or_update(
@@ -140,6 +102,36 @@ impl UserLevelsSchema {
             .to_owned()
     }
 
+    /// Add `xp` to the member's total, starting from zero when they have none yet
+    pub fn award_xp(user_id: &String, guild_id: &String, xp: i32) -> InsertStatement {
+        let current_time = chrono::Utc::now().to_rfc3339();
+        let on_conflict = OnConflict::columns([UserLevels::UserId, UserLevels::GuildId])
+            .value(
+                UserLevels::Xp,
+                Expr::col((UserLevels::Table, UserLevels::Xp)).add(xp),
+            )
+            .update_column(UserLevels::LastMessageAt)
+            .to_owned();
+
+        Query::insert()
+            .into_table(UserLevels::Table)
+            .columns(vec![
+                UserLevels::UserId,
+                UserLevels::GuildId,
+                UserLevels::Xp,
+                UserLevels::LastMessageAt,
+            ])
+            .values_panic(vec![
+                user_id.clone().into(),
+                guild_id.clone().into(),
+                xp.into(),
+                current_time.into(),
+            ])
+            .on_conflict(on_conflict)
+            .returning_all()
+            .to_owned()
+    }
+
     pub fn update_level(
         user_id: &String,
         guild_id: &String,
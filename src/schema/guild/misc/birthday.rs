@@ -1,29 +1,26 @@
-use sea_query::{Iden, InsertStatement, OnConflict, Query};
+use schema_derive::Schema;
+use sea_query::{
+    DeleteStatement, Expr, InsertStatement, OnConflict, Query, SelectStatement, UpdateStatement,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+use crate::schema::SoftDelete;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "birthday_configs",
+    iden = BirthdayConfigs,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct BirthdayConfigsSchema {
     pub guild_id: String,
     pub channel_id: Option<String>,
     pub message: String,
     pub created_at: String,
     pub updated_at: String,
-}
-
-#[derive(Iden)]
-pub enum BirthdayConfigs {
-    #[iden = "birthday_configs"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "channel_id"]
-    ChannelId,
-    #[iden = "message"]
-    Message,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
+    pub last_announced_on: Option<String>, // Local date, YYYY-MM-DD
 }
 
 impl BirthdayConfigsSchema {
@@ -35,7 +32,7 @@ impl BirthdayConfigsSchema {
         let current_time = chrono::Utc::now().to_rfc3339();
         let birthday_message = message.unwrap_or_else(|| "Happy Birthday {user}! 🎉".to_string());
 
-        let on_conflict = OnConflict::new()
+        let on_conflict = OnConflict::column(BirthdayConfigs::GuildId)
             .update_columns(vec![
                 BirthdayConfigs::ChannelId,
                 BirthdayConfigs::Message,
@@ -60,6 +57,36 @@ impl BirthdayConfigsSchema {
                 current_time.into(),
             ])
             .on_conflict(on_conflict)
+            .returning_all()
+            .to_owned()
+    }
+
+    pub fn get_config(guild_id: &String) -> SelectStatement {
+        Self::select_by_key(guild_id.clone())
+    }
+
+    /// Claim the announcement of `date`, returning nothing when it was already announced
+    ///
+    /// Guarding on the date keeps a retried alarm from announcing the same day twice.
+    pub fn mark_announced(guild_id: &String, date: &String) -> UpdateStatement {
+        Query::update()
+            .table(BirthdayConfigs::Table)
+            .values(vec![(
+                BirthdayConfigs::LastAnnouncedOn,
+                date.clone().into(),
+            )])
+            .and_where(Expr::col(BirthdayConfigs::GuildId).eq(guild_id.clone()))
+            .and_where(
+                Expr::col(BirthdayConfigs::LastAnnouncedOn)
+                    .is_null()
+                    .or(Expr::col(BirthdayConfigs::LastAnnouncedOn).ne(date.clone())),
+            )
+            .and_where(SoftDelete::live(BirthdayConfigs::Table))
+            .returning_all()
             .to_owned()
     }
+
+    pub fn delete_config(guild_id: &String) -> DeleteStatement {
+        Self::delete_by_key(guild_id.clone())
+    }
 }
@@ -1,47 +1,38 @@
-use sea_query::{DeleteStatement, Iden, InsertStatement, Query, UpdateStatement};
+use chrono::{DateTime, Utc};
+use schema_derive::Schema;
+use sea_query::{DeleteStatement, Expr, InsertStatement, Query, SelectStatement};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+use crate::schema::SoftDelete;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "reminders",
+    iden = Reminders,
+    key(id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct RemindersSchema {
-    pub id: i32, // AUTOINCREMENT
+    #[schema(generated)]
+    pub id: i64, // AUTOINCREMENT
     pub user_id: String,
     pub guild_id: String,
-    pub channel_id: Option<String>,
+    pub channel_id: Option<String>, // NULL to send the reminder in DMs
     pub message: String,
-    pub remind_at: String, // TIMESTAMP
+    pub remind_at: String, // TIMESTAMP, RFC 3339
+    #[schema(generated)]
     pub created_at: String,
 }
 
-#[derive(Iden)]
-pub enum Reminders {
-    #[iden = "reminders"]
-    Table,
-    #[iden = "id"]
-    Id,
-    #[iden = "user_id"]
-    UserId,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "channel_id"]
-    ChannelId,
-    #[iden = "message"]
-    Message,
-    #[iden = "remind_at"]
-    RemindAt,
-    #[iden = "created_at"]
-    CreatedAt,
-}
-
 impl RemindersSchema {
     pub fn insert(
         user_id: &String,
         guild_id: &String,
         channel_id: Option<String>,
         message: &String,
-        remind_at: &String,
+        remind_at: &DateTime<Utc>,
     ) -> InsertStatement {
-        let current_time = chrono::Utc::now().to_rfc3339();
-
         Query::insert()
             .into_table(Reminders::Table)
             .columns(vec![
@@ -50,31 +41,50 @@ impl RemindersSchema {
                 Reminders::ChannelId,
                 Reminders::Message,
                 Reminders::RemindAt,
-                Reminders::CreatedAt,
             ])
             .values_panic(vec![
                 user_id.clone().into(),
                 guild_id.clone().into(),
                 channel_id.into(),
                 message.clone().into(),
-                remind_at.clone().into(),
-                current_time.into(),
+                remind_at.to_rfc3339().into(),
             ])
+            .returning_all()
             .to_owned()
     }
 
-    pub fn delete_reminder(id: i32) -> DeleteStatement {
-        Query::delete()
-            .from_table(Reminders::Table)
-            .and_where(sea_query::Expr::col(Reminders::Id).eq(id))
+    /// A pending reminder, unless its guild was soft deleted
+    pub fn get_reminder(id: i64) -> SelectStatement {
+        Self::select_by_key(id)
+    }
+
+    /// Pending reminders of a user, soonest first
+    pub fn get_user_reminders(user_id: &String) -> SelectStatement {
+        Self::select_all()
+            .and_where(Expr::col(Reminders::UserId).eq(user_id.clone()))
+            .order_by(Reminders::RemindAt, sea_query::Order::Asc)
+            .to_owned()
+    }
+
+    /// Delete a reminder, returning it
+    pub fn delete_reminder(id: i64) -> DeleteStatement {
+        Self::delete_by_key(id)
+    }
+
+    /// Delete a reminder of `user_id`, returning it so its alarm can be cancelled
+    pub fn delete_user_reminder(id: i64, user_id: &String) -> DeleteStatement {
+        Self::delete_by_key(id)
+            .and_where(Expr::col(Reminders::UserId).eq(user_id.clone()))
             .to_owned()
     }
 
     pub fn delete_user_reminders(user_id: &String, guild_id: &String) -> DeleteStatement {
         Query::delete()
             .from_table(Reminders::Table)
-            .and_where(sea_query::Expr::col(Reminders::UserId).eq(user_id.clone()))
-            .and_where(sea_query::Expr::col(Reminders::GuildId).eq(guild_id.clone()))
+            .and_where(Expr::col(Reminders::UserId).eq(user_id.clone()))
+            .and_where(Expr::col(Reminders::GuildId).eq(guild_id.clone()))
+            .and_where(SoftDelete::live(Reminders::Table))
+            .returning_all()
             .to_owned()
     }
 }
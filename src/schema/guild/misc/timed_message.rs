@@ -1,35 +1,32 @@
-use sea_query::{DeleteStatement, Iden, InsertStatement, Query, UpdateStatement};
+use super::super::deserialize_bool;
+use schema_derive::Schema;
+use sea_query::{DeleteStatement, Expr, InsertStatement, Query, SelectStatement, UpdateStatement};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "timed_messages",
+    iden = TimedMessages,
+    key(id),
+    stream,
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct TimedMessagesSchema {
-    pub id: i32, // AUTOINCREMENT
+    #[schema(generated)]
+    pub id: i64, // AUTOINCREMENT
     pub guild_id: String,
     pub channel_id: String,
     pub message: String,
-    pub interval: String, // e.g., 'daily', 'weekly', 'monthly'
-    pub send_at: String,  // TIMESTAMP
+    pub interval: i64, // Seconds between each message, 0 when `cron` is set
+    pub author_id: String,
+    #[schema(generated)]
     pub created_at: String,
-}
-
-#[derive(Iden)]
-pub enum TimedMessages {
-    #[iden = "timed_messages"]
-    Table,
-    #[iden = "id"]
-    Id,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "channel_id"]
-    ChannelId,
-    #[iden = "message"]
-    Message,
-    #[iden = "interval"]
-    Interval,
-    #[iden = "send_at"]
-    SendAt,
-    #[iden = "created_at"]
-    CreatedAt,
+    pub cron: Option<String>, // Evaluated in the guild's timezone
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub paused: bool,
+    pub next_run_at: Option<String>, // TIMESTAMP, RFC 3339
+    pub last_run_at: Option<String>, // TIMESTAMP, RFC 3339
 }
 
 impl TimedMessagesSchema {
@@ -37,11 +34,11 @@ impl TimedMessagesSchema {
         guild_id: &String,
         channel_id: &String,
         message: &String,
-        interval: &String,
-        send_at: &String,
+        interval: i64,
+        cron: Option<String>,
+        author_id: &String,
+        next_run_at: &String,
     ) -> InsertStatement {
-        let current_time = chrono::Utc::now().to_rfc3339();
-
         Query::insert()
             .into_table(TimedMessages::Table)
             .columns(vec![
@@ -49,32 +46,80 @@ impl TimedMessagesSchema {
                 TimedMessages::ChannelId,
                 TimedMessages::Message,
                 TimedMessages::Interval,
-                TimedMessages::SendAt,
-                TimedMessages::CreatedAt,
+                TimedMessages::Cron,
+                TimedMessages::AuthorId,
+                TimedMessages::NextRunAt,
             ])
             .values_panic(vec![
                 guild_id.clone().into(),
                 channel_id.clone().into(),
                 message.clone().into(),
-                interval.clone().into(),
-                send_at.clone().into(),
-                current_time.into(),
+                interval.into(),
+                cron.into(),
+                author_id.clone().into(),
+                next_run_at.clone().into(),
             ])
+            .returning_all()
+            .to_owned()
+    }
+
+    pub fn get_timed_message(id: i64) -> SelectStatement {
+        Self::select_by_key(id)
+    }
+
+    pub fn get_guild_messages(guild_id: &String) -> SelectStatement {
+        Self::select_all()
+            .and_where(Expr::col(TimedMessages::GuildId).eq(guild_id.clone()))
+            .order_by(TimedMessages::Id, sea_query::Order::Asc)
             .to_owned()
     }
 
-    pub fn update_next_send_time(id: i32, next_send_at: &String) -> UpdateStatement {
+    /// Pause or resume a message, clearing its next run while paused
+    pub fn set_paused(id: i64, paused: bool, next_run_at: Option<String>) -> UpdateStatement {
         Query::update()
             .table(TimedMessages::Table)
-            .values(vec![(TimedMessages::SendAt, next_send_at.clone().into())])
-            .and_where(sea_query::Expr::col(TimedMessages::Id).eq(id))
+            .values(vec![
+                (TimedMessages::Paused, paused.into()),
+                (TimedMessages::NextRunAt, next_run_at.into()),
+            ])
+            .and_where(Expr::col(TimedMessages::Id).eq(id))
+            .returning_all()
             .to_owned()
     }
 
-    pub fn delete_timed_message(id: i32) -> DeleteStatement {
-        Query::delete()
-            .from_table(TimedMessages::Table)
-            .and_where(sea_query::Expr::col(TimedMessages::Id).eq(id))
+    /// Move the next run of a message that is still expected at `current`
+    ///
+    /// Matching on the current run keeps a run that raced with a skip from scheduling twice.
+    pub fn set_next_run(id: i64, current: &String, next_run_at: &String) -> UpdateStatement {
+        Query::update()
+            .table(TimedMessages::Table)
+            .values(vec![(TimedMessages::NextRunAt, next_run_at.clone().into())])
+            .and_where(Expr::col(TimedMessages::Id).eq(id))
+            .and_where(Expr::col(TimedMessages::NextRunAt).eq(current.clone()))
+            .and_where(Expr::col(TimedMessages::Paused).eq(false))
+            .returning_all()
+            .to_owned()
+    }
+
+    /// Record a run that was due at `current`, see [`Self::set_next_run`]
+    pub fn record_run(
+        id: i64,
+        current: &String,
+        ran_at: &String,
+        next_run_at: &String,
+    ) -> UpdateStatement {
+        let mut query = Self::set_next_run(id, current, next_run_at);
+        query.value(TimedMessages::LastRunAt, ran_at.clone());
+        query
+    }
+
+    pub fn delete_timed_message(id: i64) -> DeleteStatement {
+        Self::delete_by_key(id)
+    }
+
+    pub fn delete_guild_message(id: i64, guild_id: &String) -> DeleteStatement {
+        Self::delete_by_key(id)
+            .and_where(Expr::col(TimedMessages::GuildId).eq(guild_id.clone()))
             .to_owned()
     }
 
@@ -1,5 +1,5 @@
-use super::deserialize_bool;
-use sea_query::{DeleteStatement, Iden, InsertStatement, UpdateStatement};
+use super::{SoftDelete, deserialize_bool};
+use sea_query::{DeleteStatement, Expr, Iden, InsertStatement, Query, UpdateStatement};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
@@ -25,7 +25,8 @@ pub struct GuildSchema {
     pub enabled: bool, // Indicates if the guild is enabled (1) or disabled (0)
     pub shard_id: u32,
     pub updated_at: String,
-    pub started_at: String,
+    #[serde(alias = "added_at")]
+    pub started_at: String, // When the bot was added, stored as `added_at`
 }
 
 #[derive(Iden, Debug, Clone, Copy, PartialEq, Eq)]
@@ -42,12 +43,28 @@ pub enum Guild {
     AddedAt,
     #[iden = "updated_at"]
     UpdatedAt,
+    #[iden = "deleted_at"]
+    DeletedAt,
+}
+
+impl Guild {
+    pub fn all_columns() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::Enabled,
+            Self::ShardId,
+            Self::AddedAt,
+            Self::UpdatedAt,
+        ]
+    }
 }
 
 impl GuildSchema {
+    /// Add a guild, or restore it and its configuration when it was soft deleted
     pub fn insert(guild_id: &str, shard_id: u32) -> InsertStatement {
         let on_conflict = sea_query::OnConflict::new()
             .update_columns([Guild::Enabled, Guild::ShardId, Guild::UpdatedAt])
+            .value(Guild::DeletedAt, Expr::val(None::<String>))
             .to_owned();
         sea_query::Query::insert()
             .into_table(Guild::Table)
@@ -95,30 +112,71 @@ impl GuildSchema {
             .to_owned()
     }
 
-    pub fn delete(guild_id: &str) -> DeleteStatement {
-        sea_query::Query::delete()
-            .from_table(Guild::Table)
+    /// Soft delete a guild, its configuration is kept until [`GuildSchema::purge_deleted`]
+    pub fn soft_delete(guild_id: &str) -> UpdateStatement {
+        sea_query::Query::update()
+            .table(Guild::Table)
+            .value(Guild::Enabled, 0)
+            .value(Guild::DeletedAt, Expr::current_timestamp())
             .and_where(sea_query::Expr::col(Guild::Id).eq(guild_id))
-            .returning_all()
+            .and_where(SoftDelete::live(Guild::Table))
+            .to_owned()
+    }
+
+    /// Hard delete the guilds soft deleted more than `retention_days` ago, returning their ids
+    ///
+    /// Everything they own goes with them through `ON DELETE CASCADE`, except `guild_members`
+    /// which has to be emptied first with [`GuildSchema::purge_deleted_members`].
+    pub fn purge_deleted(retention_days: u32) -> DeleteStatement {
+        Query::delete()
+            .from_table(Guild::Table)
+            .and_where(Expr::col(Guild::Id).in_subquery(Self::expired(retention_days)))
+            .returning_col(Guild::Id)
+            .to_owned()
+    }
+
+    pub fn purge_deleted_members(retention_days: u32) -> DeleteStatement {
+        Query::delete()
+            .from_table(Members::Table)
+            .and_where(Expr::col(Members::GuildId).in_subquery(Self::expired(retention_days)))
+            .to_owned()
+    }
+
+    /// Ids of the guilds soft deleted more than `retention_days` ago
+    fn expired(retention_days: u32) -> sea_query::SelectStatement {
+        let cutoff =
+            Expr::cust_with_values("datetime('now', ?)", [format!("-{} days", retention_days)]);
+        Query::select()
+            .column(Guild::Id)
+            .from(Guild::Table)
+            .and_where(Expr::col(Guild::DeletedAt).lt(cutoff))
             .to_owned()
     }
 
     pub fn get_all() -> sea_query::SelectStatement {
-        sea_query::Query::select().from(Guild::Table).to_owned()
+        sea_query::Query::select()
+            .columns(Guild::all_columns())
+            .from(Guild::Table)
+            .and_where(SoftDelete::live(Guild::Table))
+            .to_owned()
     }
 
     pub fn get_by_id(guild_id: &str) -> sea_query::SelectStatement {
         sea_query::Query::select()
+            .columns(Guild::all_columns())
             .from(Guild::Table)
             .and_where(sea_query::Expr::col(Guild::Id).eq(guild_id))
+            .and_where(SoftDelete::live(Guild::Table))
             .to_owned()
     }
 
     pub fn get_by_ids(guilds: &[String]) -> sea_query::SelectStatement {
         let guild_ids: Vec<String> = guilds.iter().cloned().collect();
         sea_query::Query::select()
+            .columns(Guild::all_columns())
             .from(Guild::Table)
             .and_where(sea_query::Expr::col(Guild::Id).is_in(guild_ids))
+            .and_where(SoftDelete::live(Guild::Table))
             .to_owned()
     }
 
@@ -126,6 +184,7 @@ impl GuildSchema {
         sea_query::Query::select()
             .from(Guild::Table)
             .and_where(sea_query::Expr::col(Guild::Id).eq(guild_id))
+            .and_where(SoftDelete::live(Guild::Table))
             .columns(vec![Guild::ShardId])
             .to_owned()
     }
@@ -1,6 +1,8 @@
-use sea_query::{DeleteStatement, Iden, InsertStatement, OnConflict, Query};
+use sea_query::{DeleteStatement, Expr, Iden, InsertStatement, OnConflict, Query, SelectStatement};
 use serde::{Deserialize, Serialize};
 
+use crate::schema::SoftDelete;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PermissionRolesSchema {
     pub guild_id: String,
@@ -65,6 +67,21 @@ impl PermissionRolesSchema {
             .to_owned()
     }
 
+    /// Permissions of the guild granted to any of `role_ids`
+    pub fn granted_to_roles(guild_id: &String, role_ids: &[String]) -> SelectStatement {
+        Query::select()
+            .columns([
+                PermissionRoles::GuildId,
+                PermissionRoles::Permission,
+                PermissionRoles::RoleId,
+            ])
+            .from(PermissionRoles::Table)
+            .and_where(Expr::col(PermissionRoles::GuildId).eq(guild_id.clone()))
+            .and_where(Expr::col(PermissionRoles::RoleId).is_in(role_ids.iter().cloned()))
+            .and_where(SoftDelete::live(PermissionRoles::Table))
+            .to_owned()
+    }
+
     pub fn delete_permission(guild_id: &String, permission: &String) -> DeleteStatement {
         Query::delete()
             .from_table(PermissionRoles::Table)
@@ -100,6 +117,21 @@ impl PermissionUsersSchema {
             .to_owned()
     }
 
+    /// Permissions of the guild granted to the user directly
+    pub fn granted_to_user(guild_id: &String, user_id: &String) -> SelectStatement {
+        Query::select()
+            .columns([
+                PermissionUsers::GuildId,
+                PermissionUsers::Permission,
+                PermissionUsers::UserId,
+            ])
+            .from(PermissionUsers::Table)
+            .and_where(Expr::col(PermissionUsers::GuildId).eq(guild_id.clone()))
+            .and_where(Expr::col(PermissionUsers::UserId).eq(user_id.clone()))
+            .and_where(SoftDelete::live(PermissionUsers::Table))
+            .to_owned()
+    }
+
     pub fn delete_permission(guild_id: &String, permission: &String) -> DeleteStatement {
         Query::delete()
             .from_table(PermissionUsers::Table)
@@ -1,181 +1,263 @@
-use sea_query::{Iden, InsertStatement, Query};
+use schema_derive::Schema;
+use sea_query::{Alias, Cond, DeleteStatement, Expr, InsertStatement, Order, SelectStatement};
+use serde::{Deserialize, Serialize};
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+use super::Guild;
+use crate::schema::SoftDelete;
+use crate::services::streaming::StreamableSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "guild_prefixes",
+    iden = Prefixes,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct PrefixSchema {
     pub guild_id: String,
     pub prefix: String,
 }
 
-#[derive(Iden, Clone, Copy)]
-pub enum Prefixes {
-    #[iden = "guild_prefixes"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "prefix"]
-    Prefix,
-}
-
 impl PrefixSchema {
     pub fn insert(guild_id: &str, prefix: &str) -> InsertStatement {
-        let on_conflict = sea_query::OnConflict::new()
-            .update_columns([Prefixes::Prefix])
-            .to_owned();
-        Query::insert()
-            .into_table(Prefixes::Table)
-            .columns([Prefixes::GuildId, Prefixes::Prefix])
-            .values_panic([guild_id.into(), prefix.into()])
-            .on_conflict(on_conflict)
-            .to_owned()
+        Self {
+            guild_id: guild_id.to_string(),
+            prefix: prefix.to_string(),
+        }
+        .upsert()
     }
 
-    pub fn delete(guild_id: &str) -> sea_query::DeleteStatement {
-        sea_query::Query::delete()
-            .from_table(Prefixes::Table)
-            .and_where(sea_query::Expr::col(Prefixes::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn delete(guild_id: &str) -> DeleteStatement {
+        Self::delete_by_key(guild_id)
     }
 
-    pub fn get(guild_id: &str) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .columns([Prefixes::Prefix])
-            .from(Prefixes::Table)
-            .and_where(sea_query::Expr::col(Prefixes::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn get(guild_id: &str) -> SelectStatement {
+        Self::select_by_key(guild_id)
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "guild_languages",
+    iden = Languages,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct LanguageSchema {
     pub guild_id: String,
     pub language: String,
 }
 
-#[derive(Iden, Clone, Copy)]
-pub enum Languages {
-    #[iden = "guild_languages"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "language"]
-    Language,
-}
-
 impl LanguageSchema {
     pub fn insert(guild_id: &str, language: &str) -> InsertStatement {
-        let on_conflict = sea_query::OnConflict::new()
-            .update_columns([Languages::Language])
-            .to_owned();
-        Query::insert()
-            .into_table(Languages::Table)
-            .columns([Languages::GuildId, Languages::Language])
-            .values_panic([guild_id.into(), language.into()])
-            .on_conflict(on_conflict)
-            .to_owned()
+        Self {
+            guild_id: guild_id.to_string(),
+            language: language.to_string(),
+        }
+        .upsert()
     }
 
-    pub fn delete(guild_id: &str) -> sea_query::DeleteStatement {
-        sea_query::Query::delete()
-            .from_table(Languages::Table)
-            .and_where(sea_query::Expr::col(Languages::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn delete(guild_id: &str) -> DeleteStatement {
+        Self::delete_by_key(guild_id)
     }
 
-    pub fn get(guild_id: &str) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .columns([Languages::Language])
-            .from(Languages::Table)
-            .and_where(sea_query::Expr::col(Languages::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn get(guild_id: &str) -> SelectStatement {
+        Self::select_by_key(guild_id)
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "guild_colours",
+    iden = Colours,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct ColourSchema {
     pub guild_id: String,
     pub colour: String,
 }
 
-#[derive(Iden, Clone, Copy)]
-pub enum Colours {
-    #[iden = "guild_colours"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "colour"]
-    Colour,
-}
-
 impl ColourSchema {
     pub fn insert(guild_id: &str, colour: &str) -> InsertStatement {
-        let on_conflict = sea_query::OnConflict::new()
-            .update_columns([Colours::Colour])
-            .to_owned();
-        Query::insert()
-            .into_table(Colours::Table)
-            .columns([Colours::GuildId, Colours::Colour])
-            .values_panic([guild_id.into(), colour.into()])
-            .on_conflict(on_conflict)
-            .to_owned()
+        Self {
+            guild_id: guild_id.to_string(),
+            colour: colour.to_string(),
+        }
+        .upsert()
     }
 
-    pub fn delete(guild_id: &str) -> sea_query::DeleteStatement {
-        sea_query::Query::delete()
-            .from_table(Colours::Table)
-            .and_where(sea_query::Expr::col(Colours::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn delete(guild_id: &str) -> DeleteStatement {
+        Self::delete_by_key(guild_id)
     }
 
-    pub fn get(guild_id: &str) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .columns([Colours::Colour])
-            .from(Colours::Table)
-            .and_where(sea_query::Expr::col(Colours::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn get(guild_id: &str) -> SelectStatement {
+        Self::select_by_key(guild_id)
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "guild_timezones",
+    iden = Timezones,
+    key(guild_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct TimezoneSchema {
     pub guild_id: String,
     pub timezone: String,
 }
 
-#[derive(Iden, Clone, Copy)]
-pub enum Timezones {
-    #[iden = "guild_timezones"]
-    Table,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "timezone"]
-    Timezone,
-}
-
 impl TimezoneSchema {
     pub fn insert(guild_id: &str, timezone: &str) -> InsertStatement {
-        let on_conflict = sea_query::OnConflict::new()
-            .update_columns([Timezones::Timezone])
-            .to_owned();
-        Query::insert()
-            .into_table(Timezones::Table)
-            .columns([Timezones::GuildId, Timezones::Timezone])
-            .values_panic([guild_id.into(), timezone.into()])
-            .on_conflict(on_conflict)
-            .to_owned()
+        Self {
+            guild_id: guild_id.to_string(),
+            timezone: timezone.to_string(),
+        }
+        .upsert()
     }
 
-    pub fn delete(guild_id: &str) -> sea_query::DeleteStatement {
-        sea_query::Query::delete()
-            .from_table(Timezones::Table)
-            .and_where(sea_query::Expr::col(Timezones::GuildId).eq(guild_id))
-            .to_owned()
+    pub fn delete(guild_id: &str) -> DeleteStatement {
+        Self::delete_by_key(guild_id)
     }
 
-    pub fn get(guild_id: &str) -> sea_query::SelectStatement {
-        sea_query::Query::select()
-            .columns([Timezones::Timezone])
-            .from(Timezones::Table)
-            .and_where(sea_query::Expr::col(Timezones::GuildId).eq(guild_id))
+    pub fn get(guild_id: &str) -> SelectStatement {
+        Self::select_by_key(guild_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBody {
+    pub prefix: Option<String>,
+    pub language: Option<String>,
+    pub timezone: Option<String>,
+    pub colour: Option<String>,
+}
+
+impl SettingsBody {
+    /// Settings of a guild, unset ones are `None`, and no row is returned for an unknown guild
+    pub fn get(guild_id: &str) -> SelectStatement {
+        let guild_col = (Guild::Table, Guild::Id);
+        let prefix_col = (Prefixes::Table, Prefixes::GuildId);
+        let language_col = (Languages::Table, Languages::GuildId);
+        let timezone_col = (Timezones::Table, Timezones::GuildId);
+        let colour_col = (Colours::Table, Colours::GuildId);
+        SelectStatement::new()
+            .columns([(Prefixes::Table, Prefixes::Prefix)])
+            .columns([(Languages::Table, Languages::Language)])
+            .columns([(Timezones::Table, Timezones::Timezone)])
+            .columns([(Colours::Table, Colours::Colour)])
+            .from(Guild::Table)
+            .and_where(Expr::col(guild_col).eq(guild_id))
+            .and_where(SoftDelete::live(Guild::Table))
+            .left_join(
+                Prefixes::Table,
+                (Expr::col(guild_col).equals(prefix_col)).and(SoftDelete::live(Prefixes::Table)),
+            )
+            .left_join(
+                Languages::Table,
+                (Expr::col(guild_col).equals(language_col)).and(SoftDelete::live(Languages::Table)),
+            )
+            .left_join(
+                Timezones::Table,
+                (Expr::col(guild_col).equals(timezone_col)).and(SoftDelete::live(Timezones::Table)),
+            )
+            .left_join(
+                Colours::Table,
+                (Expr::col(guild_col).equals(colour_col)).and(SoftDelete::live(Colours::Table)),
+            )
             .to_owned()
     }
+
+    pub fn set(guild_id: &str, data: &SettingsBody) -> Vec<InsertStatement> {
+        let mut queries = Vec::new();
+        if let Some(prefix) = &data.prefix {
+            queries.push(PrefixSchema::insert(guild_id, prefix));
+        }
+        if let Some(language) = &data.language {
+            queries.push(LanguageSchema::insert(guild_id, language));
+        }
+        if let Some(timezone) = &data.timezone {
+            queries.push(TimezoneSchema::insert(guild_id, timezone));
+        }
+        if let Some(colour) = &data.colour {
+            queries.push(ColourSchema::insert(guild_id, colour));
+        }
+        queries
+    }
+
+    pub fn delete(guild_id: &str, data: &SettingsBody) -> Vec<DeleteStatement> {
+        let mut queries = Vec::new();
+        if data.prefix.is_none() {
+            queries.push(PrefixSchema::delete(guild_id));
+        }
+        if data.language.is_none() {
+            queries.push(LanguageSchema::delete(guild_id));
+        }
+        if data.timezone.is_none() {
+            queries.push(TimezoneSchema::delete(guild_id));
+        }
+        if data.colour.is_none() {
+            queries.push(ColourSchema::delete(guild_id));
+        }
+        queries
+    }
+}
+
+/// The settings of a single guild, as sent by the settings stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildSettingsSchema {
+    pub guild_id: String,
+    #[serde(flatten)]
+    pub settings: SettingsBody,
+}
+
+impl StreamableSchema for GuildSettingsSchema {
+    /// `guild_id`
+    type Cursor = String;
+
+    /// Every guild with at least one setting configured
+    fn batch_after(batch_size: u64, after: Option<&Self::Cursor>) -> sea_query::SelectStatement {
+        let guild_col = (Guild::Table, Guild::Id);
+        let prefix_col = (Prefixes::Table, Prefixes::GuildId);
+        let language_col = (Languages::Table, Languages::GuildId);
+        let timezone_col = (Timezones::Table, Timezones::GuildId);
+        let colour_col = (Colours::Table, Colours::GuildId);
+
+        let mut query = SelectStatement::new();
+        query
+            .expr_as(Expr::col(guild_col), Alias::new("guild_id"))
+            .columns([(Prefixes::Table, Prefixes::Prefix)])
+            .columns([(Languages::Table, Languages::Language)])
+            .columns([(Timezones::Table, Timezones::Timezone)])
+            .columns([(Colours::Table, Colours::Colour)])
+            .from(Guild::Table)
+            .left_join(Prefixes::Table, Expr::col(guild_col).equals(prefix_col))
+            .left_join(Languages::Table, Expr::col(guild_col).equals(language_col))
+            .left_join(Timezones::Table, Expr::col(guild_col).equals(timezone_col))
+            .left_join(Colours::Table, Expr::col(guild_col).equals(colour_col))
+            .cond_where(
+                Cond::any()
+                    .add(Expr::col(prefix_col).is_not_null())
+                    .add(Expr::col(language_col).is_not_null())
+                    .add(Expr::col(timezone_col).is_not_null())
+                    .add(Expr::col(colour_col).is_not_null()),
+            )
+            // Settings rows are soft deleted along with their guild
+            .and_where(SoftDelete::live(Guild::Table))
+            .order_by(guild_col, Order::Asc)
+            .limit(batch_size);
+        if let Some(guild_id) = after {
+            query.and_where(Expr::col(guild_col).gt(guild_id));
+        }
+        query.to_owned()
+    }
+
+    fn cursor(&self) -> Self::Cursor {
+        self.guild_id.clone()
+    }
 }
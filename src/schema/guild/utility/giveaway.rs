@@ -1,51 +1,36 @@
 use super::super::deserialize_bool;
-use sea_query::{DeleteStatement, Iden, InsertStatement, Query, UpdateStatement};
+use chrono::{DateTime, Utc};
+use schema_derive::Schema;
+use sea_query::{DeleteStatement, InsertStatement, Query, SelectStatement, UpdateStatement};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "giveaways",
+    iden = Giveaways,
+    key(giveaway_id),
+    soft_delete,
+    ddl = "migrations"
+)]
 pub struct GiveawaysSchema {
     pub giveaway_id: String,
     pub guild_id: String,
     pub channel_id: String,
     pub message_id: String,
     pub prize: String,
-    pub end_time: String, // TIMESTAMP
+    pub end_time: String, // TIMESTAMP, RFC 3339
     pub winners_count: i32,
+    #[schema(column = "user_id")]
+    #[serde(alias = "user_id")]
     pub host_id: String,
     #[serde(deserialize_with = "deserialize_bool")]
     pub ended: bool,
+    #[schema(generated)]
     pub created_at: String,
+    #[schema(generated)]
     pub updated_at: String,
 }
 
-#[derive(Iden)]
-pub enum Giveaways {
-    #[iden = "giveaways"]
-    Table,
-    #[iden = "giveaway_id"]
-    GiveawayId,
-    #[iden = "guild_id"]
-    GuildId,
-    #[iden = "channel_id"]
-    ChannelId,
-    #[iden = "message_id"]
-    MessageId,
-    #[iden = "prize"]
-    Prize,
-    #[iden = "end_time"]
-    EndTime,
-    #[iden = "winners_count"]
-    WinnersCount,
-    #[iden = "host_id"]
-    HostId,
-    #[iden = "ended"]
-    Ended,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
-}
-
 impl GiveawaysSchema {
     pub fn insert(
         giveaway_id: &String,
@@ -53,12 +38,10 @@ impl GiveawaysSchema {
         channel_id: &String,
         message_id: &String,
         prize: &String,
-        end_time: &String,
+        end_time: &DateTime<Utc>,
         winners_count: i32,
         host_id: &String,
     ) -> InsertStatement {
-        let current_time = chrono::Utc::now().to_rfc3339();
-
         Query::insert()
             .into_table(Giveaways::Table)
             .columns(vec![
@@ -69,10 +52,8 @@ impl GiveawaysSchema {
                 Giveaways::Prize,
                 Giveaways::EndTime,
                 Giveaways::WinnersCount,
-                Giveaways::HostId,
+                Giveaways::UserId,
                 Giveaways::Ended,
-                Giveaways::CreatedAt,
-                Giveaways::UpdatedAt,
             ])
             .values_panic(vec![
                 giveaway_id.clone().into(),
@@ -80,16 +61,29 @@ impl GiveawaysSchema {
                 channel_id.clone().into(),
                 message_id.clone().into(),
                 prize.clone().into(),
-                end_time.clone().into(),
+                end_time.to_rfc3339().into(),
                 winners_count.into(),
                 host_id.clone().into(),
                 false.into(),
-                current_time.clone().into(),
-                current_time.into(),
             ])
+            .returning_all()
+            .to_owned()
+    }
+
+    pub fn get_giveaway(giveaway_id: &String) -> SelectStatement {
+        Self::select_by_key(giveaway_id.clone())
+    }
+
+    /// Giveaways of a guild, running ones first, then by end time
+    pub fn get_guild_giveaways(guild_id: &String) -> SelectStatement {
+        Self::select_all()
+            .and_where(sea_query::Expr::col(Giveaways::GuildId).eq(guild_id.clone()))
+            .order_by(Giveaways::Ended, sea_query::Order::Asc)
+            .order_by(Giveaways::EndTime, sea_query::Order::Asc)
             .to_owned()
     }
 
+    /// Only updates giveaways still running, so a giveaway ends at most once
     pub fn mark_ended(giveaway_id: &String) -> UpdateStatement {
         Query::update()
             .table(Giveaways::Table)
@@ -98,13 +92,12 @@ impl GiveawaysSchema {
                 (Giveaways::UpdatedAt, chrono::Utc::now().to_rfc3339().into()),
             ])
             .and_where(sea_query::Expr::col(Giveaways::GiveawayId).eq(giveaway_id.clone()))
+            .and_where(sea_query::Expr::col(Giveaways::Ended).eq(false))
+            .returning_all()
             .to_owned()
     }
 
     pub fn delete_giveaway(giveaway_id: &String) -> DeleteStatement {
-        Query::delete()
-            .from_table(Giveaways::Table)
-            .and_where(sea_query::Expr::col(Giveaways::GiveawayId).eq(giveaway_id.clone()))
-            .to_owned()
+        Self::delete_by_key(giveaway_id.clone())
     }
 }
@@ -0,0 +1,63 @@
+use sea_query::{Iden, InsertStatement, Order, SelectStatement};
+use serde::{Deserialize, Serialize};
+
+/// Version of the D1 schema the `Iden` enums in this module are written against.
+/// Bump it together with a new file in `migrations/` whenever a table or column changes.
+pub const SCHEMA_VERSION: u32 = 6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMigrationSchema {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: Option<String>,
+}
+
+#[derive(Iden, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaMigrations {
+    #[iden = "schema_migrations"]
+    Table,
+    #[iden = "version"]
+    Version,
+    #[iden = "name"]
+    Name,
+    #[iden = "checksum"]
+    Checksum,
+    #[iden = "applied_at"]
+    AppliedAt,
+}
+
+impl SchemaMigrationSchema {
+    /// Bootstrap statement for the bookkeeping table, which can't be created by a migration itself
+    pub const CREATE_TABLE: &'static str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INTEGER PRIMARY KEY, -- Migration version, applied in ascending order
+    name TEXT NOT NULL, -- File name of the migration
+    checksum TEXT NOT NULL, -- Checksum of the migration file when it was applied
+    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+)";
+
+    pub fn insert(version: u32, name: &str, checksum: &str) -> InsertStatement {
+        sea_query::Query::insert()
+            .into_table(SchemaMigrations::Table)
+            .columns([
+                SchemaMigrations::Version,
+                SchemaMigrations::Name,
+                SchemaMigrations::Checksum,
+            ])
+            .values_panic([version.into(), name.into(), checksum.into()])
+            .to_owned()
+    }
+
+    pub fn get_all() -> SelectStatement {
+        sea_query::Query::select()
+            .from(SchemaMigrations::Table)
+            .columns([
+                SchemaMigrations::Version,
+                SchemaMigrations::Name,
+                SchemaMigrations::Checksum,
+                SchemaMigrations::AppliedAt,
+            ])
+            .order_by(SchemaMigrations::Version, Order::Asc)
+            .to_owned()
+    }
+}
@@ -1,11 +1,32 @@
 mod afk;
 pub mod guild;
+mod migration;
 mod shard;
 pub mod user;
 
 pub use afk::*;
+pub use migration::*;
 pub use shard::*;
 
+use sea_query::{Expr, Iden, IntoIden, SimpleExpr};
+
+/// `deleted_at` column of `guilds` and every guild-owned table
+///
+/// Removing a guild only sets `guilds.deleted_at`, triggers mirror it on the rows the guild owns
+/// (see `migrations/0002_soft_delete.sql`), and they are purged once the retention window has passed.
+#[derive(Iden, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftDelete {
+    #[iden = "deleted_at"]
+    DeletedAt,
+}
+
+impl SoftDelete {
+    /// Only rows of `table` that aren't soft deleted
+    pub fn live(table: impl IntoIden) -> SimpleExpr {
+        Expr::col((table.into_iden(), SoftDelete::DeletedAt)).is_null()
+    }
+}
+
 pub fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -1,7 +1,10 @@
-use chrono::Datelike;
-use sea_query::{Expr, Iden, InsertStatement, OnConflict, Query, SelectStatement};
+use chrono::{Datelike, NaiveDate};
+use sea_query::{
+    Expr, Iden, InsertStatement, OnConflict, Order, Query, SelectStatement, SimpleExpr,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::schema::{SoftDelete, guild};
 use crate::services::streaming::StreamableSchema;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -89,6 +92,44 @@ impl BirthdaySchema {
             .to_owned()
     }
 
+    /// Birthdays of the guild's members celebrated on `date`, see [`Self::celebrated_on`]
+    pub fn get_guild_birthdays_on(guild_id: &str, date: NaiveDate) -> SelectStatement {
+        let user_id_col = (guild::Members::Table, guild::Members::UserId);
+        let guild_id_col = (guild::Members::Table, guild::Members::GuildId);
+
+        Query::select()
+            .columns(
+                Birthdays::all_columns()
+                    .into_iter()
+                    .map(|c| (Birthdays::Table, c)),
+            )
+            .from(Birthdays::Table)
+            .inner_join(
+                guild::Members::Table,
+                Expr::col((Birthdays::Table, Birthdays::UserId)).equals(user_id_col),
+            )
+            .and_where(Expr::col(guild_id_col).eq(guild_id))
+            .and_where(SoftDelete::live(guild::Members::Table))
+            .and_where(Self::celebrated_on(date))
+            .order_by((Birthdays::Table, Birthdays::UserId), Order::Asc)
+            .to_owned()
+    }
+
+    /// Birthdays falling on `date`, Feb 29 birthdays are celebrated on Feb 28 outside leap years
+    pub fn celebrated_on(date: NaiveDate) -> SimpleExpr {
+        let on = |month: u32, day: u32| {
+            Expr::col((Birthdays::Table, Birthdays::Month))
+                .eq(month)
+                .and(Expr::col((Birthdays::Table, Birthdays::Day)).eq(day))
+        };
+        let celebrated = on(date.month(), date.day());
+        let leap_year = NaiveDate::from_ymd_opt(date.year(), 2, 29).is_some();
+        if !leap_year && date.month() == 2 && date.day() == 28 {
+            return celebrated.or(on(2, 29));
+        }
+        celebrated
+    }
+
     pub fn delete_birthday(user_id: &str) -> sea_query::DeleteStatement {
         Query::delete()
             .from_table(Birthdays::Table)
@@ -99,12 +140,15 @@ impl BirthdaySchema {
 }
 
 impl StreamableSchema for BirthdaySchema {
-    fn all_by_batch(batch_size: u64, offset: u64) -> sea_query::SelectStatement {
-        let now = chrono::Utc::now();
-        let current_month = now.month();
-        let current_day = now.day();
+    /// `user_id`
+    type Cursor = String;
 
-        Query::select()
+    /// Only today's birthdays are streamed
+    fn batch_after(batch_size: u64, after: Option<&Self::Cursor>) -> sea_query::SelectStatement {
+        let today = chrono::Utc::now().date_naive();
+
+        let mut query = Query::select();
+        query
             .columns(vec![
                 Birthdays::UserId,
                 Birthdays::Day,
@@ -114,13 +158,16 @@ impl StreamableSchema for BirthdaySchema {
                 Birthdays::UpdatedAt,
             ])
             .from(Birthdays::Table)
-            .and_where(
-                Expr::col(Birthdays::Month)
-                    .eq(current_month)
-                    .and(Expr::col(Birthdays::Day).eq(current_day)),
-            )
-            .limit(batch_size)
-            .offset(offset)
-            .to_owned()
+            .and_where(Self::celebrated_on(today))
+            .order_by(Birthdays::UserId, Order::Asc)
+            .limit(batch_size);
+        if let Some(user_id) = after {
+            query.and_where(Expr::col(Birthdays::UserId).gt(user_id));
+        }
+        query.to_owned()
+    }
+
+    fn cursor(&self) -> Self::Cursor {
+        self.user_id.clone()
     }
 }
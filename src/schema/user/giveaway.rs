@@ -1,7 +1,10 @@
-use super::super::deserialize_bool;
-use sea_query::{DeleteStatement, Iden, InsertStatement, Query, UpdateStatement};
+use sea_query::{
+    Alias, DeleteStatement, Expr, Func, Iden, InsertStatement, Query, SelectStatement, SimpleExpr,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::schema::guild::Giveaways;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GiveawayEntriesSchema {
     pub giveaway_id: String,
@@ -22,30 +25,202 @@ pub enum GiveawayEntries {
 }
 
 impl GiveawayEntriesSchema {
-    pub fn insert(giveaway_id: &String, user_id: &String) -> InsertStatement {
+    pub fn insert(giveaway_id: &str, user_id: &str) -> InsertStatement {
         Query::insert()
             .into_table(GiveawayEntries::Table)
-            .columns(vec![
+            .columns(vec![GiveawayEntries::GiveawayId, GiveawayEntries::UserId])
+            .values_panic(vec![giveaway_id.into(), user_id.into()])
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    GiveawayEntries::GiveawayId,
+                    GiveawayEntries::UserId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .to_owned()
+    }
+
+    /// Entrants who haven't won the giveaway yet
+    pub fn get_eligible(giveaway_id: &str) -> SelectStatement {
+        let winners = Query::select()
+            .column(GiveawayWinners::UserId)
+            .from(GiveawayWinners::Table)
+            .and_where(Expr::col(GiveawayWinners::GiveawayId).eq(giveaway_id))
+            .to_owned();
+
+        Query::select()
+            .columns([
                 GiveawayEntries::GiveawayId,
                 GiveawayEntries::UserId,
                 GiveawayEntries::CreatedAt,
             ])
-            .values_panic(vec![giveaway_id.clone().into(), user_id.clone().into()])
+            .from(GiveawayEntries::Table)
+            .and_where(Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id))
+            .and_where(Expr::col(GiveawayEntries::UserId).not_in_subquery(winners))
+            .to_owned()
+    }
+
+    /// Number of entrants, as a single [`GiveawayEntryCount`] row
+    pub fn count_entries(giveaway_id: &str) -> SelectStatement {
+        Query::select()
+            .expr_as(
+                Func::count(Expr::col(GiveawayEntries::UserId)),
+                Alias::new("entries"),
+            )
+            .from(GiveawayEntries::Table)
+            .and_where(Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id))
             .to_owned()
     }
 
-    pub fn delete_entry(giveaway_id: &String, user_id: &String) -> DeleteStatement {
+    pub fn delete_entry(giveaway_id: &str, user_id: &str) -> DeleteStatement {
         Query::delete()
             .from_table(GiveawayEntries::Table)
-            .and_where(sea_query::Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id.clone()))
-            .and_where(sea_query::Expr::col(GiveawayEntries::UserId).eq(user_id.clone()))
+            .and_where(sea_query::Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id))
+            .and_where(sea_query::Expr::col(GiveawayEntries::UserId).eq(user_id))
+            .returning_all()
             .to_owned()
     }
 
-    pub fn delete_all_entries(giveaway_id: &String) -> DeleteStatement {
+    pub fn delete_all_entries(giveaway_id: &str) -> DeleteStatement {
         Query::delete()
             .from_table(GiveawayEntries::Table)
-            .and_where(sea_query::Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id.clone()))
+            .and_where(sea_query::Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id))
+            .to_owned()
+    }
+}
+
+/// Row of [`GiveawayEntriesSchema::count_entries`]
+#[derive(Debug, Deserialize)]
+pub struct GiveawayEntryCount {
+    pub entries: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GiveawayWinnersSchema {
+    pub giveaway_id: String,
+    pub user_id: String,
+    pub round: i64, // 0 for the original draw, incremented on every reroll
+    pub created_at: String,
+}
+
+#[derive(Iden)]
+pub enum GiveawayWinners {
+    #[iden = "giveaway_winners"]
+    Table,
+    #[iden = "giveaway_id"]
+    GiveawayId,
+    #[iden = "user_id"]
+    UserId,
+    #[iden = "round"]
+    Round,
+    #[iden = "created_at"]
+    CreatedAt,
+}
+
+/// Row of [`GiveawayWinnersSchema::next_round`]
+#[derive(Debug, Deserialize)]
+pub struct GiveawayRound {
+    pub round: i64,
+}
+
+impl GiveawayWinnersSchema {
+    /// Record `user_ids` as the first round of winners, as long as the giveaway is still running
+    ///
+    /// Queued before [`GiveawaysSchema::mark_ended`] in the same batch, so when two calls race to
+    /// end a giveaway only the one that ends it stores winners.
+    ///
+    /// [`GiveawaysSchema::mark_ended`]: crate::schema::guild::GiveawaysSchema::mark_ended
+    pub fn insert_first_round(giveaway_id: &str, user_ids: &[String]) -> InsertStatement {
+        let running = Query::select()
+            .expr(Expr::val(1))
+            .from(Giveaways::Table)
+            .and_where(Expr::col((Giveaways::Table, Giveaways::GiveawayId)).eq(giveaway_id))
+            .and_where(Expr::col((Giveaways::Table, Giveaways::Ended)).eq(false))
+            .to_owned();
+        let entrants = Self::drawn_entrants(giveaway_id, user_ids, Expr::val(0).into())
+            .and_where(Expr::exists(running))
+            .to_owned();
+        Self::insert_drawn(entrants)
+    }
+
+    /// Every winner of a giveaway, in draw order
+    pub fn get_winners(giveaway_id: &str) -> SelectStatement {
+        Query::select()
+            .columns([
+                GiveawayWinners::GiveawayId,
+                GiveawayWinners::UserId,
+                GiveawayWinners::Round,
+                GiveawayWinners::CreatedAt,
+            ])
+            .from(GiveawayWinners::Table)
+            .and_where(Expr::col(GiveawayWinners::GiveawayId).eq(giveaway_id))
+            .order_by(GiveawayWinners::Round, sea_query::Order::Asc)
+            .order_by(GiveawayWinners::CreatedAt, sea_query::Order::Asc)
+            .to_owned()
+    }
+
+    /// Record `user_ids` as the winners of a new round of a giveaway, returning the inserted rows
+    ///
+    /// The round is computed by the insert itself, so concurrent rerolls can't share a round,
+    /// and entrants who won in the meantime are skipped instead of failing the whole draw.
+    pub fn insert_next_round(giveaway_id: &str, user_ids: &[String]) -> InsertStatement {
+        let winners = Query::select()
+            .column(GiveawayWinners::UserId)
+            .from(GiveawayWinners::Table)
+            .and_where(Expr::col(GiveawayWinners::GiveawayId).eq(giveaway_id))
+            .to_owned();
+        let round = SimpleExpr::SubQuery(
+            None,
+            Box::new(Self::next_round(giveaway_id).into_sub_query_statement()),
+        );
+        let entrants = Self::drawn_entrants(giveaway_id, user_ids, round)
+            .and_where(Expr::col(GiveawayEntries::UserId).not_in_subquery(winners))
+            .to_owned();
+        Self::insert_drawn(entrants)
+    }
+
+    /// `giveaway_id, user_id, round` of the drawn entrants, for [`Self::insert_drawn`]
+    fn drawn_entrants(
+        giveaway_id: &str,
+        user_ids: &[String],
+        round: SimpleExpr,
+    ) -> SelectStatement {
+        Query::select()
+            .columns([GiveawayEntries::GiveawayId, GiveawayEntries::UserId])
+            .expr(round)
+            .from(GiveawayEntries::Table)
+            .and_where(Expr::col(GiveawayEntries::GiveawayId).eq(giveaway_id))
+            .and_where(Expr::col(GiveawayEntries::UserId).is_in(user_ids.iter().cloned()))
+            .to_owned()
+    }
+
+    fn insert_drawn(entrants: SelectStatement) -> InsertStatement {
+        Query::insert()
+            .into_table(GiveawayWinners::Table)
+            .columns([
+                GiveawayWinners::GiveawayId,
+                GiveawayWinners::UserId,
+                GiveawayWinners::Round,
+            ])
+            .select_from(entrants)
+            .expect("The select has one expression per inserted column")
+            .returning_all()
+            .to_owned()
+    }
+
+    /// The round a new draw of this giveaway belongs to, as a single [`GiveawayRound`] row
+    pub fn next_round(giveaway_id: &str) -> SelectStatement {
+        let last_round = Func::coalesce([
+            Func::max(Expr::col((GiveawayWinners::Table, GiveawayWinners::Round))).into(),
+            Expr::val(-1).into(),
+        ]);
+        Query::select()
+            .expr_as(Expr::expr(last_round).add(1), Alias::new("round"))
+            .from(GiveawayWinners::Table)
+            .and_where(
+                Expr::col((GiveawayWinners::Table, GiveawayWinners::GiveawayId)).eq(giveaway_id),
+            )
             .to_owned()
     }
 }
@@ -1,27 +1,18 @@
-use sea_query::{Iden, InsertStatement, OnConflict, Query, UpdateStatement};
+use schema_derive::Schema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "user_profiles",
+    iden = UserLevelProfiles,
+    key(user_id),
+    ddl = "migrations"
+)]
 pub struct UserLevelProfilesSchema {
     pub user_id: String,
     pub avatar_url: Option<String>,
     pub background_url: Option<String>,
+    #[schema(generated)]
     pub created_at: String,
     pub updated_at: String,
 }
-
-#[derive(Iden)]
-pub enum UserLevelProfiles {
-    #[iden = "user_level_profiles"]
-    Table,
-    #[iden = "user_id"]
-    UserId,
-    #[iden = "avatar_url"]
-    AvatarUrl,
-    #[iden = "background_url"]
-    BackgroundUrl,
-    #[iden = "created_at"]
-    CreatedAt,
-    #[iden = "updated_at"]
-    UpdatedAt,
-}
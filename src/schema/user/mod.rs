@@ -5,12 +5,14 @@ mod afk;
 mod birthday;
 mod giveaway;
 mod leveling;
+mod session;
 mod voice;
 
 pub use afk::*;
 pub use birthday::*;
 pub use giveaway::*;
 pub use leveling::*;
+pub use session::*;
 pub use voice::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -0,0 +1,108 @@
+use schema_derive::Schema;
+use sea_query::{DeleteStatement, Expr, Order, Query, SelectStatement, UpdateStatement};
+use serde::{Deserialize, Serialize};
+
+/// A dashboard login, the session cookie holds the token this row is keyed by the hash of
+///
+/// Timestamps written by the API are RFC 3339 in UTC, so they compare as strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[schema(
+    table = "user_sessions",
+    iden = UserSessions,
+    key(id),
+    ddl = "migrations"
+)]
+pub struct UserSessionSchema {
+    pub id: String, // SHA-256 of the cookie token, hex encoded
+    pub user_id: String,
+    pub access_token: String,  // Encrypted, see services::session
+    pub refresh_token: String, // Encrypted, see services::session
+    pub access_expires_at: String,
+    pub expires_at: String,
+    pub user_agent: Option<String>,
+    pub last_used_at: String,
+    #[schema(generated)]
+    pub created_at: String,
+}
+
+impl UserSessionSchema {
+    /// A session that hasn't ended at `now`
+    pub fn get_session(id: &str, now: &str) -> SelectStatement {
+        Self::select_by_key(id)
+            .and_where(Expr::col(UserSessions::ExpiresAt).gt(now))
+            .to_owned()
+    }
+
+    /// Sessions of a user that haven't ended at `now`, most recently used first
+    pub fn get_user_sessions(user_id: &str, now: &str) -> SelectStatement {
+        Self::select_all()
+            .and_where(Expr::col(UserSessions::UserId).eq(user_id))
+            .and_where(Expr::col(UserSessions::ExpiresAt).gt(now))
+            .order_by(UserSessions::LastUsedAt, Order::Desc)
+            .to_owned()
+    }
+
+    /// Store the tokens Discord handed out when the access token of `previous` was refreshed
+    ///
+    /// Only applies while the row still holds the tokens of `previous`, returning it when it did,
+    /// so of two concurrent refreshes only the first one is kept.
+    pub fn update_tokens(
+        previous: &UserSessionSchema,
+        access_token: String,
+        refresh_token: String,
+        access_expires_at: String,
+        now: &str,
+    ) -> UpdateStatement {
+        Query::update()
+            .table(UserSessions::Table)
+            .values([
+                (UserSessions::AccessToken, access_token.into()),
+                (UserSessions::RefreshToken, refresh_token.into()),
+                (UserSessions::AccessExpiresAt, access_expires_at.into()),
+                (UserSessions::LastUsedAt, now.into()),
+            ])
+            .and_where(Expr::col(UserSessions::Id).eq(previous.id.as_str()))
+            .and_where(Expr::col(UserSessions::RefreshToken).eq(previous.refresh_token.as_str()))
+            .and_where(
+                Expr::col(UserSessions::AccessExpiresAt).eq(previous.access_expires_at.as_str()),
+            )
+            .returning_all()
+            .to_owned()
+    }
+
+    pub fn set_last_used_at(id: &str, now: &str) -> UpdateStatement {
+        Query::update()
+            .table(UserSessions::Table)
+            .value(UserSessions::LastUsedAt, now)
+            .and_where(Expr::col(UserSessions::Id).eq(id))
+            .to_owned()
+    }
+
+    /// Delete a session, returning it so its tokens can be revoked
+    pub fn delete_session(id: &str) -> DeleteStatement {
+        Self::delete_by_key(id)
+    }
+
+    /// Delete a session whose refresh failed, unless it was refreshed in the meantime
+    pub fn delete_stale_session(previous: &UserSessionSchema) -> DeleteStatement {
+        Self::delete_by_key(previous.id.as_str())
+            .and_where(Expr::col(UserSessions::RefreshToken).eq(previous.refresh_token.as_str()))
+            .to_owned()
+    }
+
+    /// Delete a session of `user_id`, returning it so its tokens can be revoked
+    pub fn delete_user_session(id: &str, user_id: &str) -> DeleteStatement {
+        Self::delete_by_key(id)
+            .and_where(Expr::col(UserSessions::UserId).eq(user_id))
+            .to_owned()
+    }
+
+    /// Delete every session that ended before `now`
+    pub fn purge_expired(now: &str) -> DeleteStatement {
+        Query::delete()
+            .from_table(UserSessions::Table)
+            .and_where(Expr::col(UserSessions::ExpiresAt).lte(now))
+            .returning_all()
+            .to_owned()
+    }
+}
@@ -4,7 +4,10 @@ use serde::{Deserialize, Serialize};
 use time::Duration;
 use worker::{console_error, Result, Url};
 
-use crate::{services::cookie::CookieJar, DISCORD_API_BASE_URL};
+use crate::{
+    services::{cookie::CookieJar, session::SESSION_COOKIE},
+    DISCORD_API_BASE_URL,
+};
 
 pub enum DiscordOAuth2Scope {
     Identify,
@@ -122,8 +125,14 @@ impl DiscordOAuthAccessToken {
     pub fn refresh_token(&self) -> &str {
         &self.refresh_token
     }
+
+    /// Seconds until the access token expires
+    pub fn expires_in(&self) -> i64 {
+        self.expires_in
+    }
 }
 
+/// Cookies that held the raw Discord tokens before sessions, only kept to clear them
 pub enum DiscordCookie {
     AccessToken,
     RefreshToken,
@@ -147,6 +156,22 @@ pub enum DiscordOAuthGrantType {
     RefreshToken,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum DiscordTokenType {
+    #[serde(rename = "access_token")]
+    AccessToken,
+    #[serde(rename = "refresh_token")]
+    RefreshToken,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordRevokeBody {
+    client_id: String,
+    client_secret: String,
+    token: String,
+    token_type_hint: DiscordTokenType,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 
 pub struct DiscordAccessCodeBody {
@@ -300,55 +325,51 @@ impl DiscordAPIClient {
         Ok(token)
     }
 
-    pub fn set_cookies(tokens: DiscordOAuthAccessToken) -> [Cookie<'static>; 2] {
-        let access_cookie = Cookie::build((
-            DiscordCookie::AccessToken.to_string(),
-            tokens.access_token.clone(),
-        ))
-        .path("/")
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::None)
-        .max_age(cookie::time::Duration::seconds(tokens.expires_in))
-        .build();
-
-        let refresh_cookie = Cookie::build((
-            DiscordCookie::RefreshToken.to_string(),
-            tokens.refresh_token.clone(),
-        ))
-        .path("/")
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::None)
-        .build();
-
-        [access_cookie, refresh_cookie]
-    }
-}
+    /// Revoke a token at Discord, revoking either token of a grant revokes both
+    pub async fn revoke_token(&self, token: &str, token_type: DiscordTokenType) -> Result<()> {
+        let url = format!("{}/oauth2/token/revoke", DISCORD_API_BASE_URL);
+        let params = DiscordRevokeBody {
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            token: token.to_string(),
+            token_type_hint: token_type,
+        };
+
+        let response = match self.client.post(&url).form(&params).send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                console_error!("Error sending request to Discord API: {}", e);
+                return Err(worker::Error::RustError(
+                    "Failed to send request to Discord API".into(),
+                ));
+            }
+        };
 
-pub fn remove_error_cookies(jar: &CookieJar) -> ((CookieJar, CookieJar)) {
-    let discord_token = Cookie::build((DiscordCookie::AccessToken.to_string(), ""))
-        .path("/")
-        .http_only(true)
-        .max_age(Duration::ZERO)
-        .build();
-    let discord_refresh_token = Cookie::build((DiscordCookie::RefreshToken.to_string(), ""))
-        .path("/")
-        .http_only(true)
-        .max_age(Duration::ZERO)
-        .build();
-    (
-        jar.clone().add(discord_token),
-        jar.clone().add(discord_refresh_token),
-    )
+        if !response.status().is_success() {
+            return Err(worker::Error::RustError(format!(
+                "Discord refused to revoke the token: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
 }
 
-pub fn add_success_cookies(
-    jar: &CookieJar,
-    cookies: [Cookie<'static>; 2],
-) -> (CookieJar, CookieJar) {
-    (
-        jar.clone().add(cookies[0].clone()),
-        jar.clone().add(cookies[1].clone()),
-    )
+/// Clear the session cookie, along with the raw token cookies of logins made before sessions
+pub fn remove_session_cookies(jar: &CookieJar) -> CookieJar {
+    let names = [
+        SESSION_COOKIE.to_string(),
+        DiscordCookie::AccessToken.to_string(),
+        DiscordCookie::RefreshToken.to_string(),
+    ];
+    names.into_iter().fold(jar.clone(), |jar, name| {
+        let cookie = Cookie::build((name, ""))
+            .path("/")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::None)
+            .max_age(Duration::ZERO)
+            .build();
+        jar.add(cookie)
+    })
 }
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    schema::{guild::BirthdayConfigsSchema, user::BirthdaySchema},
+    state::database::{Database, DatabaseExt, DbResult},
+};
+
+/// Data of a `BirthdayAnnouncement` event, one per guild and day
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BirthdayAnnouncementPayload {
+    pub guild_id: String,
+    /// Channel from the guild's birthday config, `None` leaves the choice to the bot
+    pub channel_id: Option<String>,
+    /// Local date of the guild the birthdays are celebrated on, YYYY-MM-DD
+    pub date: String,
+    pub birthdays: Vec<BirthdayMessage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BirthdayMessage {
+    pub user_id: String,
+    /// Age turned today, only known when the member shared their birth year
+    pub age: Option<i32>,
+    /// The guild's template, rendered for this member
+    pub message: String,
+}
+
+/// Fill in the placeholders of a `birthday_configs.message` template
+///
+/// `{user}` is the member's mention, `{user_id}` their id and `{age}` the age turned today,
+/// left empty when the birth year is unknown.
+pub fn render_message(template: &str, user_id: &str, age: Option<i32>) -> String {
+    template
+        .replace("{user}", &format!("<@{}>", user_id))
+        .replace("{user_id}", user_id)
+        .replace("{age}", &age.map(|age| age.to_string()).unwrap_or_default())
+}
+
+/// Start of the first local day after `after` in `timezone`
+///
+/// A few zones skip midnight on DST changes, their day starts at the first valid time instead.
+pub fn next_midnight(after: DateTime<Utc>, timezone: Tz) -> DateTime<Utc> {
+    let tomorrow = after.with_timezone(&timezone).date_naive() + Days::new(1);
+    (0..24)
+        .filter_map(|hour| NaiveTime::from_hms_opt(hour, 0, 0))
+        .find_map(|time| {
+            timezone
+                .from_local_datetime(&tomorrow.and_time(time))
+                .earliest()
+        })
+        .map_or(after + chrono::Duration::days(1), |time| {
+            time.with_timezone(&Utc)
+        })
+}
+
+/// Claim `date` for the guild and collect the birthdays to announce on it
+///
+/// Returns `None` when the day was already announced, so running it twice is harmless.
+pub async fn announce(
+    database: &Database,
+    config: &BirthdayConfigsSchema,
+    date: NaiveDate,
+) -> DbResult<Option<BirthdayAnnouncementPayload>> {
+    let guild_id = &config.guild_id;
+    let query = BirthdaySchema::get_guild_birthdays_on(guild_id, date);
+    let birthdays: Vec<BirthdaySchema> = database.execute(query).await?;
+
+    let day = date.format("%Y-%m-%d").to_string();
+    let claimed: Vec<BirthdayConfigsSchema> = database
+        .execute(BirthdayConfigsSchema::mark_announced(guild_id, &day))
+        .await?;
+    if claimed.is_empty() {
+        return Ok(None);
+    }
+
+    let birthdays: Vec<BirthdayMessage> = (birthdays.into_iter())
+        .map(|birthday| {
+            let age = (birthday.year).map(|year| date.year() - year as i32);
+            BirthdayMessage {
+                message: render_message(&config.message, &birthday.user_id, age),
+                user_id: birthday.user_id,
+                age,
+            }
+        })
+        .collect();
+    info!(
+        "{} birthday(s) to announce in guild {} on {}",
+        birthdays.len(),
+        guild_id,
+        day
+    );
+
+    Ok(Some(BirthdayAnnouncementPayload {
+        guild_id: guild_id.clone(),
+        channel_id: config.channel_id.clone(),
+        date: day,
+        birthdays,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        assert_eq!(
+            render_message("Happy {age}th {user} ({user_id})", "10", Some(30)),
+            "Happy 30th <@10> (10)"
+        );
+    }
+
+    #[test]
+    fn unknown_age_renders_empty() {
+        assert_eq!(
+            render_message("{user} turns {age} today", "10", None),
+            "<@10> turns  today"
+        );
+        assert_eq!(
+            render_message("No placeholders", "10", None),
+            "No placeholders"
+        );
+    }
+
+    #[test]
+    fn next_midnight_is_local() {
+        assert_eq!(
+            next_midnight(utc("2025-06-01T12:00:00Z"), Tz::UTC),
+            utc("2025-06-02T00:00:00Z")
+        );
+        // Already the 2nd in Tokyo
+        assert_eq!(
+            next_midnight(utc("2025-06-01T20:00:00Z"), Tz::Asia__Tokyo),
+            utc("2025-06-02T15:00:00Z")
+        );
+        assert_eq!(
+            next_midnight(utc("2025-06-01T23:00:00Z"), Tz::America__New_York),
+            utc("2025-06-02T04:00:00Z")
+        );
+    }
+
+    #[test]
+    fn next_midnight_without_midnight() {
+        // Santiago goes from 00:00 to 01:00 on 2025-09-07, that day starts at 01:00 -03
+        assert_eq!(
+            next_midnight(utc("2025-09-06T16:00:00Z"), Tz::America__Santiago),
+            utc("2025-09-07T04:00:00Z")
+        );
+        // And the next one is a normal midnight
+        assert_eq!(
+            next_midnight(utc("2025-09-07T04:00:00Z"), Tz::America__Santiago),
+            utc("2025-09-08T03:00:00Z")
+        );
+    }
+
+    #[test]
+    fn next_midnight_after_a_repeated_hour() {
+        // Santiago goes back from 00:00 to 23:00 on 2025-04-06, 23:00 on the 5th happens twice
+        assert_eq!(
+            next_midnight(utc("2025-04-05T12:00:00Z"), Tz::America__Santiago),
+            utc("2025-04-06T04:00:00Z")
+        );
+        // From inside the repeated hour
+        assert_eq!(
+            next_midnight(utc("2025-04-06T03:30:00Z"), Tz::America__Santiago),
+            utc("2025-04-06T04:00:00Z")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Serialize, de::DeserializeOwned};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+use tracing::{info, warn};
+use worker::{Cache, Response};
+
+/// Cache keys must be URLs, this host is never resolved
+const CACHE_HOST: &str = "https://settings-cache.internal";
+
+/// How long an entry may be served without being invalidated.
+/// The Cache API is local to each data center, so a write only invalidates the data center that
+/// handled it, and this TTL bounds how stale the others can get. Kept short since settings
+/// reads fall back to a single indexed D1 query.
+pub const CACHE_TTL_SECONDS: u32 = 30;
+
+/// Every cached view of a guild's settings, invalidated together on any settings write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum SettingsSection {
+    All,
+    Prefix,
+    Language,
+    Timezone,
+    Colour,
+}
+
+/// Read-through cache for guild settings, backed by the Workers Cache API
+///
+/// Hits and misses are traced with `cache`, `section`, `guild_id` and `hit` fields.
+#[derive(Debug)]
+pub struct SettingsCache {
+    cache: Cache,
+}
+
+impl Default for SettingsCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsCache {
+    /// Key of the full `GuildSettings` stream, only cached when streamed from the start
+    pub const STREAM_KEY: &'static str = "https://settings-cache.internal/stream/settings";
+
+    pub fn new() -> Self {
+        Self {
+            cache: Cache::default(),
+        }
+    }
+
+    fn key(guild_id: &str, section: SettingsSection) -> String {
+        format!("{}/guild/{}/{}", CACHE_HOST, guild_id, section.as_ref())
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        guild_id: &str,
+        section: SettingsSection,
+    ) -> Option<T> {
+        let cached = match self.cache.get(Self::key(guild_id, section), true).await {
+            Ok(Some(mut response)) => response.json::<T>().await.ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Failed to read {} settings cache: {}", section.as_ref(), e);
+                None
+            }
+        };
+        info!(
+            cache = "guild_settings",
+            section = section.as_ref(),
+            guild_id,
+            hit = cached.is_some(),
+            "Guild settings cache {}",
+            if cached.is_some() { "hit" } else { "miss" }
+        );
+        cached
+    }
+
+    pub async fn put<T: Serialize>(&self, guild_id: &str, section: SettingsSection, value: &T) {
+        let response = Response::from_json(value).and_then(cacheable);
+        let result = match response {
+            Ok(response) => self.cache.put(Self::key(guild_id, section), response).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Failed to cache {} settings: {}", section.as_ref(), e);
+        }
+    }
+
+    /// Drop every cached section of a guild, and the settings stream which contains it
+    pub async fn invalidate(&self, guild_id: &str) {
+        let keys = (SettingsSection::iter())
+            .map(|section| Self::key(guild_id, section))
+            .chain([Self::STREAM_KEY.to_string()]);
+        for key in keys {
+            if let Err(e) = self.cache.delete(key.as_str(), true).await {
+                warn!("Failed to invalidate settings cache {}: {}", key, e);
+            }
+        }
+        info!(
+            cache = "guild_settings",
+            guild_id, "Guild settings cache invalidated"
+        );
+    }
+}
+
+/// Mark a response as cacheable for [`CACHE_TTL_SECONDS`]
+pub fn cacheable(mut response: Response) -> worker::Result<Response> {
+    (response.headers_mut()).set("Cache-Control", &format!("max-age={}", CACHE_TTL_SECONDS))?;
+    Ok(response)
+}
@@ -0,0 +1,18 @@
+use tracing::warn;
+use worker::Env;
+
+use crate::durables::bot::{BotDurable, ConfigSection};
+
+/// Tell the bot a section of the guild's configuration changed, after the write succeeded
+///
+/// Failures are only logged, the bot still picks the change up on its next stream refresh.
+pub async fn publish(env: &Env, guild_id: &str, section: ConfigSection) {
+    if let Err(e) = BotDurable::publish_config_update(env, guild_id, section).await {
+        warn!(
+            "Failed to publish {} config update of guild {}: {}",
+            section.as_ref(),
+            guild_id,
+            e
+        );
+    }
+}
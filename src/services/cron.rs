@@ -0,0 +1,288 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Days searched for the next occurrence, enough for `0 0 29 2 *`
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// A standard five field cron expression: `minute hour day-of-month month day-of-week`
+///
+/// Fields accept `*`, values, ranges (`1-5`), steps (`*/15`, `10-50/10`) and lists (`1,15`).
+/// Day of week runs from 0 (Sunday) to 6, 7 is Sunday too. Like Vixie cron, when both day fields
+/// are restricted a day matching either of them matches. The `@hourly`, `@daily`, `@weekly`,
+/// `@monthly` and `@yearly` shorthands are accepted as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_any: bool,
+    day_of_week_any: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields.as_slice() else {
+            return Err(format!(
+                "Expected 5 fields in cron expression, found {}",
+                fields.len()
+            ));
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0, 7, "day of week")?;
+        // 7 is another name for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(CronSchedule {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days_of_month: parse_field(day_of_month, 1, 31, "day of month")?,
+            months: parse_field(month, 1, 12, "month")?,
+            days_of_week,
+            day_of_month_any: day_of_month.starts_with('*'),
+            day_of_week_any: day_of_week.starts_with('*'),
+        })
+    }
+
+    /// First occurrence strictly after `after`, with the fields read as wall time in `timezone`
+    ///
+    /// Wall times skipped by a DST change don't run that day, repeated ones run at their first occurrence.
+    pub fn next_after(&self, after: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&timezone).date_naive();
+        (0..SEARCH_DAYS)
+            .filter_map(|offset| start.checked_add_signed(Duration::days(offset)))
+            .filter(|date| self.matches_day(date))
+            .find_map(|date| self.first_time_of_day(date, after, timezone))
+    }
+
+    fn matches_day(&self, date: &NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let day_of_month = bit(self.days_of_month, date.day());
+        let day_of_week = bit(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.day_of_month_any, self.day_of_week_any) {
+            (true, true) => true,
+            (false, true) => day_of_month,
+            (true, false) => day_of_week,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    fn first_time_of_day(
+        &self,
+        date: NaiveDate,
+        after: DateTime<Utc>,
+        timezone: Tz,
+    ) -> Option<DateTime<Utc>> {
+        let hours = (0..24).filter(|hour| bit(self.hours, *hour));
+        hours
+            .flat_map(|hour| {
+                (0..60)
+                    .filter(|minute| bit(self.minutes, *minute))
+                    .map(move |minute| (hour, minute))
+            })
+            .filter_map(|(hour, minute)| date.and_hms_opt(hour, minute, 0))
+            .filter_map(|time| timezone.from_local_datetime(&time).earliest())
+            .map(|time| time.with_timezone(&Utc))
+            .find(|time| *time > after)
+    }
+}
+
+/// Parse a field into a bit set of the allowed values
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid {} field in cron expression: `{}`", name, field);
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                // `5/15` runs from 5 to the end of the field
+                None if part.contains('/') => (range.parse().map_err(|_| invalid())?, max),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    fn next(expression: &str, after: &str, timezone: Tz) -> String {
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(utc(after), timezone)
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    fn values(set: u64) -> Vec<u32> {
+        (0..64).filter(|value| bit(set, *value)).collect()
+    }
+
+    #[test]
+    fn steps_and_ranges() {
+        let minutes = |field| values(parse_field(field, 0, 59, "minute").unwrap());
+        assert_eq!(minutes("*/15"), [0, 15, 30, 45]);
+        assert_eq!(minutes("10-50/10"), [10, 20, 30, 40, 50]);
+        assert_eq!(minutes("5/15"), [5, 20, 35, 50]);
+        assert_eq!(minutes("1,3-4,58"), [1, 3, 4, 58]);
+        assert_eq!(minutes("7"), [7]);
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7"),
+            CronSchedule::parse("0 0 * * 0")
+        );
+        let weekend = CronSchedule::parse("0 0 * * 6-7").unwrap();
+        assert_eq!(values(weekend.days_of_week), [0, 6]);
+        // 2025-06-01 is a Sunday
+        assert_eq!(
+            next("0 0 * * 7", "2025-05-28T00:00:00Z", Tz::UTC),
+            "2025-06-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn restricted_days_match_either_field() {
+        // The 10th or any Monday, from Tuesday 2025-06-03
+        let schedule = "0 0 10 * 1";
+        assert_eq!(
+            next(schedule, "2025-06-03T00:00:00Z", Tz::UTC),
+            "2025-06-09T00:00:00+00:00"
+        );
+        assert_eq!(
+            next(schedule, "2025-06-09T00:00:00Z", Tz::UTC),
+            "2025-06-10T00:00:00+00:00"
+        );
+        assert_eq!(
+            next(schedule, "2025-06-10T00:00:00Z", Tz::UTC),
+            "2025-06-16T00:00:00+00:00"
+        );
+        // A `*` day of week leaves the day of month alone
+        assert_eq!(
+            next("0 0 10 * *", "2025-06-03T00:00:00Z", Tz::UTC),
+            "2025-06-10T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn leap_day_waits_for_a_leap_year() {
+        assert_eq!(
+            next("0 0 29 2 *", "2025-01-01T00:00:00Z", Tz::UTC),
+            "2028-02-29T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn next_occurrence_is_strictly_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2025-06-01T12:15:00Z", Tz::UTC),
+            "2025-06-01T12:30:00+00:00"
+        );
+        assert_eq!(
+            next("@hourly", "2025-12-31T23:30:00Z", Tz::UTC),
+            "2026-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn fields_are_read_in_the_timezone() {
+        assert_eq!(
+            next("0 9 * * *", "2025-06-01T00:00:00Z", Tz::Europe__Paris),
+            "2025-06-01T07:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn skipped_wall_time_does_not_run_that_day() {
+        // 02:30 doesn't exist in New York on 2025-03-09
+        assert_eq!(
+            next("30 2 * * *", "2025-03-09T05:00:00Z", Tz::America__New_York),
+            "2025-03-10T06:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn repeated_wall_time_runs_once() {
+        // 01:30 happens twice in New York on 2025-11-02, at 05:30 and 06:30 UTC
+        let schedule = "30 1 * * *";
+        assert_eq!(
+            next(schedule, "2025-11-02T04:00:00Z", Tz::America__New_York),
+            "2025-11-02T05:30:00+00:00"
+        );
+        assert_eq!(
+            next(schedule, "2025-11-02T05:30:00Z", Tz::America__New_York),
+            "2025-11-03T06:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in [
+            "*/0 * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * 32 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "50-10 * * * *",
+            "a * * * *",
+            "*/x * * * *",
+            "* * * *",
+            "* * * * * *",
+            "@reboot",
+        ] {
+            assert!(
+                CronSchedule::parse(expression).is_err(),
+                "`{}` should be rejected",
+                expression
+            );
+        }
+    }
+}
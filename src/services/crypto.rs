@@ -0,0 +1,121 @@
+use wasm_bindgen::{JsCast, JsValue, prelude::wasm_bindgen};
+use wasm_bindgen_futures::JsFuture;
+use worker::{
+    Result,
+    js_sys::{Array, ArrayBuffer, Object, Promise, Reflect, Uint8Array},
+};
+
+/// Bytes of the random AES-GCM nonce stored in front of every ciphertext
+const NONCE_LENGTH: usize = 12;
+
+// The Web Crypto API of the Workers runtime, `worker` only wraps `DigestStream`
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace = ["crypto", "subtle"], js_name = digest)]
+    fn subtle_digest(algorithm: &str, data: &Uint8Array) -> std::result::Result<Promise, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["crypto", "subtle"], js_name = importKey)]
+    fn subtle_import_key(
+        format: &str,
+        key: &Uint8Array,
+        algorithm: &str,
+        extractable: bool,
+        usages: &Array,
+    ) -> std::result::Result<Promise, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["crypto", "subtle"], js_name = encrypt)]
+    fn subtle_encrypt(
+        algorithm: &Object,
+        key: &JsValue,
+        data: &Uint8Array,
+    ) -> std::result::Result<Promise, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["crypto", "subtle"], js_name = decrypt)]
+    fn subtle_decrypt(
+        algorithm: &Object,
+        key: &JsValue,
+        data: &Uint8Array,
+    ) -> std::result::Result<Promise, JsValue>;
+}
+
+async fn resolve_bytes(promise: std::result::Result<Promise, JsValue>) -> Result<Vec<u8>> {
+    let buffer = JsFuture::from(promise?).await?;
+    Ok(Uint8Array::new(&buffer.unchecked_into::<ArrayBuffer>()).to_vec())
+}
+
+pub async fn sha256(data: &[u8]) -> Result<Vec<u8>> {
+    resolve_bytes(subtle_digest("SHA-256", &Uint8Array::from(data))).await
+}
+
+pub fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| worker::Error::RustError(format!("No randomness: {}", e)))?;
+    Ok(bytes)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// AES-256-GCM key, derived from a secret of the worker environment
+pub struct SecretKey {
+    key: JsValue,
+}
+
+impl SecretKey {
+    /// The key is the SHA-256 of `secret`, so any string of enough entropy can be used
+    pub async fn derive(secret: &str) -> Result<Self> {
+        let raw = sha256(secret.as_bytes()).await?;
+        let usages = Array::of2(&"encrypt".into(), &"decrypt".into());
+        let promise = subtle_import_key(
+            "raw",
+            &Uint8Array::from(&raw[..]),
+            "AES-GCM",
+            false,
+            &usages,
+        )?;
+        let key = JsFuture::from(promise).await?;
+        Ok(Self { key })
+    }
+
+    fn algorithm(nonce: &[u8]) -> Result<Object> {
+        let algorithm = Object::new();
+        Reflect::set(&algorithm, &"name".into(), &"AES-GCM".into())?;
+        Reflect::set(&algorithm, &"iv".into(), &Uint8Array::from(nonce))?;
+        Ok(algorithm)
+    }
+
+    /// Encrypt `plaintext` under a fresh nonce, hex encoded with the nonce in front
+    pub async fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = random_bytes::<NONCE_LENGTH>()?;
+        let data = Uint8Array::from(plaintext.as_bytes());
+        let ciphertext =
+            resolve_bytes(subtle_encrypt(&Self::algorithm(&nonce)?, &self.key, &data)).await?;
+        Ok(format!("{}{}", to_hex(&nonce), to_hex(&ciphertext)))
+    }
+
+    /// Decrypt the output of [`SecretKey::encrypt`], failing when it was tampered with
+    pub async fn decrypt(&self, encrypted: &str) -> Result<String> {
+        let invalid = || worker::Error::RustError("Invalid encrypted value".into());
+        let bytes = from_hex(encrypted).ok_or_else(invalid)?;
+        if bytes.len() <= NONCE_LENGTH {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+        let data = Uint8Array::from(ciphertext);
+        let plaintext =
+            resolve_bytes(subtle_decrypt(&Self::algorithm(nonce)?, &self.key, &data)).await?;
+        String::from_utf8(plaintext).map_err(|_| invalid())
+    }
+}
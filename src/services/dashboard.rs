@@ -0,0 +1,29 @@
+use serde::Serialize;
+use tracing::warn;
+use worker::Env;
+
+use crate::{
+    durables::bot::{BotDurable, DashboardEvent, SettingsUpdatedPayload},
+    services::{cache::SettingsSection, websocket::WsEnvelope},
+};
+
+/// Push an event to the dashboards following the guild
+///
+/// Failures are only logged, dashboards load the current state again when they reconnect.
+pub async fn push<T: Serialize>(env: &Env, guild_id: &str, event: DashboardEvent, data: T) {
+    let envelope = WsEnvelope::new(event, data);
+    if let Err(e) = BotDurable::send_dashboard_event(env, guild_id, &envelope).await {
+        warn!(
+            "Failed to push {:?} to dashboards of guild {}: {}",
+            event, guild_id, e
+        );
+    }
+}
+
+pub async fn settings_updated(env: &Env, guild_id: &str, section: SettingsSection) {
+    let payload = SettingsUpdatedPayload {
+        guild_id: guild_id.to_string(),
+        section: section.as_ref().to_string(),
+    };
+    push(env, guild_id, DashboardEvent::SettingsUpdated, payload).await;
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    schema::{
+        guild::GiveawaysSchema,
+        user::{GiveawayEntriesSchema, GiveawayRound, GiveawayWinnersSchema},
+    },
+    state::database::{Database, DatabaseExt, DbError, DbResult, UnitOfWork},
+};
+
+/// Data of a `GiveawayEnded` event, sent to the bot when a giveaway ends or is rerolled
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GiveawayEndedPayload {
+    pub giveaway: GiveawaysSchema,
+    /// Winners of this draw only, empty when nobody was left to pick
+    pub winners: Vec<String>,
+    pub round: i64,
+    pub reroll: bool,
+}
+
+/// Pick up to `count` distinct entrants with the platform CSPRNG
+///
+/// A partial Fisher-Yates shuffle, with rejection sampling so every entrant is equally likely.
+pub fn draw_winners(mut entrants: Vec<String>, count: usize) -> DbResult<Vec<String>> {
+    let count = count.min(entrants.len());
+    for i in 0..count {
+        let j = i + random_below((entrants.len() - i) as u64)? as usize;
+        entrants.swap(i, j);
+    }
+    entrants.truncate(count);
+    Ok(entrants)
+}
+
+/// Uniform random number in `0..bound`
+fn random_below(bound: u64) -> DbResult<u64> {
+    // Values past the last full multiple of `bound` would favour the low results
+    let zone = u64::MAX - (u64::MAX % bound);
+    loop {
+        let value =
+            getrandom::u64().map_err(|e| DbError::Unavailable(format!("No randomness: {}", e)))?;
+        if value < zone {
+            return Ok(value % bound);
+        }
+    }
+}
+
+/// End a running giveaway: draw its winners, store them and mark it ended
+///
+/// Returns `None` when the giveaway doesn't exist or already ended, so calling it twice is harmless.
+pub async fn end_giveaway(
+    database: &Database,
+    giveaway_id: &String,
+) -> DbResult<Option<GiveawayEndedPayload>> {
+    let giveaways: Vec<GiveawaysSchema> = database
+        .execute(GiveawaysSchema::get_giveaway(giveaway_id))
+        .await?;
+    let Some(giveaway) = giveaways.into_iter().find(|giveaway| !giveaway.ended) else {
+        return Ok(None);
+    };
+
+    let entrants = eligible_entrants(database, giveaway_id).await?;
+    let winners = draw_winners(entrants, giveaway.winners_count.max(0) as usize)?;
+
+    // The winners are only stored while the giveaway is running, so before it is marked ended
+    let mut work = UnitOfWork::new();
+    let inserted = work.add::<GiveawayWinnersSchema>(GiveawayWinnersSchema::insert_first_round(
+        giveaway_id,
+        &winners,
+    ));
+    let ended = work.add::<GiveawaysSchema>(GiveawaysSchema::mark_ended(giveaway_id));
+    let results = database.commit(work).await?;
+    let Some(giveaway) = results.first(&ended)? else {
+        // Ended by someone else in the meantime
+        return Ok(None);
+    };
+    let winners: Vec<String> = (results.get(&inserted)?.into_iter())
+        .map(|winner| winner.user_id)
+        .collect();
+
+    info!(
+        "Giveaway {} ended with {} winner(s)",
+        giveaway_id,
+        winners.len()
+    );
+    Ok(Some(GiveawayEndedPayload {
+        giveaway,
+        winners,
+        round: 0,
+        reroll: false,
+    }))
+}
+
+/// Draw `count` new winners of an ended giveaway among the entrants who haven't won yet
+///
+/// Returns `None` when the giveaway doesn't exist or is still running.
+pub async fn reroll_giveaway(
+    database: &Database,
+    giveaway_id: &String,
+    count: usize,
+) -> DbResult<Option<GiveawayEndedPayload>> {
+    let giveaways: Vec<GiveawaysSchema> = database
+        .execute(GiveawaysSchema::get_giveaway(giveaway_id))
+        .await?;
+    let Some(giveaway) = giveaways.into_iter().find(|giveaway| giveaway.ended) else {
+        return Ok(None);
+    };
+
+    let entrants = eligible_entrants(database, giveaway_id).await?;
+    let drawn = draw_winners(entrants, count)?;
+    if drawn.is_empty() {
+        warn!("No entrants left to reroll giveaway {}", giveaway_id);
+        let rounds: Vec<GiveawayRound> = database
+            .execute(GiveawayWinnersSchema::next_round(giveaway_id))
+            .await?;
+        return Ok(Some(GiveawayEndedPayload {
+            giveaway,
+            winners: Vec::new(),
+            round: rounds.first().map_or(0, |round| round.round),
+            reroll: true,
+        }));
+    }
+
+    let mut work = UnitOfWork::new();
+    let inserted = work.add::<GiveawayWinnersSchema>(GiveawayWinnersSchema::insert_next_round(
+        giveaway_id,
+        &drawn,
+    ));
+    let winners: Vec<GiveawayWinnersSchema> = database.commit(work).await?.get(&inserted)?;
+    let round = winners.first().map_or(0, |winner| winner.round);
+    if winners.len() < drawn.len() {
+        warn!(
+            "{} drawn entrant(s) of giveaway {} won in a concurrent draw",
+            drawn.len() - winners.len(),
+            giveaway_id
+        );
+    }
+
+    Ok(Some(GiveawayEndedPayload {
+        giveaway,
+        winners: winners.into_iter().map(|winner| winner.user_id).collect(),
+        round,
+        reroll: true,
+    }))
+}
+
+async fn eligible_entrants(database: &Database, giveaway_id: &str) -> DbResult<Vec<String>> {
+    let entries: Vec<GiveawayEntriesSchema> = database
+        .execute(GiveawayEntriesSchema::get_eligible(giveaway_id))
+        .await?;
+    Ok(entries.into_iter().map(|entry| entry.user_id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn entrants(count: usize) -> Vec<String> {
+        (0..count).map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn everyone_wins_when_count_exceeds_entrants() {
+        let mut winners = draw_winners(entrants(3), 10).unwrap();
+        winners.sort();
+        assert_eq!(winners, entrants(3));
+    }
+
+    #[test]
+    fn nobody_wins_a_draw_of_zero() {
+        assert!(draw_winners(entrants(3), 0).unwrap().is_empty());
+        assert!(draw_winners(Vec::new(), 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn winners_are_distinct_entrants() {
+        for _ in 0..100 {
+            let winners = draw_winners(entrants(20), 5).unwrap();
+            assert_eq!(winners.len(), 5);
+            let distinct: HashSet<_> = winners.iter().collect();
+            assert_eq!(distinct.len(), 5);
+            assert!(winners.iter().all(|w| entrants(20).contains(w)));
+        }
+    }
+
+    #[test]
+    fn every_entrant_can_win() {
+        let mut won = HashSet::new();
+        for _ in 0..500 {
+            won.extend(draw_winners(entrants(5), 1).unwrap());
+        }
+        assert_eq!(won.len(), 5);
+    }
+
+    #[test]
+    fn random_below_stays_in_bounds() {
+        for bound in [1, 2, 3, 7, u64::MAX] {
+            for _ in 0..100 {
+                assert!(random_below(bound).unwrap() < bound);
+            }
+        }
+    }
+}
@@ -16,6 +16,19 @@ pub struct PartialDiscordGuild {
     pub approximate_presence_count: Option<u64>,
 }
 
+/// `ADMINISTRATOR` permission bit
+const ADMINISTRATOR: u64 = 1 << 3;
+/// `MANAGE_GUILD` permission bit
+const MANAGE_GUILD: u64 = 1 << 5;
+
+impl PartialDiscordGuild {
+    /// Whether the user this guild was listed for may change its settings
+    pub fn can_manage(&self) -> bool {
+        let permissions = self.permissions.parse::<u64>().unwrap_or_default();
+        self.owner || permissions & (ADMINISTRATOR | MANAGE_GUILD) != 0
+    }
+}
+
 impl IntoResponse for PartialDiscordGuild {
     fn into_response(self) -> axum::response::Response {
         let body = serde_json::to_string(&self).unwrap_or_else(|_| "{}".to_string());
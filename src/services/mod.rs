@@ -1,9 +1,19 @@
-use worker::{console_error, Env};
+use worker::{Env, console_error};
 
 pub mod auth;
+pub mod birthdays;
+pub mod cache;
+pub mod config;
 pub mod cookie;
+pub mod cron;
+pub mod crypto;
+pub mod dashboard;
+pub mod giveaways;
 pub mod guilds;
+pub mod retention;
+pub mod session;
 pub mod streaming;
+pub mod timed_messages;
 pub mod user;
 pub mod websocket;
 
@@ -0,0 +1,48 @@
+use serde::Deserialize;
+use tracing::{error, info};
+use worker::Env;
+
+use crate::{
+    schema::guild::GuildSchema,
+    state::database::{Database, DbResult, UnitOfWork},
+};
+
+/// Days a soft deleted guild keeps its configuration, unless `SOFT_DELETE_RETENTION_DAYS` is set
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+#[derive(Debug, Deserialize)]
+struct PurgedGuild {
+    id: String,
+}
+
+pub fn retention_days(env: &Env) -> u32 {
+    (env.var("SOFT_DELETE_RETENTION_DAYS").ok())
+        .and_then(|days| days.to_string().parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// Hard delete every guild soft deleted for longer than the retention window, run by the cron trigger
+pub async fn purge_deleted_guilds(env: &Env, database: &Database) -> DbResult<Vec<String>> {
+    let days = retention_days(env);
+
+    let mut work = UnitOfWork::new();
+    work.push(GuildSchema::purge_deleted_members(days));
+    let purged = work.add::<PurgedGuild>(GuildSchema::purge_deleted(days));
+
+    let results = database.commit(work).await.inspect_err(|e| {
+        error!(
+            "Failed to purge guilds deleted over {} days ago: {}",
+            days, e
+        );
+    })?;
+    let purged: Vec<String> = (results.get(&purged)?.into_iter())
+        .map(|guild| guild.id)
+        .collect();
+
+    info!(
+        "Purged {} guilds deleted over {} days ago",
+        purged.len(),
+        days
+    );
+    Ok(purged)
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use cookie::{Cookie, SameSite};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use worker::{Env, Result};
+
+use crate::{
+    schema::user::UserSessionSchema,
+    services::{
+        auth::{DiscordAPIClient, DiscordOAuthAccessToken, DiscordTokenType},
+        crypto::{self, SecretKey},
+    },
+    state::database::{Database, DatabaseExt},
+};
+
+/// Cookie holding the opaque session token, the session itself stays in `user_sessions`
+pub const SESSION_COOKIE: &str = "session";
+
+/// Days a login lasts, unless `SESSION_TTL_DAYS` is set
+pub const DEFAULT_SESSION_DAYS: i64 = 30;
+
+/// Access tokens are refreshed this long before Discord expires them
+const REFRESH_MARGIN_SECONDS: i64 = 60;
+/// `last_used_at` is only written again once it is older than this
+const LAST_USED_RESOLUTION_SECONDS: i64 = 5 * 60;
+
+pub fn session_days(env: &Env) -> i64 {
+    (env.var("SESSION_TTL_DAYS").ok())
+        .and_then(|days| days.to_string().parse().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_SESSION_DAYS)
+}
+
+/// A session resolved from its cookie, with a usable Discord access token
+#[derive(Debug, Clone)]
+pub struct ActiveSession {
+    pub id: String,
+    pub user_id: String,
+    pub access_token: String,
+}
+
+/// Public view of a session, tokens are never returned
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    /// Whether this is the session of the request
+    pub current: bool,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Key encrypting the Discord tokens at rest, from the `SESSION_ENCRYPTION_KEY` secret
+async fn encryption_key(env: &Env) -> Result<SecretKey> {
+    let secret = env.secret("SESSION_ENCRYPTION_KEY").map_err(|_| {
+        error!("SESSION_ENCRYPTION_KEY not set");
+        worker::Error::RustError("SESSION_ENCRYPTION_KEY not set".into())
+    })?;
+    SecretKey::derive(&secret.to_string()).await
+}
+
+/// Sessions are stored under the hash of their token, so a database leak can't be replayed
+async fn session_id(token: &str) -> Result<String> {
+    Ok(crypto::to_hex(&crypto::sha256(token.as_bytes()).await?))
+}
+
+fn session_cookie(token: String, days: i64) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::None)
+        .max_age(cookie::time::Duration::days(days))
+        .build()
+}
+
+/// Start a session for the tokens Discord handed out at login, returning its cookie
+pub async fn create(
+    env: &Env,
+    database: &Database,
+    tokens: &DiscordOAuthAccessToken,
+    user_id: String,
+    user_agent: Option<String>,
+) -> Result<Cookie<'static>> {
+    let key = encryption_key(env).await?;
+    let token = crypto::to_hex(&crypto::random_bytes::<32>()?);
+    let days = session_days(env);
+    let now = Utc::now();
+
+    let session = UserSessionSchema {
+        id: session_id(&token).await?,
+        user_id,
+        access_token: key.encrypt(tokens.access_token()).await?,
+        refresh_token: key.encrypt(tokens.refresh_token()).await?,
+        access_expires_at: timestamp(now + chrono::Duration::seconds(tokens.expires_in())),
+        expires_at: timestamp(now + chrono::Duration::days(days)),
+        user_agent,
+        last_used_at: timestamp(now),
+        created_at: String::new(),
+    };
+    let _: () = database.execute(session.upsert()).await?;
+    info!("Started a session for user {}", session.user_id);
+    Ok(session_cookie(token, days))
+}
+
+/// The session of a cookie token, refreshing its access token when it is about to expire
+///
+/// `None` when the session is unknown, ended, or Discord no longer accepts its refresh token.
+pub async fn resolve(
+    env: &Env,
+    database: &Database,
+    discord: &DiscordAPIClient,
+    token: &str,
+) -> Result<Option<ActiveSession>> {
+    let id = session_id(token).await?;
+    let now = Utc::now();
+    let sessions: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::get_session(&id, &timestamp(now)))
+        .await?;
+    let Some(session) = sessions.into_iter().next() else {
+        return Ok(None);
+    };
+    let key = encryption_key(env).await?;
+
+    let refresh_after = timestamp(now + chrono::Duration::seconds(REFRESH_MARGIN_SECONDS));
+    if session.access_expires_at > refresh_after {
+        let stale = timestamp(now - chrono::Duration::seconds(LAST_USED_RESOLUTION_SECONDS));
+        if session.last_used_at < stale {
+            let _: () = database
+                .execute(UserSessionSchema::set_last_used_at(&id, &timestamp(now)))
+                .await?;
+        }
+        return active(&key, session).await.map(Some);
+    }
+
+    let refresh_token = key.decrypt(&session.refresh_token).await?;
+    let tokens = match discord.refresh_access_token(&refresh_token).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            // A concurrent request may have used the refresh token first
+            if let Some(current) = refreshed(database, &id, now, &refresh_after).await? {
+                return active(&key, current).await.map(Some);
+            }
+            warn!(
+                "Ending session of user {}, refresh failed: {}",
+                session.user_id, e
+            );
+            let _: Vec<UserSessionSchema> = database
+                .execute(UserSessionSchema::delete_stale_session(&session))
+                .await?;
+            return Ok(None);
+        }
+    };
+    let updated: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::update_tokens(
+            &session,
+            key.encrypt(tokens.access_token()).await?,
+            key.encrypt(tokens.refresh_token()).await?,
+            timestamp(now + chrono::Duration::seconds(tokens.expires_in())),
+            &timestamp(now),
+        ))
+        .await?;
+    if updated.is_empty() {
+        // Refreshed or ended by a concurrent request, the stored tokens win
+        let current = refreshed(database, &id, now, &refresh_after).await?;
+        return match current {
+            Some(current) => active(&key, current).await.map(Some),
+            None => Ok(None),
+        };
+    }
+    Ok(Some(ActiveSession {
+        id,
+        user_id: session.user_id,
+        access_token: tokens.access_token().to_string(),
+    }))
+}
+
+/// The session as stored now, if its access token is still usable after `refresh_after`
+async fn refreshed(
+    database: &Database,
+    id: &str,
+    now: DateTime<Utc>,
+    refresh_after: &str,
+) -> Result<Option<UserSessionSchema>> {
+    let sessions: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::get_session(id, &timestamp(now)))
+        .await?;
+    Ok((sessions.into_iter()).find(|session| session.access_expires_at.as_str() > refresh_after))
+}
+
+async fn active(key: &SecretKey, session: UserSessionSchema) -> Result<ActiveSession> {
+    Ok(ActiveSession {
+        access_token: key.decrypt(&session.access_token).await?,
+        id: session.id,
+        user_id: session.user_id,
+    })
+}
+
+/// Sessions of a user that haven't ended, most recently used first
+pub async fn list(database: &Database, user_id: &str, current: &str) -> Result<Vec<SessionInfo>> {
+    let now = timestamp(Utc::now());
+    let sessions: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::get_user_sessions(user_id, &now))
+        .await?;
+    Ok((sessions.into_iter())
+        .map(|session| SessionInfo {
+            current: session.id == current,
+            id: session.id,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            expires_at: session.expires_at,
+        })
+        .collect())
+}
+
+/// End the session of a cookie token and revoke its tokens at Discord
+pub async fn end(
+    env: &Env,
+    database: &Database,
+    discord: &DiscordAPIClient,
+    token: &str,
+) -> Result<()> {
+    let id = session_id(token).await?;
+    let ended: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::delete_session(&id))
+        .await?;
+    for session in ended {
+        revoke(env, discord, &session).await;
+    }
+    Ok(())
+}
+
+/// End one of the sessions of `user_id`, returning whether it existed
+pub async fn end_user_session(
+    env: &Env,
+    database: &Database,
+    discord: &DiscordAPIClient,
+    id: &str,
+    user_id: &str,
+) -> Result<bool> {
+    let ended: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::delete_user_session(id, user_id))
+        .await?;
+    for session in ended.iter() {
+        revoke(env, discord, session).await;
+    }
+    Ok(!ended.is_empty())
+}
+
+/// Revoke the tokens of an ended session, failures are only logged since the row is gone
+async fn revoke(env: &Env, discord: &DiscordAPIClient, session: &UserSessionSchema) {
+    let key = match encryption_key(env).await {
+        Ok(key) => key,
+        Err(e) => {
+            error!(
+                "Failed to revoke the tokens of user {}: {}",
+                session.user_id, e
+            );
+            return;
+        }
+    };
+    let tokens = [
+        (&session.access_token, DiscordTokenType::AccessToken),
+        (&session.refresh_token, DiscordTokenType::RefreshToken),
+    ];
+    for (encrypted, token_type) in tokens {
+        let revoked = match key.decrypt(encrypted).await {
+            Ok(token) => discord.revoke_token(&token, token_type).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = revoked {
+            error!(
+                "Failed to revoke a token of user {} at Discord: {}",
+                session.user_id, e
+            );
+        }
+    }
+    info!("Ended a session of user {}", session.user_id);
+}
+
+/// Delete every session past its expiry, run by the cron trigger
+pub async fn purge_expired(database: &Database) -> Result<usize> {
+    let purged: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::purge_expired(&timestamp(Utc::now())))
+        .await
+        .inspect_err(|e| error!("Failed to purge expired sessions: {}", e))?;
+    info!("Purged {} expired sessions", purged.len());
+    Ok(purged.len())
+}
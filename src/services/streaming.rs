@@ -6,37 +6,146 @@ use axum::{
     response::IntoResponse,
 };
 use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use tokio::sync::mpsc::channel;
 use tokio_stream::wrappers::ReceiverStream;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 use wasm_bindgen_futures::spawn_local;
+use worker::Cache;
 
-use crate::state::database::{Database, DatabaseExt};
+use crate::{
+    services::cache::cacheable,
+    state::database::{Database, DatabaseExt},
+};
 
 const BATCH_SIZE: u64 = 100;
 
-pub trait StreamableSchema {
-    fn all_by_batch(batch_size: u64, offset: u64) -> sea_query::SelectStatement;
+/// A schema that can be streamed in pages ordered by its primary key
+///
+/// Pages are fetched with keyset pagination (`WHERE key > cursor ORDER BY key LIMIT n`),
+/// so a stream never skips or repeats rows when other rows are inserted or deleted mid-stream.
+pub trait StreamableSchema: Sized {
+    /// Primary key of a row, sent with every NDJSON line so the stream can be resumed
+    type Cursor: Serialize + DeserializeOwned;
+
+    /// Up to `batch_size` rows whose key is strictly after `after`, in ascending key order
+    fn batch_after(batch_size: u64, after: Option<&Self::Cursor>) -> sea_query::SelectStatement;
+
+    /// The key of this row
+    fn cursor(&self) -> Self::Cursor;
+}
+
+/// Query of the stream routes, `?after=<cursor>` resumes right after the line carrying that cursor
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamQuery {
+    pub after: Option<String>,
+}
+
+/// A single NDJSON line, the row's fields with its cursor alongside
+#[derive(Serialize)]
+struct StreamLine<'a, T> {
+    cursor: String,
+    #[serde(flatten)]
+    row: &'a T,
+}
+
+/// Encode a cursor as an opaque, URL safe string
+pub fn encode_cursor<C: Serialize>(cursor: &C) -> String {
+    let json = serde_json::to_vec(cursor).unwrap_or_default();
+    json.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode_cursor<C: DeserializeOwned>(cursor: &str) -> Result<C, String> {
+    if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+        return Err("Malformed cursor".to_string());
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "Malformed cursor".to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Malformed cursor: {}", e))
 }
 
 pub fn setup_stream<T>(
     name: &str,
     database: Database,
+    query: StreamQuery,
 ) -> Result<impl IntoResponse, (StatusCode, String)>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+    T: StreamableSchema + Send + 'static,
+{
+    stream_response::<T>(name, database, query, None)
+}
+
+/// Same as [`setup_stream`], but a stream requested from the start is served from the Cache API
+/// under `cache_key`, and stored there once it was streamed completely.
+/// Writers invalidate it by deleting `cache_key`.
+pub async fn setup_cached_stream<T>(
+    name: &str,
+    database: Database,
+    query: StreamQuery,
+    cache_key: &'static str,
+) -> Result<impl IntoResponse, (StatusCode, String)>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+    T: StreamableSchema + Send + 'static,
+{
+    if query.after.is_some() {
+        return stream_response::<T>(name, database, query, None);
+    }
+
+    let cached = match Cache::default().get(cache_key, true).await {
+        Ok(Some(mut response)) => response.text().await.ok(),
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to read cached {name} stream: {}", e);
+            None
+        }
+    };
+    info!(
+        cache = "stream",
+        stream = name,
+        hit = cached.is_some(),
+        "{name} stream cache {}",
+        if cached.is_some() { "hit" } else { "miss" }
+    );
+
+    match cached {
+        Some(body) => ndjson_response(Body::from(body)),
+        None => stream_response::<T>(name, database, query, Some(cache_key)),
+    }
+}
+
+fn stream_response<T>(
+    name: &str,
+    database: Database,
+    query: StreamQuery,
+    cache_key: Option<&'static str>,
+) -> Result<Response<Body>, (StatusCode, String)>
 where
     T: Serialize + for<'a> Deserialize<'a>,
     T: StreamableSchema + Send + 'static,
 {
     debug!("Fetching all {name} schema from the database");
+    let mut cursor = match query.after.as_deref().map(decode_cursor::<T::Cursor>) {
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(e)) => {
+            warn!("Invalid cursor for {name} stream: {}", e);
+            return Err((StatusCode::BAD_REQUEST, e));
+        }
+        None => None,
+    };
+
     let name = name.to_string();
     let (tx, rx) = channel::<Result<Bytes, Error>>(32);
     spawn_local(async move {
-        let mut offset = 0;
+        let mut cached_body = cache_key.map(|_| String::new());
         loop {
-            let query = T::all_by_batch(BATCH_SIZE, offset);
-            let users: Vec<T> = match database.execute(query).await {
-                Ok(users) => users,
+            let query = T::batch_after(BATCH_SIZE, cursor.as_ref());
+            let rows: Vec<T> = match database.execute(query).await {
+                Ok(rows) => rows,
                 Err(e) => {
                     warn!("Failed to get {name} schema: {:?}", e);
                     if let Err(e) = tx
@@ -46,30 +155,50 @@ where
                         .await
                     {
                         debug!("Client disconnected, stopping {name} streaming: {}", e);
-                        break;
                     }
                     return;
                 }
             };
 
-            if users.is_empty() {
-                break;
-            }
-
-            for user in users {
-                let json = serde_json::to_string(&user).unwrap_or_else(|_| "{}".to_string());
-                if let Err(e) = tx.send(Ok(Bytes::from(format!("{}\n", json)))).await {
+            let is_last_batch = (rows.len() as u64) < BATCH_SIZE;
+            for row in rows.iter() {
+                let line = StreamLine {
+                    cursor: encode_cursor(&row.cursor()),
+                    row,
+                };
+                let json = serde_json::to_string(&line).unwrap_or_else(|_| "{}".to_string());
+                let json = format!("{}\n", json);
+                if let Some(body) = cached_body.as_mut() {
+                    body.push_str(&json);
+                }
+                if let Err(e) = tx.send(Ok(Bytes::from(json))).await {
                     debug!("Client disconnected, stopping {name} streaming: {}", e);
-                    break;
+                    return;
                 }
             }
 
-            offset += BATCH_SIZE;
+            match rows.last() {
+                Some(last) if !is_last_batch => cursor = Some(last.cursor()),
+                _ => break,
+            }
+        }
+
+        if let (Some(key), Some(body)) = (cache_key, cached_body) {
+            let response = worker::Response::ok(body).and_then(cacheable);
+            let result = match response {
+                Ok(response) => Cache::default().put(key, response).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Failed to cache {name} stream: {}", e);
+            }
         }
     });
 
-    let body = Body::from_stream(ReceiverStream::new(rx));
+    ndjson_response(Body::from_stream(ReceiverStream::new(rx)))
+}
 
+fn ndjson_response(body: Body) -> Result<Response<Body>, (StatusCode, String)> {
     Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/x-ndjson")
@@ -82,3 +211,67 @@ where
             )
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::AfkStatusSchema;
+
+    use super::*;
+
+    #[test]
+    fn string_cursor_round_trip() {
+        let cursor = encode_cursor(&"123456789012345678".to_string());
+        assert!(cursor.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            decode_cursor::<String>(&cursor).unwrap(),
+            "123456789012345678"
+        );
+    }
+
+    #[test]
+    fn afk_cursor_of_a_global_status_round_trips() {
+        let status = AfkStatusSchema {
+            user_id: "10".into(),
+            guild_id: None,
+            reason: "AFK".into(),
+            created_at: String::new(),
+        };
+        let cursor = encode_cursor(&status.cursor());
+        let decoded: (String, String) = decode_cursor(&cursor).unwrap();
+        assert_eq!(decoded, ("10".to_string(), String::new()));
+    }
+
+    #[test]
+    fn afk_cursor_of_a_guild_status_round_trips() {
+        let cursor = ("10".to_string(), "20".to_string());
+        let decoded: (String, String) = decode_cursor(&encode_cursor(&cursor)).unwrap();
+        assert_eq!(decoded, cursor);
+    }
+
+    #[test]
+    fn odd_length_is_rejected() {
+        assert!(decode_cursor::<String>("abc").is_err());
+    }
+
+    #[test]
+    fn non_hex_is_rejected() {
+        assert!(decode_cursor::<String>("zz").is_err());
+    }
+
+    #[test]
+    fn multibyte_input_is_rejected() {
+        // Two bytes long, slicing it in the middle of the character would panic
+        assert!(decode_cursor::<String>("é").is_err());
+    }
+
+    #[test]
+    fn cursor_of_another_type_is_rejected() {
+        let cursor = encode_cursor(&"10".to_string());
+        assert!(decode_cursor::<(String, String)>(&cursor).is_err());
+    }
+
+    #[test]
+    fn empty_cursor_is_rejected() {
+        assert!(decode_cursor::<String>("").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    schema::guild::{TimedMessagesSchema, TimezoneSchema},
+    services::cron::CronSchedule,
+    state::database::{Database, DatabaseExt, DbResult},
+};
+
+/// Shortest interval a timed message can repeat at
+pub const MIN_INTERVAL_SECONDS: i64 = 60;
+
+/// Data of a `TimedMessageDue` event, asking the bot to post the message
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimedMessageDuePayload {
+    pub id: i64,
+    pub guild_id: String,
+    pub channel_id: String,
+    pub message: String,
+    /// When the message was scheduled, it may be sent a little later
+    pub scheduled_for: String,
+}
+
+impl From<&TimedMessagesSchema> for TimedMessageDuePayload {
+    fn from(message: &TimedMessagesSchema) -> Self {
+        TimedMessageDuePayload {
+            id: message.id,
+            guild_id: message.guild_id.clone(),
+            channel_id: message.channel_id.clone(),
+            message: message.message.clone(),
+            scheduled_for: message.next_run_at.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// When a timed message repeats
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Validate the `interval`/`cron` pair of a new message, exactly one of them must be set
+    pub fn new(interval: Option<i64>, cron: Option<&str>) -> Result<Self, String> {
+        match (interval, cron) {
+            (Some(seconds), None) if seconds >= MIN_INTERVAL_SECONDS => {
+                Ok(Schedule::Interval(Duration::seconds(seconds)))
+            }
+            (Some(_), None) => Err(format!(
+                "Interval must be at least {} seconds",
+                MIN_INTERVAL_SECONDS
+            )),
+            (None, Some(cron)) => CronSchedule::parse(cron).map(Schedule::Cron),
+            _ => Err("Either an interval or a cron expression is required".to_string()),
+        }
+    }
+
+    pub fn of(message: &TimedMessagesSchema) -> Result<Self, String> {
+        match &message.cron {
+            Some(cron) => Schedule::new(None, Some(cron)),
+            None => Schedule::new(Some(message.interval), None),
+        }
+    }
+
+    /// First run after `previous` that is still in the future, runs missed in between are skipped
+    pub fn next_run(
+        &self,
+        previous: DateTime<Utc>,
+        now: DateTime<Utc>,
+        timezone: Tz,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval(interval) => {
+                let mut next = previous + *interval;
+                if next <= now {
+                    // Stay on the original cadence instead of drifting to `now`
+                    let missed = (now - previous).num_seconds() / interval.num_seconds();
+                    next = previous + *interval * (missed as i32 + 1);
+                }
+                Some(next)
+            }
+            Schedule::Cron(cron) => cron.next_after(previous.max(now), timezone),
+        }
+    }
+}
+
+/// The guild's `guild_timezones` zone, UTC when unset or unknown
+pub async fn guild_timezone(database: &Database, guild_id: &str) -> DbResult<Tz> {
+    #[derive(Deserialize)]
+    struct Timezone {
+        timezone: String,
+    }
+
+    let timezones: Vec<Timezone> = database.execute(TimezoneSchema::get(guild_id)).await?;
+    let Some(timezone) = timezones.into_iter().next() else {
+        return Ok(Tz::UTC);
+    };
+    Ok(timezone.timezone.parse().unwrap_or_else(|_| {
+        warn!(
+            "Unknown timezone {} for guild {}, using UTC",
+            timezone.timezone, guild_id
+        );
+        Tz::UTC
+    }))
+}
+
+pub fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    (DateTime::parse_from_rfc3339(time).ok()).map(|time| time.with_timezone(&Utc))
+}
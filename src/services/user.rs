@@ -56,13 +56,13 @@ impl DiscordUserApi {
             .get(&url)
             .send()
             .await
-            .map_err(|e| panic!("Failed to send request to Discord API: {}", e))?;
+            .map_err(|e| -> Error { panic!("Failed to send request to Discord API: {}", e) })?;
 
         if response.status().is_success() {
             let user: DiscordUser = response
                 .json()
                 .await
-                .map_err(|e| panic!("Failed to parse user data: {}", e))?;
+                .map_err(|e| -> Error { panic!("Failed to parse user data: {}", e) })?;
             Ok(user)
         } else {
             panic!("Failed to fetch user data: {}", response.status())
@@ -1,10 +1,20 @@
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::Value;
+use bincode::Options;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Number, Value};
+
+/// Largest binary frame accepted, the size limit of websocket messages on Workers
+const MAX_FRAME_BYTES: u64 = 1 << 20;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WsEnvelope<T> {
     pub event: T,
     pub data: Value,
+    /// Sequence number of gateway events sent to the bot, see `Resume`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
+    /// Set by the bot on a `Request` and echoed back on its `Response`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
 }
 
 impl<T> WsEnvelope<T> {
@@ -13,7 +23,12 @@ impl<T> WsEnvelope<T> {
         U: Serialize,
     {
         let data = serde_json::to_value(data).unwrap_or(Value::Null);
-        Self { event, data }
+        Self {
+            event,
+            data,
+            seq: None,
+            nonce: None,
+        }
     }
 
     pub fn data_as<U>(&self) -> Option<U>
@@ -23,3 +38,233 @@ impl<T> WsEnvelope<T> {
         serde_json::from_value(self.data.clone()).ok()
     }
 }
+
+impl<T: Serialize> WsEnvelope<T> {
+    /// Encode as a binary frame, events are written by variant index
+    pub fn to_bincode(&self) -> bincode::Result<Vec<u8>> {
+        let frame = BinaryFrame {
+            event: &self.event,
+            data: WireValue::from(&self.data),
+            seq: self.seq,
+            nonce: self.nonce.clone(),
+        };
+        frame_options().serialize(&frame)
+    }
+}
+
+impl<T: DeserializeOwned> WsEnvelope<T> {
+    /// Decode a binary frame written by [`WsEnvelope::to_bincode`]
+    pub fn from_bincode(bytes: &[u8]) -> bincode::Result<Self> {
+        // The limit of the options doesn't count every byte read, check the whole frame
+        if bytes.len() as u64 > MAX_FRAME_BYTES {
+            return Err(Box::new(bincode::ErrorKind::SizeLimit));
+        }
+        let frame: BinaryFrame<T> = frame_options().deserialize(bytes)?;
+        Ok(Self {
+            event: frame.event,
+            data: frame.data.into(),
+            seq: frame.seq,
+            nonce: frame.nonce,
+        })
+    }
+}
+
+fn frame_options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(MAX_FRAME_BYTES)
+}
+
+/// Layout of a binary frame, bincode is positional so every field is always written
+#[derive(Serialize, Deserialize)]
+struct BinaryFrame<E> {
+    event: E,
+    data: WireValue,
+    seq: Option<i64>,
+    nonce: Option<String>,
+}
+
+/// `serde_json::Value` in a form bincode can read back, it can't decode self-describing types
+#[derive(Serialize, Deserialize)]
+enum WireValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<WireValue>),
+    Object(Vec<(String, WireValue)>),
+}
+
+impl From<&Value> for WireValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => WireValue::Null,
+            Value::Bool(value) => WireValue::Bool(*value),
+            Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+                (Some(value), _) => WireValue::Int(value),
+                (None, Some(value)) => WireValue::UInt(value),
+                _ => WireValue::Float(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(value) => WireValue::String(value.clone()),
+            Value::Array(values) => WireValue::Array(values.iter().map(WireValue::from).collect()),
+            Value::Object(map) => WireValue::Object(
+                (map.iter())
+                    .map(|(key, value)| (key.clone(), WireValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<WireValue> for Value {
+    fn from(value: WireValue) -> Self {
+        match value {
+            WireValue::Null => Value::Null,
+            WireValue::Bool(value) => Value::Bool(value),
+            WireValue::Int(value) => Value::Number(value.into()),
+            WireValue::UInt(value) => Value::Number(value.into()),
+            WireValue::Float(value) => Number::from_f64(value).map_or(Value::Null, Value::Number),
+            WireValue::String(value) => Value::String(value),
+            WireValue::Array(values) => Value::Array(values.into_iter().map(Value::from).collect()),
+            WireValue::Object(entries) => Value::Object(
+                (entries.into_iter())
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::durables::bot::{SocketReceiveEvent, SocketSendEvent};
+
+    fn envelope(data: Value) -> WsEnvelope<SocketSendEvent> {
+        let mut envelope = WsEnvelope::new(SocketSendEvent::Response, data);
+        envelope.seq = Some(42);
+        envelope.nonce = Some("abc".into());
+        envelope
+    }
+
+    /// The envelope read back from a JSON frame and from a binary frame
+    fn round_trips(envelope: &WsEnvelope<SocketSendEvent>) -> [WsEnvelope<SocketSendEvent>; 2] {
+        let json = serde_json::to_string(envelope).unwrap();
+        let bytes = envelope.to_bincode().unwrap();
+        [
+            serde_json::from_str(&json).unwrap(),
+            WsEnvelope::from_bincode(&bytes).unwrap(),
+        ]
+    }
+
+    fn assert_round_trips(data: Value) {
+        let sent = envelope(data);
+        for received in round_trips(&sent) {
+            assert!(received.event == sent.event);
+            assert_eq!(received.data, sent.data);
+            assert_eq!(received.seq, sent.seq);
+            assert_eq!(received.nonce, sent.nonce);
+        }
+    }
+
+    #[test]
+    fn nested_objects_round_trip() {
+        assert_round_trips(json!({
+            "guild_id": "1",
+            "winners": ["10", "11"],
+            "giveaway": { "prize": "Nitro", "ended": true, "channel": null },
+            "rounds": [[0, 1], []],
+            "empty": {}
+        }));
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        let big = i64::MAX as u64 + 1;
+        assert_round_trips(json!([big, u64::MAX, i64::MIN, -1, 0]));
+        assert_round_trips(json!({ "ratio": 1.5, "tiny": -2.5e-300 }));
+
+        let [json, binary] = round_trips(&envelope(json!(u64::MAX)));
+        assert_eq!(json.data.as_u64(), Some(u64::MAX));
+        assert_eq!(binary.data.as_u64(), Some(u64::MAX));
+        let [json, binary] = round_trips(&envelope(json!(1.0)));
+        assert!(json.data.is_f64() && binary.data.is_f64());
+    }
+
+    #[test]
+    fn missing_nonce_and_seq_round_trip() {
+        let sent = WsEnvelope::new(SocketSendEvent::BotPong, Value::Null);
+        for received in round_trips(&sent) {
+            assert_eq!(received.seq, None);
+            assert_eq!(received.nonce, None);
+            assert_eq!(received.data, Value::Null);
+        }
+        let json = serde_json::to_value(&sent).unwrap();
+        assert_eq!(json, json!({ "event": "BotPong", "data": null }));
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let data = Value::String("x".repeat(MAX_FRAME_BYTES as usize));
+        assert!(envelope(data.clone()).to_bincode().is_err());
+
+        let frame = BinaryFrame {
+            event: SocketSendEvent::Response,
+            data: WireValue::from(&data),
+            seq: None,
+            nonce: None,
+        };
+        let bytes = bincode::DefaultOptions::new().serialize(&frame).unwrap();
+        assert!(WsEnvelope::<SocketSendEvent>::from_bincode(&bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let bytes = envelope(json!({ "a": 1 })).to_bincode().unwrap();
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(WsEnvelope::<SocketSendEvent>::from_bincode(truncated).is_err());
+    }
+
+    /// Binary frames start with the event's variant index, which the bot decodes by position
+    fn send_index(event: SocketSendEvent) -> u8 {
+        WsEnvelope::new(event, Value::Null).to_bincode().unwrap()[0]
+    }
+
+    fn receive_event(index: u8) -> SocketReceiveEvent {
+        // Index, `WireValue::Null` then no seq and no nonce
+        WsEnvelope::from_bincode(&[index, 0, 0, 0]).unwrap().event
+    }
+
+    #[test]
+    fn send_event_indices_are_pinned() {
+        use SocketSendEvent::*;
+        let events = [
+            Ready,
+            BotPong,
+            ReminderDue,
+            GiveawayEnded,
+            TimedMessageDue,
+            BirthdayAnnouncement,
+            ConfigUpdated,
+            HeartbeatAck,
+            Resumed,
+            InvalidSession,
+            Response,
+        ];
+        for (index, event) in events.into_iter().enumerate() {
+            assert_eq!(send_index(event), index as u8);
+        }
+    }
+
+    #[test]
+    fn receive_event_indices_are_pinned() {
+        use SocketReceiveEvent::*;
+        let events = [BotUpdate, ReminderAck, Heartbeat, Resume, Request];
+        for (index, event) in events.into_iter().enumerate() {
+            assert!(receive_event(index as u8) == event, "index {}", index);
+        }
+        assert!(WsEnvelope::<SocketReceiveEvent>::from_bincode(&[5, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+use sea_query::Value;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use worker::{
+    D1Database, D1PreparedStatement, Env,
+    js_sys::{Array, Reflect, Uint8Array},
+    send::SendWrapper,
+    worker_sys::D1Database as D1DatabaseSys,
+};
+
+use super::{QueryMeta, QueryOutput, Statement, StorageBackend};
+use crate::state::database::{DbError, DbResult};
+
+/// Cloudflare D1, looked up from the worker [`Env`] on every call
+#[derive(Debug, Clone)]
+pub struct D1Backend {
+    env: SendWrapper<Env>,
+    binding: String,
+}
+
+impl D1Backend {
+    pub fn new<T: Into<String>>(env: &Env, binding: T) -> Self {
+        Self {
+            env: SendWrapper::new(env.clone()),
+            binding: binding.into(),
+        }
+    }
+
+    fn get_db(&self) -> DbResult<D1Database> {
+        self.env.d1(&self.binding).map_err(DbError::from_worker)
+    }
+
+    fn prepare(&self, db: &D1Database, statement: &Statement) -> DbResult<D1PreparedStatement> {
+        let params = convert_params(&statement.params)?;
+        (db.prepare(&statement.sql).bind(&params)).map_err(|e| DbError::Binding(e.to_string()))
+    }
+}
+
+// Statements are run through the raw JS bindings because `worker::D1Result` doesn't
+// expose the whole `meta` object, `served_by` in particular
+#[async_trait(?Send)]
+impl StorageBackend for D1Backend {
+    async fn run(&self, statement: &Statement) -> DbResult<QueryOutput> {
+        let db = self.get_db()?;
+        let prepared = self.prepare(&db, statement)?;
+        let promise = prepared.inner().run().map_err(js_error)?;
+        let result = JsFuture::from(promise).await.map_err(js_error)?;
+        output(&result)
+    }
+
+    async fn batch(&self, statements: &[Statement]) -> DbResult<Vec<QueryOutput>> {
+        let db = self.get_db()?;
+        let prepared = (statements.iter())
+            .map(|statement| self.prepare(&db, statement))
+            .collect::<DbResult<Vec<_>>>()?;
+
+        let array: Array = (prepared.iter())
+            .map(|statement| JsValue::from(statement.inner().clone()))
+            .collect();
+        let promise =
+            (db.as_ref().unchecked_ref::<D1DatabaseSys>().batch(array)).map_err(js_error)?;
+        let results = JsFuture::from(promise).await.map_err(js_error)?;
+        (results.unchecked_into::<Array>().iter())
+            .map(|result| output(&result))
+            .collect()
+    }
+}
+
+fn js_error(error: JsValue) -> DbError {
+    DbError::from_worker(worker::Error::from(error))
+}
+
+/// Read the rows and meta of a D1 result as raw JSON, since `D1Result::results` panics
+/// when a row doesn't match the requested type
+fn output(result: &JsValue) -> DbResult<QueryOutput> {
+    let field = |name: &str| {
+        (Reflect::get(result, &JsValue::from_str(name)))
+            .map_err(|_| DbError::Deserialization(format!("D1 result has no `{}`", name)))
+    };
+    let rows: Option<Vec<serde_json::Value>> = serde_wasm_bindgen::from_value(field("results")?)
+        .map_err(|e| DbError::Deserialization(e.to_string()))?;
+    let meta: Option<QueryMeta> = serde_wasm_bindgen::from_value(field("meta")?)
+        .map_err(|e| DbError::Deserialization(e.to_string()))?;
+
+    Ok(QueryOutput {
+        rows: rows.unwrap_or_default(),
+        meta: meta.unwrap_or_default(),
+    })
+}
+
+/// Largest integer a JS number holds exactly, `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+fn convert_params(values: &[Value]) -> DbResult<Vec<JsValue>> {
+    (values.iter())
+        .map(|value| Param::classify(value).map(Param::into_js))
+        .collect()
+}
+
+/// A parameter as D1 will receive it, decided without touching JS so it can be tested natively
+#[derive(Debug, Clone, PartialEq)]
+enum Param<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(Cow<'a, str>),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Param<'a> {
+    fn classify(value: &'a Value) -> DbResult<Self> {
+        // NULL of any type
+        if *value == value.as_null() {
+            return Ok(Param::Null);
+        }
+
+        let param = match value {
+            Value::Bool(Some(b)) => Param::Bool(*b),
+            Value::Char(Some(c)) => Param::Text(c.to_string().into()),
+            Value::String(Some(s)) => Param::Text(s.as_str().into()),
+            // Signed number types
+            Value::TinyInt(Some(i)) => Param::Number(*i as f64),
+            Value::SmallInt(Some(i)) => Param::Number(*i as f64),
+            Value::Int(Some(i)) => Param::Number(*i as f64),
+            Value::BigInt(Some(i)) => Param::integer(*i as i128),
+            // Unsigned number types
+            Value::TinyUnsigned(Some(u)) => Param::Number(*u as f64),
+            Value::SmallUnsigned(Some(u)) => Param::Number(*u as f64),
+            Value::Unsigned(Some(u)) => Param::Number(*u as f64),
+            Value::BigUnsigned(Some(u)) => Param::integer(*u as i128),
+            // Float types
+            Value::Float(Some(f)) => Param::Number(*f as f64),
+            Value::Double(Some(f)) => Param::Number(*f),
+            Value::Bytes(Some(b)) => Param::Bytes(b.as_slice()),
+            Value::Json(Some(json)) => Param::Text(json.to_string().into()),
+            value => {
+                return Err(DbError::Binding(format!(
+                    "Unsupported parameter type: {:?}",
+                    value
+                )));
+            }
+        };
+        Ok(param)
+    }
+
+    /// A 64-bit integer as a JS number when that is exact, or as its decimal text otherwise
+    ///
+    /// D1 refuses `BigInt` parameters, and snowflakes or permission bitfields above 2^53 would be
+    /// rounded by a number. SQLite stores the text as is in `TEXT` columns and converts it back to
+    /// an exact `INTEGER` in `INTEGER` columns and comparisons with them.
+    fn integer(i: i128) -> Self {
+        if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&i) {
+            Param::Number(i as f64)
+        } else {
+            Param::Text(i.to_string().into())
+        }
+    }
+
+    fn into_js(self) -> JsValue {
+        match self {
+            Param::Null => JsValue::NULL,
+            Param::Bool(b) => JsValue::from_bool(b),
+            Param::Number(n) => JsValue::from_f64(n),
+            Param::Text(s) => JsValue::from_str(&s),
+            Param::Bytes(b) => Uint8Array::from(b).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_param(value: impl Into<Value>, expected: Param) {
+        assert_eq!(Param::classify(&value.into()).unwrap(), expected);
+    }
+
+    const TWO_POW_53: i64 = 1 << 53;
+
+    #[test]
+    fn safe_integers_are_numbers() {
+        assert_param(0i64, Param::Number(0.0));
+        assert_param(TWO_POW_53 - 1, Param::Number((TWO_POW_53 - 1) as f64));
+        assert_param(-(TWO_POW_53 - 1), Param::Number(-(TWO_POW_53 - 1) as f64));
+    }
+
+    #[test]
+    fn integers_past_two_pow_53_are_text() {
+        assert_param(TWO_POW_53, Param::Text("9007199254740992".into()));
+        assert_param(-TWO_POW_53, Param::Text("-9007199254740992".into()));
+        assert_param(TWO_POW_53 + 1, Param::Text("9007199254740993".into()));
+        assert_param(-(TWO_POW_53 + 1), Param::Text("-9007199254740993".into()));
+    }
+
+    #[test]
+    fn extreme_integers_are_text() {
+        assert_param(u64::MAX, Param::Text("18446744073709551615".into()));
+        assert_param(i64::MIN, Param::Text("-9223372036854775808".into()));
+        assert_param(u32::MAX, Param::Number(u32::MAX as f64));
+    }
+
+    #[test]
+    fn nulls_of_any_type() {
+        assert_param(None::<i64>, Param::Null);
+        assert_param(None::<String>, Param::Null);
+        assert_param(Value::Bytes(None), Param::Null);
+    }
+
+    #[test]
+    fn other_types() {
+        assert_param(true, Param::Bool(true));
+        assert_param('a', Param::Text("a".into()));
+        assert_param("text", Param::Text("text".into()));
+        assert_param(1.5f64, Param::Number(1.5));
+        assert_param(vec![1u8, 2], Param::Bytes(&[1, 2]));
+        assert_param(
+            serde_json::json!({ "a": 1 }),
+            Param::Text(r#"{"a":1}"#.into()),
+        );
+    }
+
+    #[test]
+    fn unsupported_types_are_binding_errors() {
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let value = Value::from(date);
+        assert!(matches!(Param::classify(&value), Err(DbError::Binding(_))));
+    }
+}
@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use sea_query::{QueryStatementWriter, SqliteQueryBuilder, Value};
+use serde::Deserialize;
+
+use super::DbResult;
+
+mod d1;
+#[cfg(not(target_arch = "wasm32"))]
+mod sqlite;
+
+pub use d1::D1Backend;
+#[cfg(not(target_arch = "wasm32"))]
+pub use sqlite::SqliteBackend;
+
+/// SQL text and its positional parameters, as handed to a [`StorageBackend`]
+#[derive(Debug, Clone, Default)]
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+impl Statement {
+    /// A statement without parameters, e.g. from a migration file
+    pub fn raw(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            params: Vec::new(),
+        }
+    }
+
+    pub fn build<Q: QueryStatementWriter>(query: Q) -> Self {
+        let (sql, params) = query.build(SqliteQueryBuilder);
+        Self {
+            sql,
+            params: params.0,
+        }
+    }
+}
+
+/// Execution statistics reported by the backend for one statement
+///
+/// Mirrors the `meta` object of a D1 result, every field is optional since
+/// other backends only know some of them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QueryMeta {
+    /// Rows scanned, what D1 bills read units on
+    pub rows_read: Option<u64>,
+    pub rows_written: Option<u64>,
+    /// Time spent executing the SQL inside the database, in milliseconds
+    pub duration: Option<f64>,
+    /// Which database instance answered, e.g. a read replica
+    pub served_by: Option<String>,
+}
+
+impl QueryMeta {
+    /// Totals of several statements, e.g. of a batch
+    pub fn sum<'a>(metas: impl IntoIterator<Item = &'a QueryMeta>) -> Self {
+        let add = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        (metas.into_iter()).fold(Self::default(), |total, meta| Self {
+            rows_read: add(total.rows_read, meta.rows_read),
+            rows_written: add(total.rows_written, meta.rows_written),
+            duration: match (total.duration, meta.duration) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+            },
+            served_by: total.served_by.or_else(|| meta.served_by.clone()),
+        })
+    }
+}
+
+/// Rows of a statement together with its [`QueryMeta`]
+#[derive(Debug, Clone, Default)]
+pub struct QueryOutput {
+    pub rows: Vec<serde_json::Value>,
+    pub meta: QueryMeta,
+}
+
+/// Storage engine executing the SQL built by [`super::Database`]
+///
+/// Rows are returned as JSON objects keyed by column name, the same shape D1 produces,
+/// so every backend deserializes into the same `*Schema` structs.
+#[async_trait(?Send)]
+pub trait StorageBackend: std::fmt::Debug + Send + Sync {
+    /// Run a single statement and return its rows
+    async fn run(&self, statement: &Statement) -> DbResult<QueryOutput>;
+
+    /// Run every statement atomically, returning the rows of each statement in order
+    async fn batch(&self, statements: &[Statement]) -> DbResult<Vec<QueryOutput>>;
+}
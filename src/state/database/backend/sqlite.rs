@@ -0,0 +1,190 @@
+use std::{path::Path, sync::Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{
+    Connection, params_from_iter,
+    types::{Value as SqliteValue, ValueRef},
+};
+use sea_query::Value;
+
+use super::{QueryMeta, QueryOutput, Statement, StorageBackend};
+use crate::state::database::{Database, DbError, DbResult};
+
+/// Native SQLite, used to run the real queries outside of a Workers runtime (e.g. in integration tests)
+///
+/// ```ignore
+/// let database = SqliteBackend::migrated().await?;
+/// database.execute(GuildSchema::insert("1234", 0)).await?;
+/// ```
+#[derive(Debug)]
+pub struct SqliteBackend {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// An empty in-memory database with foreign keys enforced, like D1
+    pub fn open_in_memory() -> DbResult<Self> {
+        let connection = Connection::open_in_memory().map_err(sqlite_error)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(sqlite_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// An in-memory [`Database`] with every migration of this build applied, like a deployed D1
+    pub async fn migrated() -> DbResult<Database> {
+        let database = Database::with_backend(Self::open_in_memory()?);
+        database.apply_migrations().await?;
+        Ok(database)
+    }
+
+    /// An in-memory database built from `schemas/*.sql`, in file order like `scripts/build-db.sh`
+    ///
+    /// The schema files are the frozen baseline, later changes are only in `migrations/`, so
+    /// this is the state of a database from before versioned migrations.
+    pub fn with_schemas() -> DbResult<Self> {
+        let backend = Self::open_in_memory()?;
+        backend.apply_schema_dir(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/schemas")))?;
+        Ok(backend)
+    }
+
+    /// Execute every `.sql` file of a directory, sorted by name
+    pub fn apply_schema_dir(&self, dir: &Path) -> DbResult<()> {
+        let mut files = (std::fs::read_dir(dir)
+            .map_err(|e| DbError::Unavailable(e.to_string()))?)
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .filter(|path| !path.ends_with("schema_combined.sql"))
+        .collect::<Vec<_>>();
+        files.sort();
+
+        for file in files {
+            let sql =
+                std::fs::read_to_string(&file).map_err(|e| DbError::Unavailable(e.to_string()))?;
+            self.execute_script(&sql)
+                .map_err(|e| DbError::Query(format!("{}: {}", file.display(), e.message())))?;
+        }
+        Ok(())
+    }
+
+    /// Execute several `;` separated statements without parameters
+    pub fn execute_script(&self, sql: &str) -> DbResult<()> {
+        self.lock()?.execute_batch(sql).map_err(sqlite_error)
+    }
+
+    fn lock(&self) -> DbResult<std::sync::MutexGuard<'_, Connection>> {
+        (self.connection.lock()).map_err(|e| DbError::Unavailable(e.to_string()))
+    }
+}
+
+#[async_trait(?Send)]
+impl StorageBackend for SqliteBackend {
+    async fn run(&self, statement: &Statement) -> DbResult<QueryOutput> {
+        let connection = self.lock()?;
+        query(&connection, statement)
+    }
+
+    async fn batch(&self, statements: &[Statement]) -> DbResult<Vec<QueryOutput>> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+
+        let mut results = Vec::with_capacity(statements.len());
+        for statement in statements {
+            results.push(query(&transaction, statement)?);
+        }
+
+        transaction.commit().map_err(sqlite_error)?;
+        Ok(results)
+    }
+}
+
+fn query(connection: &Connection, statement: &Statement) -> DbResult<QueryOutput> {
+    let mut prepared = connection.prepare(&statement.sql).map_err(sqlite_error)?;
+    let readonly = prepared.readonly();
+    let columns: Vec<String> = (prepared.column_names().into_iter())
+        .map(String::from)
+        .collect();
+    let params = (statement.params.iter())
+        .map(convert_param)
+        .collect::<DbResult<Vec<_>>>()?;
+
+    let mut rows = prepared
+        .query(params_from_iter(params))
+        .map_err(sqlite_error)?;
+    let mut output = Vec::new();
+    while let Some(row) = rows.next().map_err(sqlite_error)? {
+        let mut object = serde_json::Map::with_capacity(columns.len());
+        for (index, column) in columns.iter().enumerate() {
+            let value = row.get_ref(index).map_err(sqlite_error)?;
+            object.insert(column.clone(), convert_value(value));
+        }
+        output.push(serde_json::Value::Object(object));
+    }
+
+    Ok(QueryOutput {
+        rows: output,
+        meta: QueryMeta {
+            rows_written: (!readonly).then(|| connection.changes()),
+            ..QueryMeta::default()
+        },
+    })
+}
+
+/// SQLite reports constraint failures with the same messages as D1, so they classify the same way
+fn sqlite_error(error: rusqlite::Error) -> DbError {
+    DbError::from_message(error.to_string())
+}
+
+/// Same conversions as the D1 backend, with 64-bit integers bound natively
+fn convert_param(value: &Value) -> DbResult<SqliteValue> {
+    // NULL of any type
+    if *value == value.as_null() {
+        return Ok(SqliteValue::Null);
+    }
+
+    let value = match value.clone() {
+        Value::Bool(Some(b)) => SqliteValue::Integer(b as i64),
+        Value::Char(Some(c)) => SqliteValue::Text(c.to_string()),
+        Value::String(Some(s)) => SqliteValue::Text(*s),
+        // Signed number types
+        Value::TinyInt(Some(i)) => SqliteValue::Integer(i as i64),
+        Value::SmallInt(Some(i)) => SqliteValue::Integer(i as i64),
+        Value::Int(Some(i)) => SqliteValue::Integer(i as i64),
+        Value::BigInt(Some(i)) => SqliteValue::Integer(i),
+        // Unsigned number types
+        Value::TinyUnsigned(Some(u)) => SqliteValue::Integer(u as i64),
+        Value::SmallUnsigned(Some(u)) => SqliteValue::Integer(u as i64),
+        Value::Unsigned(Some(u)) => SqliteValue::Integer(u as i64),
+        Value::BigUnsigned(Some(u)) => match i64::try_from(u) {
+            Ok(i) => SqliteValue::Integer(i),
+            Err(_) => SqliteValue::Text(u.to_string()),
+        },
+        // Float types
+        Value::Float(Some(f)) => SqliteValue::Real(f as f64),
+        Value::Double(Some(f)) => SqliteValue::Real(f),
+        Value::Bytes(Some(b)) => SqliteValue::Blob(*b),
+        Value::Json(Some(json)) => SqliteValue::Text(json.to_string()),
+        value => {
+            return Err(DbError::Binding(format!(
+                "Unsupported parameter type: {:?}",
+                value
+            )));
+        }
+    };
+    Ok(value)
+}
+
+/// Convert a column to the JSON D1 would have returned for it
+fn convert_value(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+        ValueRef::Blob(b) => b.to_vec().into(),
+    }
+}
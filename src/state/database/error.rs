@@ -0,0 +1,166 @@
+use axum::response::{IntoResponse, Response};
+use reqwest::StatusCode;
+
+pub type DbResult<T> = Result<T, DbError>;
+
+/// Errors surfaced by [`super::Database`], classified from the messages D1 returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbError {
+    /// A `UNIQUE` or `PRIMARY KEY` constraint rejected the row
+    UniqueViolation(String),
+    /// A `FOREIGN KEY` constraint failed.
+    /// `referenced` is true when a delete was blocked by rows still pointing at the target,
+    /// false when an insert/update pointed at a parent row that does not exist
+    ForeignKeyViolation { message: String, referenced: bool },
+    /// A `CHECK` or `NOT NULL` constraint rejected the value
+    CheckViolation(String),
+    /// The row returned by D1 could not be deserialized into the requested type
+    Deserialization(String),
+    /// The parameters could not be bound to the prepared statement
+    Binding(String),
+    /// The D1 binding is missing or the database could not be reached
+    Unavailable(String),
+    /// The applied migrations disagree with the ones compiled into the worker
+    Migration(String),
+    /// Any other query failure
+    Query(String),
+}
+
+impl DbError {
+    /// Classify an error returned by the worker D1 bindings
+    pub fn from_worker(error: worker::Error) -> Self {
+        match error {
+            worker::Error::BindingError(binding) => {
+                DbError::Unavailable(format!("No D1 binding found for `{}`", binding))
+            }
+            error => Self::from_message(error.to_string()),
+        }
+    }
+
+    /// Classify a raw D1/SQLite error message
+    ///
+    /// D1 reports errors as e.g. `D1_ERROR: UNIQUE constraint failed: guild_prefixes.guild_id: SQLITE_CONSTRAINT`
+    pub fn from_message(message: impl Into<String>) -> Self {
+        let message = message.into();
+        if message.contains("UNIQUE constraint failed")
+            || message.contains("PRIMARY KEY constraint failed")
+        {
+            DbError::UniqueViolation(message)
+        } else if message.contains("FOREIGN KEY constraint failed") {
+            DbError::ForeignKeyViolation {
+                message,
+                referenced: false,
+            }
+        } else if message.contains("CHECK constraint failed")
+            || message.contains("NOT NULL constraint failed")
+        {
+            DbError::CheckViolation(message)
+        } else if UNAVAILABLE_MESSAGES
+            .iter()
+            .any(|m| message.to_lowercase().contains(m))
+        {
+            DbError::Unavailable(message)
+        } else {
+            DbError::Query(message)
+        }
+    }
+
+    /// Mark a foreign key violation as coming from a delete, meaning the row is still referenced
+    pub fn during_delete(self) -> Self {
+        match self {
+            DbError::ForeignKeyViolation { message, .. } => DbError::ForeignKeyViolation {
+                message,
+                referenced: true,
+            },
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DbError::UniqueViolation(_) => StatusCode::CONFLICT,
+            DbError::ForeignKeyViolation {
+                referenced: false, ..
+            } => StatusCode::NOT_FOUND,
+            DbError::ForeignKeyViolation {
+                referenced: true, ..
+            } => StatusCode::CONFLICT,
+            DbError::CheckViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            DbError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            DbError::Migration(_) => StatusCode::CONFLICT,
+            DbError::Deserialization(_) | DbError::Binding(_) | DbError::Query(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            DbError::UniqueViolation(m)
+            | DbError::ForeignKeyViolation { message: m, .. }
+            | DbError::CheckViolation(m)
+            | DbError::Deserialization(m)
+            | DbError::Binding(m)
+            | DbError::Unavailable(m)
+            | DbError::Migration(m)
+            | DbError::Query(m) => m,
+        }
+    }
+}
+
+/// Lowercased fragments of the messages D1 uses when the database itself is unreachable
+const UNAVAILABLE_MESSAGES: [&str; 5] = [
+    "network connection lost",
+    "overloaded",
+    "storage caused object to be reset",
+    "d1 db reset",
+    "internal error",
+];
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            DbError::UniqueViolation(_) => "Unique constraint violation",
+            DbError::ForeignKeyViolation {
+                referenced: false, ..
+            } => "Referenced row does not exist",
+            DbError::ForeignKeyViolation {
+                referenced: true, ..
+            } => "Row is still referenced",
+            DbError::CheckViolation(_) => "Value rejected by constraint",
+            DbError::Deserialization(_) => "Failed to deserialize row",
+            DbError::Binding(_) => "Failed to bind query parameters",
+            DbError::Unavailable(_) => "Database unavailable",
+            DbError::Migration(_) => "Migration failed",
+            DbError::Query(_) => "Query failed",
+        };
+        write!(f, "{}: {}", kind, self.message())
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<worker::Error> for DbError {
+    fn from(error: worker::Error) -> Self {
+        DbError::from_worker(error)
+    }
+}
+
+/// Lets durable objects, which speak `worker::Result`, query with `?`
+impl From<DbError> for worker::Error {
+    fn from(error: DbError) -> Self {
+        worker::Error::RustError(error.to_string())
+    }
+}
+
+impl From<DbError> for (StatusCode, String) {
+    fn from(error: DbError) -> Self {
+        (error.status(), error.to_string())
+    }
+}
+
+impl IntoResponse for DbError {
+    fn into_response(self) -> Response {
+        <(StatusCode, String)>::from(self).into_response()
+    }
+}
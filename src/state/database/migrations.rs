@@ -0,0 +1,389 @@
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::schema::{SCHEMA_VERSION, SchemaMigrationSchema};
+
+use super::{Database, DatabaseExt, DbError, DbResult, Statement};
+
+/// A forward-only schema change stored in `migrations/`
+///
+/// Migrations are never edited once deployed, their checksum is recorded in `schema_migrations`
+/// and any drift is refused. Changes to the schema always go in a new, higher numbered file.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            sql: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/migrations/",
+                $name,
+                ".sql"
+            )),
+        }
+    };
+}
+
+/// Every migration known to this build, in the order they must be applied
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_baseline"),
+    migration!(2, "0002_soft_delete"),
+    migration!(3, "0003_giveaway_winners"),
+    migration!(4, "0004_timed_message_schedules"),
+    migration!(5, "0005_birthday_announcements"),
+    migration!(6, "0006_user_sessions"),
+];
+
+const _: () = {
+    let mut i = 1;
+    while i < MIGRATIONS.len() {
+        assert!(
+            MIGRATIONS[i - 1].version < MIGRATIONS[i].version,
+            "Migrations must be listed in strictly ascending version order"
+        );
+        i += 1;
+    }
+    assert!(
+        MIGRATIONS[MIGRATIONS.len() - 1].version == SCHEMA_VERSION,
+        "The latest migration must match schema::SCHEMA_VERSION"
+    );
+};
+
+impl Migration {
+    /// FNV-1a hash of the migration file, hex encoded
+    pub fn checksum(&self) -> String {
+        let hash = (self.sql.bytes()).fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// Split the migration file into individual statements for a D1 batch
+    ///
+    /// Comments are dropped, and semicolons inside quotes or `BEGIN ... END` / `CASE ... END`
+    /// blocks (trigger bodies) don't end a statement.
+    pub fn statements(&self) -> Vec<String> {
+        let mut statements = Vec::new();
+        let mut current = String::new();
+        let mut word = String::new();
+        let mut depth = 0usize;
+        let mut chars = self.sql.chars().peekable();
+
+        let end_word = |word: &mut String, depth: &mut usize| {
+            match word.to_ascii_uppercase().as_str() {
+                "BEGIN" | "CASE" => *depth += 1,
+                "END" => *depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            word.clear();
+        };
+
+        while let Some(c) = chars.next() {
+            match c {
+                '-' if chars.peek() == Some(&'-') => {
+                    end_word(&mut word, &mut depth);
+                    while chars.next_if(|c| *c != '\n').is_some() {}
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    end_word(&mut word, &mut depth);
+                    chars.next();
+                    let mut last = ' ';
+                    for c in chars.by_ref() {
+                        if last == '*' && c == '/' {
+                            break;
+                        }
+                        last = c;
+                    }
+                }
+                '\'' | '"' | '`' => {
+                    end_word(&mut word, &mut depth);
+                    current.push(c);
+                    while let Some(next) = chars.next() {
+                        current.push(next);
+                        if next == c {
+                            // A doubled quote is an escaped quote
+                            if chars.peek() == Some(&c) {
+                                current.extend(chars.next());
+                                continue;
+                            }
+                            break;
+                        }
+                    }
+                }
+                ';' => {
+                    end_word(&mut word, &mut depth);
+                    current.push(c);
+                    if depth == 0 {
+                        let statement = current.trim();
+                        if statement != ";" {
+                            statements.push(statement.to_string());
+                        }
+                        current.clear();
+                    }
+                }
+                c if c.is_alphanumeric() || c == '_' => {
+                    word.push(c);
+                    current.push(c);
+                }
+                c => {
+                    end_word(&mut word, &mut depth);
+                    current.push(c);
+                }
+            }
+        }
+
+        let rest = current.trim();
+        if !rest.is_empty() {
+            statements.push(rest.to_string());
+        }
+        statements
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    /// Highest migration version applied to the database
+    pub current_version: u32,
+    /// Version the `Iden` enums of this build are written against
+    pub expected_version: u32,
+    pub applied: Vec<SchemaMigrationSchema>,
+    pub pending: Vec<PendingMigration>,
+    /// Applied migrations whose file changed since they were applied
+    pub modified: Vec<u32>,
+    /// Applied migrations this build doesn't know about, usually from a newer deployment
+    pub unknown: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+}
+
+impl MigrationStatus {
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty() && self.modified.is_empty() && self.unknown.is_empty()
+    }
+
+    fn from_applied(applied: Vec<SchemaMigrationSchema>) -> Self {
+        let mut pending = Vec::new();
+        let mut modified = Vec::new();
+
+        for migration in MIGRATIONS {
+            match applied.iter().find(|a| a.version == migration.version) {
+                Some(record) if record.checksum != migration.checksum() => {
+                    modified.push(migration.version)
+                }
+                Some(_) => {}
+                None => pending.push(PendingMigration {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    checksum: migration.checksum(),
+                }),
+            }
+        }
+
+        let unknown = (applied.iter())
+            .filter(|a| !MIGRATIONS.iter().any(|m| m.version == a.version))
+            .map(|a| a.version)
+            .collect();
+
+        Self {
+            current_version: applied.iter().map(|a| a.version).max().unwrap_or(0),
+            expected_version: SCHEMA_VERSION,
+            applied,
+            pending,
+            modified,
+            unknown,
+        }
+    }
+}
+
+impl Database {
+    async fn ensure_migrations_table(&self) -> DbResult<()> {
+        self.run(Statement::raw(SchemaMigrationSchema::CREATE_TABLE))
+            .await?;
+        Ok(())
+    }
+
+    /// Compare the migrations recorded in the database with the ones compiled into this build
+    ///
+    /// Only reads, a database without `schema_migrations` has no migrations applied yet.
+    pub async fn migration_status(&self) -> DbResult<MigrationStatus> {
+        let applied: Vec<SchemaMigrationSchema> =
+            match self.execute(SchemaMigrationSchema::get_all()).await {
+                Ok(applied) => applied,
+                Err(e) if e.message().contains("no such table") => Vec::new(),
+                Err(e) => return Err(e),
+            };
+        Ok(MigrationStatus::from_applied(applied))
+    }
+
+    /// Apply every pending migration in order, each one atomically in its own batch
+    ///
+    /// The migration is recorded in the same batch as its statements, so a failure leaves
+    /// no partial schema behind, and two concurrent runs can't both apply the same version.
+    pub async fn apply_migrations(&self) -> DbResult<MigrationStatus> {
+        self.ensure_migrations_table().await?;
+        let status = self.migration_status().await?;
+        if !status.modified.is_empty() {
+            return Err(DbError::Migration(format!(
+                "Applied migrations {:?} were modified after being applied",
+                status.modified
+            )));
+        }
+        if !status.unknown.is_empty() {
+            return Err(DbError::Migration(format!(
+                "Database has migrations {:?} unknown to this build",
+                status.unknown
+            )));
+        }
+
+        for pending in status.pending.iter() {
+            let Some(migration) = MIGRATIONS.iter().find(|m| m.version == pending.version) else {
+                continue;
+            };
+            info!("Applying migration {}", migration.name);
+
+            let mut statements: Vec<_> = (migration.statements().into_iter())
+                .map(Statement::raw)
+                .collect();
+            statements.push(Statement::build(SchemaMigrationSchema::insert(
+                migration.version,
+                migration.name,
+                &pending.checksum,
+            )));
+
+            self.run_batch(statements).await.map_err(|e| {
+                error!("Failed to apply migration {}: {}", migration.name, e);
+                DbError::Migration(format!("{}: {}", migration.name, e.message()))
+            })?;
+        }
+
+        self.migration_status().await
+    }
+
+    /// Check that the D1 schema matches [`SCHEMA_VERSION`], failing with [`DbError::Migration`]
+    /// when migrations are pending, modified or unknown to this build
+    pub async fn check_schema_version(&self) -> DbResult<()> {
+        let status = self.migration_status().await?;
+        if !status.is_up_to_date() {
+            return Err(DbError::Migration(format!(
+                "Database schema is at version {} but this build expects {} (pending: {:?}, modified: {:?}, unknown: {:?})",
+                status.current_version,
+                status.expected_version,
+                (status.pending.iter())
+                    .map(|m| m.version)
+                    .collect::<Vec<_>>(),
+                status.modified,
+                status.unknown
+            )));
+        }
+        info!("Database schema is at version {}", status.current_version);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statements(sql: &'static str) -> Vec<String> {
+        Migration {
+            version: 0,
+            name: "test",
+            sql,
+        }
+        .statements()
+    }
+
+    #[test]
+    fn splits_on_semicolons() {
+        assert_eq!(
+            statements("CREATE TABLE a (id INTEGER);\nCREATE TABLE b (id INTEGER);\n"),
+            [
+                "CREATE TABLE a (id INTEGER);",
+                "CREATE TABLE b (id INTEGER);"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_trigger_bodies_together() {
+        let sql = "CREATE TRIGGER t AFTER DELETE ON a BEGIN\n    DELETE FROM b WHERE id = old.id;\n    UPDATE c SET n = n - 1;\nEND;\nSELECT 1;";
+        let split = statements(sql);
+        assert_eq!(split.len(), 2);
+        assert!(split[0].starts_with("CREATE TRIGGER") && split[0].ends_with("END;"));
+        assert_eq!(split[1], "SELECT 1;");
+    }
+
+    #[test]
+    fn case_inside_a_trigger_doesnt_end_it() {
+        let sql = "CREATE TRIGGER t AFTER UPDATE ON a BEGIN\n    UPDATE b SET state = CASE WHEN new.done THEN 'done' ELSE 'open' END;\n    DELETE FROM c;\nEND;\nSELECT 2;";
+        let split = statements(sql);
+        assert_eq!(split.len(), 2);
+        assert!(split[0].contains("DELETE FROM c;") && split[0].ends_with("END;"));
+        assert_eq!(split[1], "SELECT 2;");
+    }
+
+    #[test]
+    fn semicolons_and_doubled_quotes_inside_strings() {
+        assert_eq!(
+            statements("INSERT INTO a VALUES ('one; two', 'it''s; here');SELECT \"a;b\";"),
+            [
+                "INSERT INTO a VALUES ('one; two', 'it''s; here');",
+                "SELECT \"a;b\";"
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_dropped() {
+        let sql = "-- header; with a semicolon\nCREATE TABLE a (id INTEGER); -- trailing\n/* block;\n comment */ SELECT 1 /* inline */;\n-- only a comment";
+        assert_eq!(
+            statements(sql),
+            ["CREATE TABLE a (id INTEGER);", "SELECT 1 ;"]
+        );
+    }
+
+    #[test]
+    fn words_in_comments_dont_open_blocks() {
+        assert_eq!(
+            statements("-- BEGIN\nSELECT 1;/* CASE */SELECT 2;"),
+            ["SELECT 1;", "SELECT 2;"]
+        );
+    }
+
+    #[test]
+    fn identifiers_containing_keywords_dont_open_blocks() {
+        assert_eq!(
+            statements("CREATE TABLE a (begin_at TEXT, end_at TEXT, cases INTEGER);SELECT 1;"),
+            [
+                "CREATE TABLE a (begin_at TEXT, end_at TEXT, cases INTEGER);",
+                "SELECT 1;"
+            ]
+        );
+    }
+
+    #[test]
+    fn bundled_migrations_split_without_leftovers() {
+        for migration in MIGRATIONS {
+            for statement in migration.statements() {
+                assert!(
+                    statement.ends_with(';'),
+                    "{}: {}",
+                    migration.name,
+                    statement
+                );
+            }
+        }
+    }
+}
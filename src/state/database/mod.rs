@@ -0,0 +1,449 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sea_query::{
+    DeleteStatement, InsertStatement, QueryStatement, QueryStatementWriter, SelectStatement,
+    UpdateStatement,
+};
+use serde::de::DeserializeOwned;
+use tracing::{Instrument, Span, debug, error, field::Empty, info_span, warn};
+use worker::Env;
+
+mod backend;
+mod error;
+mod migrations;
+mod unit_of_work;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use backend::SqliteBackend;
+pub use backend::{D1Backend, QueryMeta, QueryOutput, Statement, StorageBackend};
+pub use error::{DbError, DbResult};
+pub use migrations::{MIGRATIONS, Migration, MigrationStatus, PendingMigration};
+pub use unit_of_work::{
+    BatchResults, FromBatchResults, FromStatementRows, StatementHandle, UnitOfWork,
+};
+
+/// Queries taking longer than this are logged as warnings, unless `SLOW_QUERY_THRESHOLD_MS` is set
+pub const DEFAULT_SLOW_QUERY_THRESHOLD_MS: f64 = 200.0;
+
+#[derive(Debug, Clone)]
+pub struct Database {
+    backend: Arc<dyn StorageBackend>,
+    slow_query_threshold_ms: f64,
+}
+
+#[async_trait(?Send)]
+pub trait DatabaseExt<Q, U>
+where
+    Q: QueryStatementWriter,
+    U: DeserializeOwned,
+{
+    async fn execute(&self, input: Q) -> DbResult<U>;
+    async fn batch(&self, inputs: &[Q]) -> DbResult<U>;
+}
+
+impl Database {
+    /// A database backed by the D1 `binding` of the worker environment
+    pub fn new<T: Into<String>>(env: &Env, binding: T) -> Self {
+        let threshold = (env.var("SLOW_QUERY_THRESHOLD_MS").ok())
+            .and_then(|threshold| threshold.to_string().parse().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_THRESHOLD_MS);
+        Self::with_backend(D1Backend::new(env, binding)).with_slow_query_threshold(threshold)
+    }
+
+    /// A database backed by any [`StorageBackend`], e.g. [`SqliteBackend`] outside of Workers
+    pub fn with_backend(backend: impl StorageBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            slow_query_threshold_ms: DEFAULT_SLOW_QUERY_THRESHOLD_MS,
+        }
+    }
+
+    /// Log queries (or batches) taking at least `milliseconds` as warnings
+    pub fn with_slow_query_threshold(mut self, milliseconds: f64) -> Self {
+        self.slow_query_threshold_ms = milliseconds;
+        self
+    }
+
+    async fn run(&self, statement: Statement) -> DbResult<Vec<serde_json::Value>> {
+        let span = info_span!(
+            "query",
+            sql = %statement.sql,
+            params = statement.params.len(),
+            duration_ms = Empty,
+            db_duration_ms = Empty,
+            rows_read = Empty,
+            rows_written = Empty,
+            served_by = Empty,
+        );
+        let started = Utc::now();
+        let output =
+            (self.backend.run(&statement).instrument(span.clone()).await).inspect_err(|e| {
+                span.in_scope(|| error!("Failed to execute query: {}\n{}", statement.sql, e))
+            })?;
+
+        self.record_query(&span, started, &output.meta);
+        Ok(output.rows)
+    }
+
+    async fn run_batch(&self, statements: Vec<Statement>) -> DbResult<Vec<Vec<serde_json::Value>>> {
+        let query_strings: Vec<&str> = statements.iter().map(|s| s.sql.as_str()).collect();
+        let span = info_span!(
+            "query_batch",
+            sql = ?query_strings,
+            statements = statements.len(),
+            params = statements.iter().map(|s| s.params.len()).sum::<usize>(),
+            duration_ms = Empty,
+            db_duration_ms = Empty,
+            rows_read = Empty,
+            rows_written = Empty,
+            served_by = Empty,
+        );
+        let started = Utc::now();
+        let outputs = (self
+            .backend
+            .batch(&statements)
+            .instrument(span.clone())
+            .await)
+            .inspect_err(|e| {
+                span.in_scope(|| {
+                    error!(
+                        "Failed to execute batch queries: {:?}\n{}",
+                        query_strings, e
+                    )
+                })
+            })?;
+
+        let meta = QueryMeta::sum(outputs.iter().map(|output| &output.meta));
+        self.record_query(&span, started, &meta);
+        Ok(outputs.into_iter().map(|output| output.rows).collect())
+    }
+
+    /// Fill the span of a finished query and warn when it was slower than the threshold
+    fn record_query(&self, span: &Span, started: DateTime<Utc>, meta: &QueryMeta) {
+        let elapsed = Utc::now() - started;
+        let duration_ms = (elapsed.num_microseconds()).map_or(f64::MAX, |us| us as f64 / 1000.0);
+
+        span.record("duration_ms", duration_ms);
+        span.record("db_duration_ms", meta.duration);
+        span.record("rows_read", meta.rows_read);
+        span.record("rows_written", meta.rows_written);
+        span.record("served_by", meta.served_by.as_deref());
+
+        span.in_scope(|| {
+            if duration_ms >= self.slow_query_threshold_ms {
+                warn!(
+                    threshold_ms = self.slow_query_threshold_ms,
+                    "Slow query took {:.1}ms", duration_ms
+                );
+            } else {
+                debug!("Query took {:.1}ms", duration_ms);
+            }
+        });
+    }
+
+    async fn execute_run<Q: QueryStatementWriter>(
+        &self,
+        query: Q,
+    ) -> DbResult<Vec<serde_json::Value>> {
+        self.run(Statement::build(query)).await
+    }
+
+    async fn batch_run<Q: QueryStatementWriter + Clone>(
+        &self,
+        queries: &[Q],
+    ) -> DbResult<Vec<Vec<serde_json::Value>>> {
+        let statements = queries.iter().cloned().map(Statement::build).collect();
+        self.run_batch(statements).await
+    }
+
+    fn batch_queries(queries: &[QueryStatement]) -> Vec<Statement> {
+        (queries.iter().cloned())
+            .map(|query| match query {
+                QueryStatement::Select(s) => Statement::build(s),
+                QueryStatement::Insert(i) => Statement::build(i),
+                QueryStatement::Update(u) => Statement::build(u),
+                QueryStatement::Delete(d) => Statement::build(d),
+            })
+            .collect()
+    }
+
+    /// Execute every statement of a [`UnitOfWork`] atomically in a single batch
+    ///
+    /// The returned [`BatchResults`] keeps the rows of each statement separately, in order
+    pub async fn commit(&self, work: impl Into<UnitOfWork>) -> DbResult<BatchResults> {
+        let work = work.into();
+        let rows = self
+            .run_batch(Self::batch_queries(work.statements()))
+            .await?;
+        Ok(BatchResults::new(rows))
+    }
+}
+
+#[async_trait(?Send)]
+impl DatabaseExt<InsertStatement, ()> for Database {
+    async fn execute(&self, input: InsertStatement) -> DbResult<()> {
+        self.execute_run(input).await?;
+        Ok(())
+    }
+
+    async fn batch(&self, inputs: &[InsertStatement]) -> DbResult<()> {
+        let _ = self.batch_run(inputs).await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<T> DatabaseExt<InsertStatement, Vec<T>> for Database
+where
+    T: DeserializeOwned,
+{
+    async fn execute(&self, input: InsertStatement) -> DbResult<Vec<T>> {
+        let result = self.execute_run(input).await?;
+        rows::<T>(result)
+    }
+
+    async fn batch(&self, inputs: &[InsertStatement]) -> DbResult<Vec<T>> {
+        let results = self.batch_run(inputs).await?;
+
+        let mut all_results = Vec::new();
+        for result in results {
+            all_results.append(&mut rows::<T>(result)?);
+        }
+
+        Ok(all_results)
+    }
+}
+
+#[async_trait(?Send)]
+impl<T> DatabaseExt<SelectStatement, Vec<T>> for Database
+where
+    T: DeserializeOwned,
+{
+    async fn execute(&self, input: SelectStatement) -> DbResult<Vec<T>> {
+        let result = self.execute_run(input).await?;
+        rows::<T>(result)
+    }
+
+    async fn batch(&self, inputs: &[SelectStatement]) -> DbResult<Vec<T>> {
+        let results = self.batch_run(inputs).await?;
+
+        let mut all_results = Vec::new();
+        for result in results {
+            all_results.append(&mut rows::<T>(result)?);
+        }
+
+        Ok(all_results)
+    }
+}
+
+#[async_trait(?Send)]
+impl DatabaseExt<UpdateStatement, ()> for Database {
+    async fn execute(&self, input: UpdateStatement) -> DbResult<()> {
+        let _ = self.execute_run(input).await?;
+        Ok(())
+    }
+
+    async fn batch(&self, inputs: &[UpdateStatement]) -> DbResult<()> {
+        let _ = self.batch_run(inputs).await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<T> DatabaseExt<UpdateStatement, Vec<T>> for Database
+where
+    T: DeserializeOwned,
+{
+    async fn execute(&self, input: UpdateStatement) -> DbResult<Vec<T>> {
+        let result = self.execute_run(input).await?;
+        rows::<T>(result)
+    }
+
+    async fn batch(&self, inputs: &[UpdateStatement]) -> DbResult<Vec<T>> {
+        let results = self.batch_run(inputs).await?;
+
+        let mut all_results = Vec::new();
+        for result in results {
+            all_results.append(&mut rows::<T>(result)?);
+        }
+
+        Ok(all_results)
+    }
+}
+
+#[async_trait(?Send)]
+impl<T> DatabaseExt<DeleteStatement, Vec<T>> for Database
+where
+    T: DeserializeOwned,
+{
+    async fn execute(&self, input: DeleteStatement) -> DbResult<Vec<T>> {
+        let result = (self.execute_run(input).await).map_err(DbError::during_delete)?;
+        rows::<T>(result)
+    }
+
+    async fn batch(&self, inputs: &[DeleteStatement]) -> DbResult<Vec<T>> {
+        let results = (self.batch_run(inputs).await).map_err(DbError::during_delete)?;
+
+        let mut all_results = Vec::new();
+        for result in results {
+            all_results.append(&mut rows::<T>(result)?);
+        }
+
+        Ok(all_results)
+    }
+}
+
+#[async_trait(?Send)]
+impl DatabaseExt<DeleteStatement, ()> for Database {
+    async fn execute(&self, input: DeleteStatement) -> DbResult<()> {
+        let _ = (self.execute_run(input).await).map_err(DbError::during_delete)?;
+        Ok(())
+    }
+
+    async fn batch(&self, inputs: &[DeleteStatement]) -> DbResult<()> {
+        let _ = (self.batch_run(inputs).await).map_err(DbError::during_delete)?;
+        Ok(())
+    }
+}
+
+/// Deserialize the rows returned by the backend, reporting malformed rows as [`DbError::Deserialization`]
+fn rows<T: DeserializeOwned>(values: Vec<serde_json::Value>) -> DbResult<Vec<T>> {
+    (values.into_iter())
+        .map(|value| {
+            serde_json::from_value(value).map_err(|e| DbError::Deserialization(e.to_string()))
+        })
+        .collect()
+}
+
+pub trait IntoQueryStatement {
+    fn into_query_statement(self) -> QueryStatement;
+}
+
+impl IntoQueryStatement for InsertStatement {
+    fn into_query_statement(self) -> QueryStatement {
+        QueryStatement::Insert(self)
+    }
+}
+
+impl IntoQueryStatement for SelectStatement {
+    fn into_query_statement(self) -> QueryStatement {
+        QueryStatement::Select(self)
+    }
+}
+
+impl IntoQueryStatement for UpdateStatement {
+    fn into_query_statement(self) -> QueryStatement {
+        QueryStatement::Update(self)
+    }
+}
+
+impl IntoQueryStatement for DeleteStatement {
+    fn into_query_statement(self) -> QueryStatement {
+        QueryStatement::Delete(self)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QueryBuilder(Vec<QueryStatement>);
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, stmt: impl IntoQueryStatement) -> Self {
+        self.0.push(stmt.into_query_statement());
+        self
+    }
+}
+
+impl From<QueryBuilder> for Vec<QueryStatement> {
+    fn from(builder: QueryBuilder) -> Self {
+        builder.0
+    }
+}
+
+impl From<QueryBuilder> for Vec<InsertStatement> {
+    fn from(builder: QueryBuilder) -> Self {
+        builder
+            .0
+            .into_iter()
+            .filter_map(|stmt| {
+                if let QueryStatement::Insert(insert) = stmt {
+                    Some(insert)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl From<QueryBuilder> for Vec<SelectStatement> {
+    fn from(builder: QueryBuilder) -> Self {
+        builder
+            .0
+            .into_iter()
+            .filter_map(|stmt| {
+                if let QueryStatement::Select(select) = stmt {
+                    Some(select)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl From<QueryBuilder> for Vec<UpdateStatement> {
+    fn from(builder: QueryBuilder) -> Self {
+        builder
+            .0
+            .into_iter()
+            .filter_map(|stmt| {
+                if let QueryStatement::Update(update) = stmt {
+                    Some(update)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl From<QueryBuilder> for Vec<DeleteStatement> {
+    fn from(builder: QueryBuilder) -> Self {
+        builder
+            .0
+            .into_iter()
+            .filter_map(|stmt| {
+                if let QueryStatement::Delete(delete) = stmt {
+                    Some(delete)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Macro to create a Vec<QueryStatement> from mixed statement types
+/// Usage: queries![insert_stmt, select_stmt, update_stmt, delete_stmt]
+/// Macro that creates a QueryBuilder which can convert to different vector types, or into a [`UnitOfWork`]
+#[macro_export]
+macro_rules! queries {
+    () => {
+        $crate::state::database::QueryBuilder::new()
+    };
+    ($($stmt:expr),+ $(,)?) => {
+        {
+            let mut builder = $crate::state::database::QueryBuilder::new();
+            $(
+                builder = builder.push($stmt);
+            )+
+            builder.into()
+        }
+    };
+}
@@ -0,0 +1,258 @@
+use std::marker::PhantomData;
+
+use sea_query::QueryStatement;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{DbError, DbResult, IntoQueryStatement, QueryBuilder};
+
+/// A sequence of statements executed atomically in a single D1 batch
+///
+/// Every statement added returns a [`StatementHandle`] used to read back its own rows,
+/// so two statements of the same kind (e.g. two SELECTs) never overwrite each other.
+///
+/// ```ignore
+/// let mut work = UnitOfWork::new();
+/// let old = work.add::<AfkConfigSchema>(AfkConfigSchema::get(&user_id));
+/// let new = work.add::<AfkConfigSchema>(AfkConfigSchema::insert(&user_id, &None, &None));
+/// let results = database.commit(work).await?;
+/// let (old, new) = (results.first(&old)?, results.first(&new)?);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct UnitOfWork {
+    statements: Vec<QueryStatement>,
+}
+
+/// Typed index of a statement inside a [`UnitOfWork`]
+#[derive(Debug)]
+pub struct StatementHandle<T> {
+    index: usize,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for StatementHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for StatementHandle<T> {}
+
+impl<T> StatementHandle<T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl UnitOfWork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a statement whose rows will be read back as `T`
+    pub fn add<T: DeserializeOwned>(
+        &mut self,
+        stmt: impl IntoQueryStatement,
+    ) -> StatementHandle<T> {
+        self.statements.push(stmt.into_query_statement());
+        StatementHandle {
+            index: self.statements.len() - 1,
+            _row: PhantomData,
+        }
+    }
+
+    /// Queue a statement whose rows are not needed
+    pub fn push(&mut self, stmt: impl IntoQueryStatement) -> &mut Self {
+        self.statements.push(stmt.into_query_statement());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    pub fn statements(&self) -> &[QueryStatement] {
+        &self.statements
+    }
+}
+
+impl From<QueryBuilder> for UnitOfWork {
+    fn from(builder: QueryBuilder) -> Self {
+        Self {
+            statements: builder.into(),
+        }
+    }
+}
+
+impl From<Vec<QueryStatement>> for UnitOfWork {
+    fn from(statements: Vec<QueryStatement>) -> Self {
+        Self { statements }
+    }
+}
+
+/// Rows returned by every statement of a committed [`UnitOfWork`], in statement order
+#[derive(Debug, Clone, Default)]
+pub struct BatchResults {
+    rows: Vec<Vec<Value>>,
+}
+
+impl BatchResults {
+    pub(super) fn new(rows: Vec<Vec<Value>>) -> Self {
+        Self { rows }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// All rows returned by the statement behind `handle`
+    pub fn get<T: DeserializeOwned>(&self, handle: &StatementHandle<T>) -> DbResult<Vec<T>> {
+        self.get_at(handle.index)
+    }
+
+    /// The first row returned by the statement behind `handle`, if any
+    pub fn first<T: DeserializeOwned>(&self, handle: &StatementHandle<T>) -> DbResult<Option<T>> {
+        Ok(self.get(handle)?.into_iter().next())
+    }
+
+    /// All rows returned by the statement at `index`, for callers without a handle
+    pub fn get_at<T: DeserializeOwned>(&self, index: usize) -> DbResult<Vec<T>> {
+        let Some(rows) = self.rows.get(index) else {
+            return Err(DbError::Deserialization(format!(
+                "No result for statement {} in a batch of {}",
+                index,
+                self.rows.len()
+            )));
+        };
+        (rows.iter().cloned())
+            .map(|row| {
+                serde_json::from_value(row).map_err(|e| DbError::Deserialization(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Convert the whole batch into a tuple, one element per statement
+    ///
+    /// `let (old, new): (Option<AfkConfigSchema>, Vec<AfkConfigSchema>) = results.into_typed()?;`
+    pub fn into_typed<R: FromBatchResults>(self) -> DbResult<R> {
+        R::from_batch(&self)
+    }
+}
+
+/// How the rows of a single statement are read back when converting a batch into a tuple
+pub trait FromStatementRows: Sized {
+    fn from_rows(results: &BatchResults, index: usize) -> DbResult<Self>;
+}
+
+impl<T: DeserializeOwned> FromStatementRows for Vec<T> {
+    fn from_rows(results: &BatchResults, index: usize) -> DbResult<Self> {
+        results.get_at(index)
+    }
+}
+
+impl<T: DeserializeOwned> FromStatementRows for Option<T> {
+    fn from_rows(results: &BatchResults, index: usize) -> DbResult<Self> {
+        Ok(results.get_at::<T>(index)?.into_iter().next())
+    }
+}
+
+impl FromStatementRows for () {
+    fn from_rows(_: &BatchResults, _: usize) -> DbResult<Self> {
+        Ok(())
+    }
+}
+
+pub trait FromBatchResults: Sized {
+    fn from_batch(results: &BatchResults) -> DbResult<Self>;
+}
+
+macro_rules! impl_from_batch_results {
+    ($($name:ident),+) => {
+        impl<$($name: FromStatementRows),+> FromBatchResults for ($($name,)+) {
+            fn from_batch(results: &BatchResults) -> DbResult<Self> {
+                let expected = $crate::count!($($name)+);
+                if results.len() != expected {
+                    return Err(DbError::Deserialization(format!(
+                        "Expected {} statement results, got {}",
+                        expected,
+                        results.len()
+                    )));
+                }
+                let mut indices = 0..expected;
+                let mut next_index = || {
+                    indices.next().ok_or_else(|| {
+                        DbError::Deserialization(format!(
+                            "No statement index left in a batch of {}",
+                            expected
+                        ))
+                    })
+                };
+                Ok(($(
+                    $name::from_rows(results, next_index()?)?,
+                )+))
+            }
+        }
+    };
+}
+
+impl_from_batch_results!(A);
+impl_from_batch_results!(A, B);
+impl_from_batch_results!(A, B, C);
+impl_from_batch_results!(A, B, C, D);
+impl_from_batch_results!(A, B, C, D, E);
+impl_from_batch_results!(A, B, C, D, E, F);
+impl_from_batch_results!(A, B, C, D, E, F, G);
+impl_from_batch_results!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn results() -> BatchResults {
+        BatchResults::new(vec![
+            vec![json!({ "id": 1 }), json!({ "id": 2 })],
+            vec![],
+            vec![json!({ "id": 3 })],
+        ])
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Row {
+        id: i64,
+    }
+
+    #[test]
+    fn into_typed_reads_each_statement_in_order() {
+        let (all, none, first): (Vec<Row>, Option<Row>, Option<Row>) =
+            results().into_typed().unwrap();
+        assert_eq!(all, vec![Row { id: 1 }, Row { id: 2 }]);
+        assert_eq!(none, None);
+        assert_eq!(first, Some(Row { id: 3 }));
+    }
+
+    #[test]
+    fn into_typed_rejects_a_different_statement_count() {
+        let typed: DbResult<(Vec<Row>, Vec<Row>)> = results().into_typed();
+        assert!(matches!(typed, Err(DbError::Deserialization(_))));
+        let typed: DbResult<(Vec<Row>, (), (), ())> = results().into_typed();
+        assert!(matches!(typed, Err(DbError::Deserialization(_))));
+    }
+
+    #[test]
+    fn get_at_past_the_batch_is_an_error() {
+        assert!(matches!(
+            results().get_at::<Row>(3),
+            Err(DbError::Deserialization(_))
+        ));
+    }
+}
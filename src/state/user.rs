@@ -31,6 +31,9 @@ impl RequestedUser {
 #[derive(Debug, Clone)]
 pub struct User {
     access_token: String,
+    /// Id of the session the request was made with, see `services::session`
+    session_id: String,
+    user_id: String,
 }
 
 #[derive(Debug, Clone)]
@@ -39,12 +42,22 @@ pub struct Bot {
 }
 
 impl User {
-    pub fn new(access_token: String) -> Self {
-        Self { access_token }
+    pub fn new(access_token: String, session_id: String, user_id: String) -> Self {
+        Self {
+            access_token,
+            session_id,
+            user_id,
+        }
     }
     pub fn access_token(&self) -> &str {
         &self.access_token
     }
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
 }
 
 impl Bot {
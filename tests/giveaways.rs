@@ -0,0 +1,224 @@
+use backend::{
+    schema::{
+        ShardSchema,
+        guild::{GiveawaysSchema, GuildSchema},
+        user::{GiveawayEntriesSchema, GiveawayWinnersSchema},
+    },
+    services::giveaways::{end_giveaway, reroll_giveaway},
+    state::database::{Database, DatabaseExt, SqliteBackend, UnitOfWork},
+};
+use chrono::Utc;
+
+const GIVEAWAY: &str = "100";
+
+async fn database_with_giveaway(winners_count: i32, entrants: &[&str]) -> Database {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let _: () = database.execute(ShardSchema::new_schema(0)).await.unwrap();
+    let _: () = database.execute(GuildSchema::insert("1", 0)).await.unwrap();
+    let _: Vec<GiveawaysSchema> = database
+        .execute(GiveawaysSchema::insert(
+            &GIVEAWAY.to_string(),
+            &"1".to_string(),
+            &"2".to_string(),
+            &"3".to_string(),
+            &"Nitro".to_string(),
+            &Utc::now(),
+            winners_count,
+            &"4".to_string(),
+        ))
+        .await
+        .unwrap();
+    for user_id in entrants {
+        let _: () = database
+            .execute(GiveawayEntriesSchema::insert(GIVEAWAY, user_id))
+            .await
+            .unwrap();
+    }
+    database
+}
+
+async fn eligible(database: &Database) -> Vec<String> {
+    let entries: Vec<GiveawayEntriesSchema> = database
+        .execute(GiveawayEntriesSchema::get_eligible(GIVEAWAY))
+        .await
+        .unwrap();
+    let mut users: Vec<_> = entries.into_iter().map(|e| e.user_id).collect();
+    users.sort();
+    users
+}
+
+async fn winners(database: &Database) -> Vec<(String, i64)> {
+    let winners: Vec<GiveawayWinnersSchema> = database
+        .execute(GiveawayWinnersSchema::get_winners(GIVEAWAY))
+        .await
+        .unwrap();
+    let mut winners: Vec<_> = winners.into_iter().map(|w| (w.user_id, w.round)).collect();
+    winners.sort();
+    winners
+}
+
+fn ids(users: &[&str]) -> Vec<String> {
+    users.iter().map(|user| user.to_string()).collect()
+}
+
+#[tokio::test]
+async fn eligible_entrants_exclude_earlier_winners() {
+    let database = database_with_giveaway(1, &["10", "11", "12"]).await;
+    assert_eq!(eligible(&database).await, ids(&["10", "11", "12"]));
+
+    let _: () = database
+        .execute(GiveawayWinnersSchema::insert_first_round(
+            GIVEAWAY,
+            &ids(&["11"]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(eligible(&database).await, ids(&["10", "12"]));
+}
+
+#[tokio::test]
+async fn next_round_winners_share_a_new_round() {
+    let database = database_with_giveaway(1, &["10", "11", "12", "13"]).await;
+    let first: Vec<GiveawayWinnersSchema> = database
+        .execute(GiveawayWinnersSchema::insert_next_round(
+            GIVEAWAY,
+            &ids(&["10"]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].round, 0);
+
+    let second: Vec<GiveawayWinnersSchema> = database
+        .execute(GiveawayWinnersSchema::insert_next_round(
+            GIVEAWAY,
+            &ids(&["11", "12"]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(second.len(), 2);
+    assert!(second.iter().all(|winner| winner.round == 1));
+
+    assert_eq!(
+        winners(&database).await,
+        [("10".into(), 0), ("11".into(), 1), ("12".into(), 1)]
+    );
+}
+
+#[tokio::test]
+async fn next_round_skips_earlier_winners_and_strangers() {
+    let database = database_with_giveaway(1, &["10", "11"]).await;
+    let _: () = database
+        .execute(GiveawayWinnersSchema::insert_first_round(
+            GIVEAWAY,
+            &ids(&["10"]),
+        ))
+        .await
+        .unwrap();
+
+    // "10" won concurrently and "99" never entered
+    let inserted: Vec<GiveawayWinnersSchema> = database
+        .execute(GiveawayWinnersSchema::insert_next_round(
+            GIVEAWAY,
+            &ids(&["10", "11", "99"]),
+        ))
+        .await
+        .unwrap();
+    let users: Vec<_> = inserted
+        .iter()
+        .map(|w| (w.user_id.as_str(), w.round))
+        .collect();
+    assert_eq!(users, [("11", 1)]);
+}
+
+#[tokio::test]
+async fn end_then_reroll_draws_new_winners() {
+    let database = database_with_giveaway(2, &["10", "11", "12"]).await;
+    let giveaway_id = GIVEAWAY.to_string();
+
+    let ended = end_giveaway(&database, &giveaway_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((ended.winners.len(), ended.round), (2, 0));
+    assert!(
+        end_giveaway(&database, &giveaway_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let rerolled = reroll_giveaway(&database, &giveaway_id, 5)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(rerolled.round, 1);
+    assert_eq!(rerolled.winners.len(), 1);
+    assert!(!ended.winners.contains(&rerolled.winners[0]));
+    assert!(eligible(&database).await.is_empty());
+
+    let empty = reroll_giveaway(&database, &giveaway_id, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(empty.winners.is_empty());
+    assert_eq!(winners(&database).await.len(), 3);
+}
+
+#[tokio::test]
+async fn ending_twice_stores_one_set_of_winners() {
+    let database = database_with_giveaway(2, &["10", "11", "12"]).await;
+    let giveaway_id = GIVEAWAY.to_string();
+    let (first, second) = tokio::join!(
+        end_giveaway(&database, &giveaway_id),
+        end_giveaway(&database, &giveaway_id)
+    );
+    let ended: Vec<_> = [first.unwrap(), second.unwrap()]
+        .into_iter()
+        .flatten()
+        .collect();
+    assert_eq!(ended.len(), 1);
+
+    let mut announced = ended[0].winners.clone();
+    announced.sort();
+    let stored = winners(&database).await;
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|(_, round)| *round == 0));
+    assert_eq!(
+        stored.into_iter().map(|(user, _)| user).collect::<Vec<_>>(),
+        announced
+    );
+}
+
+#[tokio::test]
+async fn a_late_end_stores_no_winners() {
+    let database = database_with_giveaway(1, &["10", "11"]).await;
+    let giveaway_id = GIVEAWAY.to_string();
+    let ended = end_giveaway(&database, &giveaway_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The batch of a call that drew its winners before the giveaway ended
+    let mut work = UnitOfWork::new();
+    let inserted = work.add::<GiveawayWinnersSchema>(GiveawayWinnersSchema::insert_first_round(
+        GIVEAWAY,
+        &ids(&["10", "11"]),
+    ));
+    let late = work.add::<GiveawaysSchema>(GiveawaysSchema::mark_ended(&giveaway_id));
+    let results = database.commit(work).await.unwrap();
+    assert!(results.get(&inserted).unwrap().is_empty());
+    assert!(results.first(&late).unwrap().is_none());
+
+    assert_eq!(winners(&database).await, [(ended.winners[0].clone(), 0)]);
+}
+
+#[tokio::test]
+async fn running_giveaways_cannot_be_rerolled() {
+    let database = database_with_giveaway(1, &["10"]).await;
+    let rerolled = reroll_giveaway(&database, &GIVEAWAY.to_string(), 1)
+        .await
+        .unwrap();
+    assert!(rerolled.is_none());
+    assert!(winners(&database).await.is_empty());
+}
@@ -0,0 +1,62 @@
+use backend::{
+    schema::SCHEMA_VERSION,
+    state::database::{Database, DatabaseExt, DbError, MIGRATIONS, SqliteBackend},
+};
+use sea_query::{Alias, Expr, Query};
+
+#[tokio::test]
+async fn migrations_apply_to_a_fresh_database() {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let status = database.migration_status().await.unwrap();
+    assert!(status.is_up_to_date());
+    assert_eq!(status.current_version, SCHEMA_VERSION);
+}
+
+#[tokio::test]
+async fn migrations_apply_to_a_schema_built_database() {
+    let database = Database::with_backend(SqliteBackend::with_schemas().unwrap());
+    let status = database.apply_migrations().await.unwrap();
+    assert!(status.is_up_to_date());
+    assert_eq!(status.current_version, SCHEMA_VERSION);
+}
+
+#[tokio::test]
+async fn applying_migrations_twice_is_a_no_op() {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let status = database.apply_migrations().await.unwrap();
+    assert!(status.pending.is_empty());
+    assert_eq!(status.applied.len(), status.current_version as usize);
+}
+
+#[tokio::test]
+async fn status_of_an_empty_database_leaves_it_untouched() {
+    let backend = SqliteBackend::open_in_memory().unwrap();
+    let database = Database::with_backend(backend);
+    let status = database.migration_status().await.unwrap();
+    assert_eq!(status.current_version, 0);
+    assert_eq!(status.pending.len(), MIGRATIONS.len());
+
+    let tables: Vec<serde_json::Value> = database
+        .execute(
+            Query::select()
+                .column(Alias::new("name"))
+                .from(Alias::new("sqlite_master"))
+                .and_where(Expr::col(Alias::new("name")).eq("schema_migrations"))
+                .to_owned(),
+        )
+        .await
+        .unwrap();
+    assert!(tables.is_empty());
+}
+
+#[tokio::test]
+async fn schema_check_fails_closed_until_migrated() {
+    let database = Database::with_backend(SqliteBackend::with_schemas().unwrap());
+    assert!(matches!(
+        database.check_schema_version().await,
+        Err(DbError::Migration(_))
+    ));
+
+    database.apply_migrations().await.unwrap();
+    database.check_schema_version().await.unwrap();
+}
@@ -0,0 +1,411 @@
+use backend::{
+    schema::{
+        AfkStatusSchema, ShardSchema,
+        guild::{BirthdayConfigsSchema, GuildSchema, MemberSchema, PrefixSchema, SettingsBody},
+        user::{BirthdaySchema, UserSessionSchema},
+    },
+    services::birthdays::announce,
+    services::streaming::{StreamableSchema, decode_cursor, encode_cursor},
+    state::database::{Database, DatabaseExt, SqliteBackend},
+};
+use chrono::NaiveDate;
+
+async fn database_with_guild(guild_id: &str) -> Database {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let _: () = database.execute(ShardSchema::new_schema(0)).await.unwrap();
+    let _: () = database
+        .execute(GuildSchema::insert(guild_id, 0))
+        .await
+        .unwrap();
+    database
+}
+
+async fn get_guild(database: &Database, guild_id: &str) -> Option<GuildSchema> {
+    let guilds: Vec<GuildSchema> = database
+        .execute(GuildSchema::get_by_id(guild_id))
+        .await
+        .unwrap();
+    guilds.into_iter().next()
+}
+
+async fn get_settings(database: &Database, guild_id: &str) -> Option<SettingsBody> {
+    let settings: Vec<SettingsBody> = database.execute(SettingsBody::get(guild_id)).await.unwrap();
+    settings.into_iter().next()
+}
+
+#[tokio::test]
+async fn guild_insert_and_toggle() {
+    let database = database_with_guild("1").await;
+    let guild = get_guild(&database, "1").await.unwrap();
+    assert!(guild.enabled);
+    assert_eq!(guild.shard_id, 0);
+
+    let _: () = database
+        .execute(GuildSchema::toggle("1", false))
+        .await
+        .unwrap();
+    assert!(!get_guild(&database, "1").await.unwrap().enabled);
+}
+
+#[tokio::test]
+async fn soft_deleted_guild_is_hidden_until_restored() {
+    let database = database_with_guild("1").await;
+    let _: () = database
+        .execute(GuildSchema::soft_delete("1"))
+        .await
+        .unwrap();
+    assert!(get_guild(&database, "1").await.is_none());
+
+    let _: () = database.execute(GuildSchema::insert("1", 0)).await.unwrap();
+    let guild = get_guild(&database, "1").await.unwrap();
+    assert!(guild.enabled);
+}
+
+#[tokio::test]
+async fn settings_round_trip() {
+    let database = database_with_guild("1").await;
+    let body = SettingsBody {
+        prefix: Some("!".into()),
+        language: Some("english".into()),
+        timezone: Some("Europe/Paris".into()),
+        colour: Some("#ff0000".into()),
+    };
+    let _: () = database
+        .batch(&SettingsBody::set("1", &body))
+        .await
+        .unwrap();
+
+    let settings = get_settings(&database, "1").await.unwrap();
+    assert_eq!(settings.prefix.as_deref(), Some("!"));
+    assert_eq!(settings.language.as_deref(), Some("english"));
+    assert_eq!(settings.timezone.as_deref(), Some("Europe/Paris"));
+    assert_eq!(settings.colour.as_deref(), Some("#ff0000"));
+
+    let cleared = SettingsBody {
+        prefix: Some("!".into()),
+        language: None,
+        timezone: None,
+        colour: None,
+    };
+    let _: () = database
+        .batch(&SettingsBody::delete("1", &cleared))
+        .await
+        .unwrap();
+    let settings = get_settings(&database, "1").await.unwrap();
+    assert_eq!(settings.prefix.as_deref(), Some("!"));
+    assert_eq!(settings.language, None);
+    assert_eq!(settings.timezone, None);
+    assert_eq!(settings.colour, None);
+}
+
+#[tokio::test]
+async fn settings_of_a_guild_without_a_prefix() {
+    let database = database_with_guild("1").await;
+    let settings = get_settings(&database, "1").await.unwrap();
+    assert_eq!(settings.prefix, None);
+    assert_eq!(settings.language, None);
+
+    let body = SettingsBody {
+        prefix: None,
+        language: Some("français".into()),
+        timezone: None,
+        colour: None,
+    };
+    let _: () = database
+        .batch(&SettingsBody::set("1", &body))
+        .await
+        .unwrap();
+    let settings = get_settings(&database, "1").await.unwrap();
+    assert_eq!(settings.prefix, None);
+    assert_eq!(settings.language.as_deref(), Some("français"));
+}
+
+#[tokio::test]
+async fn settings_of_an_unknown_or_deleted_guild() {
+    let database = database_with_guild("1").await;
+    assert!(get_settings(&database, "2").await.is_none());
+
+    let _: () = database
+        .execute(GuildSchema::soft_delete("1"))
+        .await
+        .unwrap();
+    assert!(get_settings(&database, "1").await.is_none());
+}
+
+#[tokio::test]
+async fn prefix_set_get_and_delete() {
+    let database = database_with_guild("1").await;
+    for prefix in ["!", "?"] {
+        let _: () = database
+            .execute(PrefixSchema::insert("1", prefix))
+            .await
+            .unwrap();
+    }
+    let prefixes: Vec<PrefixSchema> = database.execute(PrefixSchema::get("1")).await.unwrap();
+    assert_eq!(prefixes.len(), 1);
+    assert_eq!(
+        (prefixes[0].guild_id.as_str(), prefixes[0].prefix.as_str()),
+        ("1", "?")
+    );
+
+    let _: () = database.execute(PrefixSchema::delete("1")).await.unwrap();
+    let prefixes: Vec<PrefixSchema> = database.execute(PrefixSchema::get("1")).await.unwrap();
+    assert!(prefixes.is_empty());
+}
+
+#[tokio::test]
+async fn settings_reject_an_unknown_language() {
+    let database = database_with_guild("1").await;
+    let body = SettingsBody {
+        prefix: None,
+        language: Some("klingon".into()),
+        timezone: None,
+        colour: None,
+    };
+    let result: Result<(), _> = database.batch(&SettingsBody::set("1", &body)).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn birthday_insert_or_update() {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let _: () = database
+        .execute(BirthdaySchema::insert_or_update("10", 14, 3, None))
+        .await
+        .unwrap();
+    let _: () = database
+        .execute(BirthdaySchema::insert_or_update("10", 15, 3, Some(1990)))
+        .await
+        .unwrap();
+
+    let birthdays: Vec<BirthdaySchema> = database
+        .execute(BirthdaySchema::get_birthday("10"))
+        .await
+        .unwrap();
+    assert_eq!(birthdays.len(), 1);
+    assert_eq!((birthdays[0].day, birthdays[0].month), (15, 3));
+    assert_eq!(birthdays[0].year, Some(1990));
+}
+
+#[tokio::test]
+async fn guild_birthdays_only_include_members() {
+    let database = database_with_guild("1").await;
+    for (user_id, day, month) in [("10", 1, 6), ("11", 1, 6), ("12", 2, 6)] {
+        let _: () = database
+            .execute(BirthdaySchema::insert_or_update(user_id, day, month, None))
+            .await
+            .unwrap();
+    }
+    for user_id in ["10", "12"] {
+        let _: () = database
+            .execute(MemberSchema::insert("1", user_id))
+            .await
+            .unwrap();
+    }
+
+    let june_first = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+    let birthdays: Vec<BirthdaySchema> = database
+        .execute(BirthdaySchema::get_guild_birthdays_on("1", june_first))
+        .await
+        .unwrap();
+    let users: Vec<_> = birthdays.iter().map(|b| b.user_id.as_str()).collect();
+    assert_eq!(users, ["10"]);
+}
+
+async fn birthdays_on(database: &Database, date: NaiveDate) -> Vec<(u8, u8)> {
+    let birthdays: Vec<BirthdaySchema> = database
+        .execute(BirthdaySchema::get_guild_birthdays_on("1", date))
+        .await
+        .unwrap();
+    birthdays.iter().map(|b| (b.month, b.day)).collect()
+}
+
+#[tokio::test]
+async fn leap_day_birthdays_move_to_february_28() {
+    let database = database_with_guild("1").await;
+    for (user_id, day, month) in [("10", 28, 2), ("11", 29, 2), ("12", 1, 3)] {
+        let _: () = database
+            .execute(BirthdaySchema::insert_or_update(user_id, day, month, None))
+            .await
+            .unwrap();
+        let _: () = database
+            .execute(MemberSchema::insert("1", user_id))
+            .await
+            .unwrap();
+    }
+
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    assert_eq!(
+        birthdays_on(&database, date(2025, 2, 28)).await,
+        [(2, 28), (2, 29)]
+    );
+    assert_eq!(birthdays_on(&database, date(2025, 3, 1)).await, [(3, 1)]);
+    assert_eq!(birthdays_on(&database, date(2024, 2, 28)).await, [(2, 28)]);
+    assert_eq!(birthdays_on(&database, date(2024, 2, 29)).await, [(2, 29)]);
+    assert_eq!(birthdays_on(&database, date(2024, 3, 1)).await, [(3, 1)]);
+}
+
+#[tokio::test]
+async fn birthdays_are_announced_once_a_day() {
+    let database = database_with_guild("1").await;
+    for (user_id, year) in [("10", Some(2000)), ("11", None)] {
+        let _: () = database
+            .execute(BirthdaySchema::insert_or_update(user_id, 1, 6, year))
+            .await
+            .unwrap();
+        let _: () = database
+            .execute(MemberSchema::insert("1", user_id))
+            .await
+            .unwrap();
+    }
+    let configs: Vec<BirthdayConfigsSchema> = database
+        .execute(BirthdayConfigsSchema::insert_or_update(
+            &"1".to_string(),
+            None,
+            Some("{user} is {age}".into()),
+        ))
+        .await
+        .unwrap();
+
+    let june_first = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+    let payload = announce(&database, &configs[0], june_first)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.date, "2025-06-01");
+    let messages: Vec<_> = (payload.birthdays.iter())
+        .map(|b| (b.user_id.as_str(), b.age, b.message.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        [("10", Some(25), "<@10> is 25"), ("11", None, "<@11> is ")]
+    );
+
+    let again = announce(&database, &configs[0], june_first).await.unwrap();
+    assert!(again.is_none());
+}
+
+#[tokio::test]
+async fn afk_stream_resumes_after_a_global_status() {
+    let database = database_with_guild("1").await;
+    for (user_id, guild_id) in [("10", None), ("10", Some("1")), ("11", None)] {
+        let guild_id = guild_id.map(String::from);
+        let _: () = database
+            .execute(AfkStatusSchema::insert(user_id, &guild_id, ""))
+            .await
+            .unwrap();
+    }
+
+    let mut after = None;
+    let mut streamed = Vec::new();
+    loop {
+        let page: Vec<AfkStatusSchema> = database
+            .execute(AfkStatusSchema::batch_after(1, after.as_ref()))
+            .await
+            .unwrap();
+        let Some(last) = page.last() else { break };
+        // Cursors go through the `?after=` query string between pages
+        after = Some(decode_cursor(&encode_cursor(&last.cursor())).unwrap());
+        streamed.extend(page.into_iter().map(|s| (s.user_id, s.guild_id)));
+    }
+    assert_eq!(
+        streamed,
+        [
+            ("10".to_string(), None),
+            ("10".to_string(), Some("1".to_string())),
+            ("11".to_string(), None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn afk_status_is_returned_and_replaced() {
+    let database = database_with_guild("1").await;
+    let guild_id = Some("1".to_string());
+    for reason in ["lunch", "dinner"] {
+        let set: Vec<AfkStatusSchema> = database
+            .execute(AfkStatusSchema::insert("10", &guild_id, reason))
+            .await
+            .unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set[0].reason, reason);
+    }
+    let statuses: Vec<AfkStatusSchema> =
+        database.execute(AfkStatusSchema::get("10")).await.unwrap();
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses[0].reason, "dinner");
+}
+
+fn session(refresh_token: &str, access_expires_at: &str) -> UserSessionSchema {
+    UserSessionSchema {
+        id: "abc".into(),
+        user_id: "10".into(),
+        access_token: "access".into(),
+        refresh_token: refresh_token.into(),
+        access_expires_at: access_expires_at.into(),
+        expires_at: "2099-01-01T00:00:00Z".into(),
+        user_agent: None,
+        last_used_at: "2025-06-01T00:00:00Z".into(),
+        created_at: String::new(),
+    }
+}
+
+async fn refresh(
+    database: &Database,
+    previous: &UserSessionSchema,
+    refresh_token: &str,
+) -> Vec<UserSessionSchema> {
+    database
+        .execute(UserSessionSchema::update_tokens(
+            previous,
+            "new access".into(),
+            refresh_token.into(),
+            "2025-06-08T00:00:00Z".into(),
+            "2025-06-01T00:00:00Z",
+        ))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn only_the_first_concurrent_refresh_is_kept() {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let stale = session("old", "2025-06-01T00:00:00Z");
+    let _: () = database.execute(stale.upsert()).await.unwrap();
+
+    let first = refresh(&database, &stale, "first").await;
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].refresh_token, "first");
+    assert!(refresh(&database, &stale, "second").await.is_empty());
+
+    let sessions: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::get_session(
+            "abc",
+            "2025-06-01T00:00:00Z",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(sessions[0].refresh_token, "first");
+    assert_eq!(sessions[0].access_expires_at, "2025-06-08T00:00:00Z");
+}
+
+#[tokio::test]
+async fn refreshed_sessions_are_not_ended_as_stale() {
+    let database = SqliteBackend::migrated().await.unwrap();
+    let stale = session("old", "2025-06-01T00:00:00Z");
+    let _: () = database.execute(stale.upsert()).await.unwrap();
+    refresh(&database, &stale, "new").await;
+
+    let deleted: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::delete_stale_session(&stale))
+        .await
+        .unwrap();
+    assert!(deleted.is_empty());
+
+    let current = session("new", "2025-06-08T00:00:00Z");
+    let deleted: Vec<UserSessionSchema> = database
+        .execute(UserSessionSchema::delete_stale_session(&current))
+        .await
+        .unwrap();
+    assert_eq!(deleted.len(), 1);
+}